@@ -0,0 +1,92 @@
+//! Compares the decoder with the legacy one, which clones the type of each value.
+//! Run with `cargo bench -p candid --bench decode`.
+
+use candid::parser::value::IDLValue;
+use candid::{CandidType, Deserialize, Encode};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+#[allow(clippy::all)]
+mod legacy;
+
+#[derive(CandidType, Deserialize)]
+struct Row {
+    id: u64,
+    name: String,
+    score: f64,
+    tags: Vec<String>,
+    active: bool,
+    parent: Option<u32>,
+    kind: Kind,
+}
+
+#[derive(CandidType, Deserialize)]
+enum Kind {
+    File,
+    Dir { entries: u32 },
+}
+
+fn rows(n: u64) -> Vec<Row> {
+    (0..n)
+        .map(|i| Row {
+            id: i,
+            name: format!("row {}", i),
+            score: i as f64 / 3.0,
+            tags: vec!["a".to_string(), "bc".to_string()],
+            active: i % 2 == 0,
+            parent: if i % 3 == 0 { None } else { Some(i as u32 / 3) },
+            kind: if i % 5 == 0 {
+                Kind::Dir { entries: i as u32 }
+            } else {
+                Kind::File
+            },
+        })
+        .collect()
+}
+
+fn vec_record(c: &mut Criterion) {
+    let mut group = c.benchmark_group("vec record");
+    for n in [1_000, 100_000].iter() {
+        let bytes = Encode!(&rows(*n)).unwrap();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::new("legacy", n), &bytes, |b, bytes| {
+            b.iter(|| {
+                let mut de = legacy::IDLDeserialize::new(bytes).unwrap();
+                let rows: Vec<Row> = de.get_value().unwrap();
+                de.done().unwrap();
+                rows
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("graph", n), &bytes, |b, bytes| {
+            b.iter(|| {
+                let mut de = candid::de::IDLDeserialize::new(bytes).unwrap();
+                let rows: Vec<Row> = de.get_value().unwrap();
+                de.done().unwrap();
+                rows
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("legacy value", n), &bytes, |b, bytes| {
+            b.iter(|| {
+                let mut de = legacy::IDLDeserialize::new(bytes).unwrap();
+                let value: IDLValue = de.get_value().unwrap();
+                de.done().unwrap();
+                value
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("graph value", n), &bytes, |b, bytes| {
+            b.iter(|| {
+                let mut de = candid::de::IDLDeserialize::new(bytes).unwrap();
+                let value: IDLValue = de.get_value().unwrap();
+                de.done().unwrap();
+                value
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = vec_record
+}
+criterion_main!(benches);
@@ -1,3 +1,4 @@
+use crate::parser::types::{DocComments, DocCursor, DocStep};
 use crate::parser::typing::TypeEnv;
 use crate::pretty::*;
 use crate::types::{Field, Function, Label, Type};
@@ -18,7 +19,7 @@ fn is_tuple(t: &Type) -> bool {
     }
 }
 
-static KEYWORDS: [&str; 29] = [
+pub static KEYWORDS: [&str; 29] = [
     "import",
     "service",
     "func",
@@ -85,6 +86,10 @@ fn pp_text(id: &str) -> RcDoc {
 }
 
 pub fn pp_ty(ty: &Type) -> RcDoc {
+    pp_ty_with_docs(ty, &DocCursor::none())
+}
+
+fn pp_ty_with_docs<'a>(ty: &'a Type, docs: &DocCursor<'a>) -> RcDoc<'a> {
     use Type::*;
     match *ty {
         Null => str("null"),
@@ -106,19 +111,25 @@ pub fn pp_ty(ty: &Type) -> RcDoc {
         Empty => str("empty"),
         Var(ref s) => str(s),
         Principal => str("principal"),
-        Opt(ref t) => kwd("opt").append(pp_ty(t)),
-        Vec(ref t) => kwd("vec").append(pp_ty(t)),
+        Opt(ref t) => kwd("opt").append(pp_ty_with_docs(t, docs)),
+        Vec(ref t) => kwd("vec").append(pp_ty_with_docs(t, docs)),
         Record(ref fs) => {
             if is_tuple(ty) {
-                let tuple = concat(fs.iter().map(|f| pp_ty(&f.ty)), ";");
+                let tuple = concat(
+                    fs.iter().map(|f| {
+                        let docs = docs.step(DocStep::Field(f.id.get_id()));
+                        pp_docs(docs.docs()).append(pp_ty_with_docs(&f.ty, &docs))
+                    }),
+                    ";",
+                );
                 kwd("record").append(enclose_space("{", tuple, "}"))
             } else {
-                kwd("record").append(pp_fields(fs, false))
+                kwd("record").append(pp_fields(fs, false, docs))
             }
         }
-        Variant(ref fs) => kwd("variant").append(pp_fields(fs, true)),
-        Func(ref func) => kwd("func").append(pp_function(func)),
-        Service(ref serv) => kwd("service").append(pp_service(serv)),
+        Variant(ref fs) => kwd("variant").append(pp_fields(fs, true, docs)),
+        Func(ref func) => kwd("func").append(pp_function(func, docs)),
+        Service(ref serv) => kwd("service").append(pp_service(serv, docs)),
         _ => unreachable!(),
     }
 }
@@ -130,23 +141,41 @@ pub fn pp_label(id: &Label) -> RcDoc {
     }
 }
 
-fn pp_field(field: &Field, is_variant: bool) -> RcDoc {
+fn pp_docs(docs: &[String]) -> RcDoc<'_> {
+    lines(docs.iter().map(|line| {
+        if line.is_empty() {
+            str("///")
+        } else {
+            str("/// ").append(line.as_str())
+        }
+    }))
+}
+
+fn pp_field<'a>(field: &'a Field, is_variant: bool, docs: &DocCursor<'a>) -> RcDoc<'a> {
     let ty_doc = if is_variant && field.ty == Type::Null {
         RcDoc::nil()
     } else {
-        kwd(" :").append(pp_ty(&field.ty))
+        kwd(" :").append(pp_ty_with_docs(&field.ty, docs))
     };
-    pp_label(&field.id).append(ty_doc)
+    pp_docs(docs.docs())
+        .append(pp_label(&field.id))
+        .append(ty_doc)
 }
 
-fn pp_fields(fs: &[Field], is_variant: bool) -> RcDoc {
-    let fields = concat(fs.iter().map(|f| pp_field(f, is_variant)), ";");
+fn pp_fields<'a>(fs: &'a [Field], is_variant: bool, docs: &DocCursor<'a>) -> RcDoc<'a> {
+    let fields = concat(
+        fs.iter().map(|f| {
+            let docs = docs.step(DocStep::Field(f.id.get_id()));
+            pp_field(f, is_variant, &docs)
+        }),
+        ";",
+    );
     enclose_space("{", fields, "}")
 }
 
-fn pp_function(func: &Function) -> RcDoc {
-    let args = pp_args(&func.args);
-    let rets = pp_args(&func.rets);
+fn pp_function<'a>(func: &'a Function, docs: &DocCursor<'a>) -> RcDoc<'a> {
+    let args = pp_args(&func.args, docs, DocStep::Arg);
+    let rets = pp_args(&func.rets, docs, DocStep::Ret);
     let modes = pp_modes(&func.modes);
     args.append(" ->")
         .append(RcDoc::space())
@@ -154,8 +183,13 @@ fn pp_function(func: &Function) -> RcDoc {
         .nest(INDENT_SPACE)
 }
 
-fn pp_args(args: &[Type]) -> RcDoc {
-    let doc = concat(args.iter().map(pp_ty), ",");
+fn pp_args<'a>(args: &'a [Type], docs: &DocCursor<'a>, step: fn(usize) -> DocStep) -> RcDoc<'a> {
+    let doc = concat(
+        args.iter()
+            .enumerate()
+            .map(|(i, t)| pp_ty_with_docs(t, &docs.step(step(i)))),
+        ",",
+    );
     enclose("(", doc, ")")
 }
 
@@ -163,34 +197,39 @@ fn pp_modes(modes: &[crate::parser::types::FuncMode]) -> RcDoc {
     RcDoc::concat(modes.iter().map(|m| RcDoc::space().append(m.to_doc())))
 }
 
-fn pp_service(serv: &[(String, Type)]) -> RcDoc {
+fn pp_service<'a>(serv: &'a [(String, Type)], docs: &DocCursor<'a>) -> RcDoc<'a> {
     let doc = concat(
         serv.iter().map(|(id, func)| {
+            let docs = docs.step(DocStep::Field(crate::idl_hash(id)));
             let func_doc = match func {
-                Type::Func(ref f) => pp_function(f),
+                Type::Func(ref f) => pp_function(f, &docs),
                 Type::Var(_) => pp_ty(func),
                 _ => unreachable!(),
             };
-            pp_text(id).append(kwd(" :")).append(func_doc)
+            pp_docs(docs.docs())
+                .append(pp_text(id))
+                .append(kwd(" :"))
+                .append(func_doc)
         }),
         ";",
     );
     enclose_space("{", doc, "}")
 }
 
-fn pp_defs(env: &TypeEnv) -> RcDoc {
+fn pp_defs<'a>(env: &'a TypeEnv, docs: &'a DocComments) -> RcDoc<'a> {
     lines(env.0.iter().map(|(id, ty)| {
-        kwd("type")
+        pp_docs(docs.type_docs(id))
+            .append(kwd("type"))
             .append(ident(id))
             .append(kwd("="))
-            .append(pp_ty(ty))
+            .append(pp_ty_with_docs(ty, &DocCursor::new(docs, Some(id))))
             .append(";")
     }))
 }
 
-fn pp_actor(ty: &Type) -> RcDoc {
+fn pp_actor<'a>(ty: &'a Type, docs: &'a DocComments) -> RcDoc<'a> {
     let doc = match ty {
-        Type::Service(ref serv) => pp_service(serv),
+        Type::Service(ref serv) => pp_service(serv, &DocCursor::new(docs, None)),
         Type::Var(_) => pp_ty(ty),
         _ => unreachable!(),
     };
@@ -198,11 +237,16 @@ fn pp_actor(ty: &Type) -> RcDoc {
 }
 
 pub fn compile(env: &TypeEnv, actor: &Option<Type>) -> String {
+    compile_with_docs(env, actor, &DocComments::default())
+}
+
+/// Same as `compile`, and emits the doc comments as `///` comments.
+pub fn compile_with_docs(env: &TypeEnv, actor: &Option<Type>, docs: &DocComments) -> String {
     match actor {
         None => "".to_string(),
         Some(actor) => {
-            let defs = pp_defs(env);
-            let actor = pp_actor(actor);
+            let defs = pp_defs(env, docs);
+            let actor = pp_actor(actor, docs);
             let doc = defs.append(actor);
             doc.pretty(LINE_WIDTH).to_string()
         }
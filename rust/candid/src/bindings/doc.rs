@@ -0,0 +1,367 @@
+//! Reference documentation of a service, in Markdown or HTML.
+use super::analysis::{chase_actor, infer_rec};
+use crate::parser::types::{DocComments, DocCursor, DocStep};
+use crate::parser::typing::TypeEnv;
+use crate::types::{Field, Function, Label, Type};
+use crate::{idl_hash, Error, Result};
+use std::collections::BTreeSet;
+
+/// Output format of the documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl std::str::FromStr for Format {
+    type Err = Error;
+    fn from_str(str: &str) -> Result<Self> {
+        match str {
+            "md" | "markdown" => Ok(Format::Markdown),
+            "html" => Ok(Format::Html),
+            _ => Err(Error::msg(format!("unknown doc format {}", str))),
+        }
+    }
+}
+
+struct Writer<'a> {
+    env: &'a TypeEnv,
+    docs: &'a DocComments,
+    format: Format,
+    recs: BTreeSet<&'a str>,
+    out: String,
+}
+
+impl<'a> Writer<'a> {
+    fn text(&self, str: &str) -> String {
+        let mut res = String::new();
+        for c in str.chars() {
+            match (self.format, c) {
+                (Format::Markdown, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#') => {
+                    res.push('\\');
+                    res.push(c);
+                }
+                (Format::Html, '&') => res.push_str("&amp;"),
+                (Format::Html, '<') => res.push_str("&lt;"),
+                (Format::Html, '>') => res.push_str("&gt;"),
+                (Format::Html, '"') => res.push_str("&quot;"),
+                _ => res.push(c),
+            }
+        }
+        res
+    }
+    fn code(&self, str: &str) -> String {
+        match self.format {
+            Format::Markdown => format!("`{}`", str.replace('`', "'")),
+            Format::Html => format!("<code>{}</code>", self.text(str)),
+        }
+    }
+    fn badge(&self, str: &str) -> String {
+        match self.format {
+            Format::Markdown => format!("`{}`", str),
+            Format::Html => format!("<span class=\"badge {0}\">{0}</span>", str),
+        }
+    }
+    fn link(&self, id: &str) -> String {
+        match self.format {
+            Format::Markdown => format!("[{}](#type-{})", self.text(id), id),
+            Format::Html => format!("<a href=\"#type-{}\">{}</a>", id, self.text(id)),
+        }
+    }
+    fn heading(&mut self, level: usize, anchor: Option<&str>, title: &str) {
+        match self.format {
+            Format::Markdown => {
+                if let Some(anchor) = anchor {
+                    self.out += &format!("<a id=\"{}\"></a>\n\n", anchor);
+                }
+                self.out += &format!("{} {}\n\n", "#".repeat(level), title);
+            }
+            Format::Html => {
+                let id = anchor.map_or(String::new(), |a| format!(" id=\"{}\"", a));
+                self.out += &format!("<h{0}{1}>{2}</h{0}>\n", level, id, title);
+            }
+        }
+    }
+    fn paragraph(&mut self, body: &str) {
+        match self.format {
+            Format::Markdown => self.out += &format!("{}\n\n", body),
+            Format::Html => self.out += &format!("<p>{}</p>\n", body),
+        }
+    }
+    fn doc_comments(&mut self, docs: &[String]) {
+        // Blank doc lines separate paragraphs.
+        for para in docs.split(|line| line.trim().is_empty()) {
+            if !para.is_empty() {
+                let body = para
+                    .iter()
+                    .map(|line| self.text(line.trim()))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                self.paragraph(&body);
+            }
+        }
+    }
+    fn table(&mut self, header: &[&str], rows: &[Vec<String>]) {
+        match self.format {
+            Format::Markdown => {
+                self.out += &format!("| {} |\n", header.join(" | "));
+                self.out += &format!("|{}\n", " --- |".repeat(header.len()));
+                for row in rows.iter() {
+                    self.out += &format!("| {} |\n", row.join(" | "));
+                }
+                self.out += "\n";
+            }
+            Format::Html => {
+                self.out += "<table>\n<tr>";
+                for h in header.iter() {
+                    self.out += &format!("<th>{}</th>", h);
+                }
+                self.out += "</tr>\n";
+                for row in rows.iter() {
+                    self.out += "<tr>";
+                    for cell in row.iter() {
+                        self.out += &format!("<td>{}</td>", cell);
+                    }
+                    self.out += "</tr>\n";
+                }
+                self.out += "</table>\n";
+            }
+        }
+    }
+
+    fn label(&self, id: &Label) -> String {
+        self.text(&super::candid::pp_label(id).pretty(80).to_string())
+    }
+    fn ty(&self, ty: &Type) -> String {
+        use Type::*;
+        match ty {
+            Var(id) => self.link(id),
+            Opt(t) => format!("opt {}", self.ty(t)),
+            Vec(t) => format!("vec {}", self.ty(t)),
+            Record(fs) => format!("record {}", self.fields(fs, false)),
+            Variant(fs) => format!("variant {}", self.fields(fs, true)),
+            Func(func) => format!("func {}", self.function(func)),
+            Service(serv) => {
+                let ms: std::vec::Vec<_> = serv
+                    .iter()
+                    .map(|(id, t)| {
+                        let t = match t {
+                            Func(func) => self.function(func),
+                            _ => self.ty(t),
+                        };
+                        format!("{} : {}", self.label(&Label::Named(id.clone())), t)
+                    })
+                    .collect();
+                format!("service {{ {} }}", ms.join("; "))
+            }
+            _ => self.text(&ty.to_string()),
+        }
+    }
+    fn fields(&self, fs: &[Field], is_variant: bool) -> String {
+        let is_tuple = fs
+            .iter()
+            .enumerate()
+            .all(|(i, f)| f.id.get_id() == i as u32);
+        let fs: Vec<_> = fs
+            .iter()
+            .map(|f| {
+                if is_tuple && !is_variant {
+                    self.ty(&f.ty)
+                } else if is_variant && f.ty == Type::Null {
+                    self.label(&f.id)
+                } else {
+                    format!("{} : {}", self.label(&f.id), self.ty(&f.ty))
+                }
+            })
+            .collect();
+        if fs.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{ {} }}", fs.join("; "))
+        }
+    }
+    fn args(&self, args: &[Type]) -> String {
+        let args: Vec<_> = args.iter().map(|t| self.ty(t)).collect();
+        format!("({})", args.join(", "))
+    }
+    fn function(&self, func: &Function) -> String {
+        let mut res = format!("{} -> {}", self.args(&func.args), self.args(&func.rets));
+        for m in func.modes.iter() {
+            res += &format!(" {}", m.to_doc().pretty(80));
+        }
+        res
+    }
+
+    fn method(&mut self, actor: &Type, id: &str, ty: &Type) -> Result<()> {
+        let func = self.env.as_func(ty)?;
+        let name = super::candid::pp_label(&Label::Named(id.to_string()))
+            .pretty(80)
+            .to_string();
+        let mut title = self.code(&name);
+        for m in func.modes.iter() {
+            title += &format!(" {}", self.badge(&m.to_doc().pretty(80).to_string()));
+        }
+        let anchor = if id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            format!("method-{}", id)
+        } else {
+            format!("method-{}", idl_hash(id))
+        };
+        self.heading(3, Some(&anchor), &title);
+        let scope = match actor {
+            Type::Var(s) => Some(s.as_str()),
+            _ => None,
+        };
+        let docs = DocCursor::new(self.docs, scope).step(DocStep::Field(idl_hash(id)));
+        self.doc_comments(docs.docs());
+        if let Type::Var(f) = ty {
+            let body = format!("Type: {}", self.link(f));
+            self.paragraph(&body);
+        }
+        let body = format!("Arguments: {}", self.args(&func.args));
+        self.paragraph(&body);
+        let body = format!("Returns: {}", self.args(&func.rets));
+        self.paragraph(&body);
+        // The fields of a function type definition are documented with the definition
+        if let Type::Func(func) = ty {
+            let mut rows = Vec::new();
+            self.function_rows(func, &docs, "", &mut rows);
+            if !rows.is_empty() {
+                self.table(&["Field", "Id", "Type", "Description"], &rows);
+            }
+        }
+        Ok(())
+    }
+
+    fn type_def(&mut self, id: &str) -> Result<()> {
+        let ty = self.env.find_type(id)?;
+        let mut title = self.code(id);
+        if self.recs.contains(id) {
+            title += &format!(" {}", self.badge("recursive"));
+        }
+        self.heading(3, Some(&format!("type-{}", id)), &title);
+        self.doc_comments(self.docs.type_docs(id));
+        let body = self.ty(ty);
+        self.paragraph(&body);
+        let mut rows = Vec::new();
+        self.field_rows(
+            ty,
+            &DocCursor::new(self.docs, Some(id)),
+            "",
+            true,
+            &mut rows,
+        );
+        if !rows.is_empty() {
+            self.table(&["Field", "Id", "Type", "Description"], &rows);
+        }
+        Ok(())
+    }
+    // Fields of the records and variants written out in `ty`. Nested fields, including those in
+    // function arguments and results, are only listed when they have doc comments.
+    fn field_rows(
+        &self,
+        ty: &Type,
+        docs: &DocCursor<'a>,
+        prefix: &str,
+        all: bool,
+        rows: &mut Vec<Vec<String>>,
+    ) {
+        let mut ty = ty;
+        while let Type::Opt(t) | Type::Vec(t) = ty {
+            ty = t;
+        }
+        match ty {
+            Type::Record(fs) | Type::Variant(fs) => {
+                for f in fs.iter() {
+                    let docs = docs.step(DocStep::Field(f.id.get_id()));
+                    let label = format!("{}{}", prefix, self.label(&f.id));
+                    if all || !docs.docs().is_empty() {
+                        let description: Vec<_> =
+                            docs.docs().iter().map(|l| self.text(l.trim())).collect();
+                        rows.push(vec![
+                            label.clone(),
+                            f.id.get_id().to_string(),
+                            self.ty(&f.ty),
+                            description.join(" "),
+                        ]);
+                    }
+                    self.field_rows(&f.ty, &docs, &format!("{}.", label), false, rows);
+                }
+            }
+            Type::Func(func) => self.function_rows(func, docs, prefix, rows),
+            _ => (),
+        }
+    }
+    fn function_rows(
+        &self,
+        func: &Function,
+        docs: &DocCursor<'a>,
+        prefix: &str,
+        rows: &mut Vec<Vec<String>>,
+    ) {
+        for (i, t) in func.args.iter().enumerate() {
+            let prefix = format!("{}arg{}.", prefix, i);
+            self.field_rows(t, &docs.step(DocStep::Arg(i)), &prefix, false, rows);
+        }
+        for (i, t) in func.rets.iter().enumerate() {
+            let prefix = format!("{}ret{}.", prefix, i);
+            self.field_rows(t, &docs.step(DocStep::Ret(i)), &prefix, false, rows);
+        }
+    }
+}
+
+static STYLE: &str = "body { font-family: sans-serif; max-width: 60em; margin: auto; }
+code { background: #f3f3f3; padding: 0 0.2em; }
+.badge { font-size: small; border-radius: 0.3em; padding: 0 0.4em; background: #ddd; }
+.query { background: #cde8ff; }
+.oneway { background: #ffe4c4; }
+.recursive { background: #e5d4ff; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.5em; text-align: left; }";
+
+/// Renders the service and the type definitions it uses as reference documentation.
+/// Without a service, all type definitions are documented.
+pub fn compile(
+    env: &TypeEnv,
+    actor: &Option<Type>,
+    docs: &DocComments,
+    title: &str,
+    format: Format,
+) -> Result<String> {
+    let def_list = match actor {
+        Some(actor) => chase_actor(env, actor)?,
+        None => env.0.keys().map(|id| id.as_str()).collect(),
+    };
+    let recs = infer_rec(env, &def_list)?;
+    let mut w = Writer {
+        env,
+        docs,
+        format,
+        recs,
+        out: String::new(),
+    };
+    if format == Format::Html {
+        w.out += &format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n",
+            w.text(title),
+            STYLE
+        );
+    }
+    let heading = w.text(title);
+    w.heading(1, None, &heading);
+    if let Some(actor) = actor {
+        w.heading(2, None, "Methods");
+        for (id, ty) in env.as_service(actor)?.iter() {
+            w.method(actor, id, ty)?;
+        }
+    }
+    if !def_list.is_empty() {
+        w.heading(2, None, "Types");
+        for id in def_list.iter() {
+            w.type_def(id)?;
+        }
+    }
+    if format == Format::Html {
+        w.out += "</body>\n</html>\n";
+    }
+    Ok(w.out)
+}
@@ -0,0 +1,274 @@
+use crate::parser::types::FuncMode;
+use crate::parser::typing::TypeEnv;
+use crate::types::{Field, Label, Type};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Converts a Candid id to an exported Go name, or `None` if it is not a valid Go id.
+fn exported(id: &str) -> Option<String> {
+    if id.is_empty() || !id.is_ascii() || id.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    if id.chars().any(|c| !c.is_ascii_alphanumeric() && c != '_') {
+        return None;
+    }
+    let mut res = String::new();
+    for part in id.split('_').filter(|p| !p.is_empty()) {
+        let mut chars = part.chars();
+        res.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+        res.extend(chars);
+    }
+    if res.is_empty() || res.starts_with(|c: char| c.is_ascii_digit()) {
+        None
+    } else {
+        Some(res)
+    }
+}
+
+fn go_name(id: &str) -> String {
+    exported(id).unwrap_or_else(|| format!("Hash{}", crate::idl_hash(id)))
+}
+
+fn field_name(id: &Label) -> String {
+    match id {
+        Label::Named(id) => go_name(id),
+        Label::Id(n) | Label::Unnamed(n) => format!("Field{}", n),
+    }
+}
+
+// Tags of the form `N` and `_N_` stand for the field id `N`.
+fn looks_like_id(id: &str) -> bool {
+    let digits = id
+        .strip_prefix('_')
+        .and_then(|id| id.strip_suffix('_'))
+        .unwrap_or(id);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+// Struct tags are raw strings, so names that do not fit in one, or that look like a field id,
+// use their hash.
+fn tag(id: &Label) -> String {
+    let name = match id {
+        Label::Named(id) if looks_like_id(id) || id.chars().any(|c| c == '`' || c.is_control()) => {
+            format!("_{}_", crate::idl_hash(id))
+        }
+        Label::Named(id) => id.replace('\\', "\\\\").replace('"', "\\\""),
+        Label::Id(n) | Label::Unnamed(n) => n.to_string(),
+    };
+    format!("`candid:\"{}\"`", name)
+}
+
+fn go_str(s: &str) -> String {
+    let mut res = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '"' => res.push_str("\\\""),
+            c if c.is_control() => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+struct Writer<'a> {
+    env: &'a TypeEnv,
+    names: BTreeMap<&'a str, String>,
+    uses_big: bool,
+}
+
+impl<'a> Writer<'a> {
+    fn ty(&mut self, t: &Type) -> String {
+        use Type::*;
+        match t {
+            Null => "struct{}".to_string(),
+            Bool => "bool".to_string(),
+            Nat | Int => {
+                self.uses_big = true;
+                "*big.Int".to_string()
+            }
+            Nat8 => "uint8".to_string(),
+            Nat16 => "uint16".to_string(),
+            Nat32 => "uint32".to_string(),
+            Nat64 => "uint64".to_string(),
+            Int8 => "int8".to_string(),
+            Int16 => "int16".to_string(),
+            Int32 => "int32".to_string(),
+            Int64 => "int64".to_string(),
+            Float32 => "float32".to_string(),
+            Float64 => "float64".to_string(),
+            Text => "string".to_string(),
+            Reserved => "interface{}".to_string(),
+            // There are no values of type empty
+            Empty => "struct{}".to_string(),
+            Var(id) => self.names[id.as_str()].clone(),
+            Opt(t) => format!("*{}", self.ty(t)),
+            Vec(t) if **t == Nat8 => "[]byte".to_string(),
+            Vec(t) => format!("[]{}", self.ty(t)),
+            Record(fs) => {
+                let fields = self.fields(fs, false);
+                if fields.is_empty() {
+                    "struct{}".to_string()
+                } else {
+                    let fields: std::vec::Vec<_> = fields
+                        .iter()
+                        .map(|(name, ty, tag)| format!("{} {} {}", name, ty, tag))
+                        .collect();
+                    format!("struct{{ {} }}", fields.join("; "))
+                }
+            }
+            Variant(fs) => {
+                let fields: std::vec::Vec<_> = self
+                    .fields(fs, true)
+                    .iter()
+                    .map(|(name, ty, tag)| format!("{} {} {}", name, ty, tag))
+                    .collect();
+                if fields.is_empty() {
+                    "struct{}".to_string()
+                } else {
+                    format!("struct{{ {} }}", fields.join("; "))
+                }
+            }
+            // Principal of the canister, and the method name
+            Func(_) => "struct{ Principal []byte; Method string }".to_string(),
+            Service(_) | Principal => "[]byte".to_string(),
+            _ => unreachable!(),
+        }
+    }
+
+    // Returns (name, type, tag) of each field. Variant cases are pointers, and exactly one of them is set.
+    fn fields(
+        &mut self,
+        fs: &[Field],
+        is_variant: bool,
+    ) -> std::vec::Vec<(String, String, String)> {
+        let mut seen = BTreeSet::new();
+        let mut res = std::vec::Vec::new();
+        for f in fs.iter() {
+            let mut name = field_name(&f.id);
+            if !seen.insert(name.clone()) {
+                name = format!("{}{}", name, f.id.get_id());
+                seen.insert(name.clone());
+            }
+            let ty = self.ty(&f.ty);
+            // `*big.Int` can already be nil
+            let ty = if is_variant && !ty.starts_with("*big.") {
+                format!("*{}", ty)
+            } else {
+                ty
+            };
+            res.push((name, ty, tag(&f.id)));
+        }
+        res
+    }
+
+    // Named records and variants are printed as multi-line structs, aligned as gofmt does.
+    fn def(&mut self, id: &str, t: &Type) -> String {
+        let name = self.names[id].clone();
+        let (mut res, fields) = match t {
+            Type::Record(fs) if !fs.is_empty() => (String::new(), self.fields(fs, false)),
+            Type::Variant(fs) if !fs.is_empty() => (
+                format!(
+                    "// {} is a variant, exactly one of its fields is set.\n",
+                    name
+                ),
+                self.fields(fs, true),
+            ),
+            _ => return format!("type {} {}\n", name, self.ty(t)),
+        };
+        let name_width = fields.iter().map(|f| f.0.len()).max().unwrap_or(0);
+        let ty_width = fields.iter().map(|f| f.1.len()).max().unwrap_or(0);
+        res += &format!("type {} struct {{\n", name);
+        for (field, ty, tag) in fields.iter() {
+            res += &format!(
+                "\t{:nw$} {:tw$} {}\n",
+                field,
+                ty,
+                tag,
+                nw = name_width,
+                tw = ty_width
+            );
+        }
+        res += "}\n";
+        res
+    }
+
+    fn service(&mut self, name: &str, actor: &'a Type) -> String {
+        let mut res = format!("type {} interface {{\n", name);
+        let mut seen = BTreeSet::new();
+        for (i, (id, t)) in self.env.as_service(actor).unwrap().iter().enumerate() {
+            let func = self.env.as_func(t).unwrap();
+            let mut method = go_name(id);
+            if !seen.insert(method.clone()) {
+                method = format!("{}{}", method, i);
+                seen.insert(method.clone());
+            }
+            let args: std::vec::Vec<_> = func
+                .args
+                .iter()
+                .enumerate()
+                .map(|(i, t)| format!("arg{} {}", i, self.ty(t)))
+                .collect();
+            let mut rets: std::vec::Vec<_> = func.rets.iter().map(|t| self.ty(t)).collect();
+            rets.push("error".to_string());
+            let rets = if rets.len() == 1 {
+                rets.pop().unwrap()
+            } else {
+                format!("({})", rets.join(", "))
+            };
+            let kind = if func.modes.contains(&FuncMode::Oneway) {
+                "oneway method"
+            } else if func.is_query() {
+                "query method"
+            } else {
+                "method"
+            };
+            res += &format!(
+                "\t// {} calls the {} {}.\n\t{}({}) {}\n",
+                method,
+                kind,
+                go_str(id),
+                method,
+                args.join(", "),
+                rets
+            );
+        }
+        res += "}\n";
+        res
+    }
+}
+
+pub fn compile(env: &TypeEnv, actor: &Option<Type>) -> String {
+    let mut used = BTreeSet::new();
+    used.insert("Service".to_string());
+    let mut names = BTreeMap::new();
+    for id in env.0.keys() {
+        let base = go_name(id);
+        let mut name = base.clone();
+        let mut i = 1;
+        while !used.insert(name.clone()) {
+            name = format!("{}{}", base, i);
+            i += 1;
+        }
+        names.insert(id.as_str(), name);
+    }
+    let mut w = Writer {
+        env,
+        names,
+        uses_big: false,
+    };
+    let mut body = String::new();
+    for (id, t) in env.0.iter() {
+        body += "\n";
+        body += &w.def(id, t);
+    }
+    if let Some(actor) = actor {
+        body += "\n";
+        body += &w.service("Service", actor);
+    }
+    let mut res = "// Code generated by didc. DO NOT EDIT.\n\npackage service\n".to_string();
+    if w.uses_big {
+        res += "\nimport \"math/big\"\n";
+    }
+    res + &body
+}
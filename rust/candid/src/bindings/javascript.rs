@@ -1,4 +1,5 @@
 use super::analysis::{chase_actor, infer_rec};
+use crate::parser::types::{DocComments, DocCursor, DocStep};
 use crate::parser::typing::TypeEnv;
 use crate::pretty::*;
 use crate::types::{Field, Function, Label, Type};
@@ -24,6 +25,10 @@ fn is_tuple(t: &Type) -> bool {
 }
 
 fn pp_ty(ty: &Type) -> RcDoc {
+    pp_ty_with_docs(ty, &DocCursor::none())
+}
+
+fn pp_ty_with_docs<'a>(ty: &'a Type, docs: &DocCursor<'a>) -> RcDoc<'a> {
     use Type::*;
     match *ty {
         Null => str("IDL.Null"),
@@ -45,19 +50,25 @@ fn pp_ty(ty: &Type) -> RcDoc {
         Empty => str("IDL.Empty"),
         Var(ref s) => str(s),
         Principal => str("IDL.Principal"),
-        Opt(ref t) => str("IDL.Opt").append(enclose("(", pp_ty(t), ")")),
-        Vec(ref t) => str("IDL.Vec").append(enclose("(", pp_ty(t), ")")),
+        Opt(ref t) => str("IDL.Opt").append(enclose("(", pp_ty_with_docs(t, docs), ")")),
+        Vec(ref t) => str("IDL.Vec").append(enclose("(", pp_ty_with_docs(t, docs), ")")),
         Record(ref fs) => {
             if is_tuple(ty) {
-                let tuple = concat(fs.iter().map(|f| pp_ty(&f.ty)), ",");
+                let tuple = concat(
+                    fs.iter().map(|f| {
+                        let docs = docs.step(DocStep::Field(f.id.get_id()));
+                        pp_docs(docs.docs()).append(pp_ty_with_docs(&f.ty, &docs))
+                    }),
+                    ",",
+                );
                 str("IDL.Tuple").append(enclose("(", tuple, ")"))
             } else {
-                str("IDL.Record").append(pp_fields(fs))
+                str("IDL.Record").append(pp_fields(fs, docs))
             }
         }
-        Variant(ref fs) => str("IDL.Variant").append(pp_fields(fs)),
-        Func(ref func) => str("IDL.Func").append(pp_function(func)),
-        Service(ref serv) => str("IDL.Service").append(pp_service(serv)),
+        Variant(ref fs) => str("IDL.Variant").append(pp_fields(fs, docs)),
+        Func(ref func) => str("IDL.Func").append(pp_function(func, docs)),
+        Service(ref serv) => str("IDL.Service").append(pp_service(serv, docs)),
         _ => unreachable!(),
     }
 }
@@ -72,28 +83,62 @@ fn pp_label(id: &Label) -> RcDoc {
     }
 }
 
-fn pp_field(field: &Field) -> RcDoc {
-    pp_label(&field.id)
+fn pp_docs(docs: &[String]) -> RcDoc<'_> {
+    let escape = |line: &String| line.replace("*/", "*\\/");
+    match docs {
+        [] => RcDoc::nil(),
+        [line] => RcDoc::text(format!("/** {} */", escape(line))).append(RcDoc::hardline()),
+        _ => {
+            let body = docs.iter().map(move |line| {
+                if line.is_empty() {
+                    str(" *")
+                } else {
+                    RcDoc::text(format!(" * {}", escape(line)))
+                }
+            });
+            str("/**")
+                .append(RcDoc::hardline())
+                .append(lines(body))
+                .append(" */")
+                .append(RcDoc::hardline())
+        }
+    }
+}
+
+fn pp_field<'a>(field: &'a Field, docs: &DocCursor<'a>) -> RcDoc<'a> {
+    pp_docs(docs.docs())
+        .append(pp_label(&field.id))
         .append(kwd(":"))
-        .append(pp_ty(&field.ty))
+        .append(pp_ty_with_docs(&field.ty, docs))
 }
 
-fn pp_fields(fs: &[Field]) -> RcDoc {
-    let fields = concat(fs.iter().map(pp_field), ",");
+fn pp_fields<'a>(fs: &'a [Field], docs: &DocCursor<'a>) -> RcDoc<'a> {
+    let fields = concat(
+        fs.iter().map(|f| {
+            let docs = docs.step(DocStep::Field(f.id.get_id()));
+            pp_field(f, &docs)
+        }),
+        ",",
+    );
     enclose_space("({", fields, "})")
 }
 
-fn pp_function(func: &Function) -> RcDoc {
-    let args = pp_args(&func.args);
-    let rets = pp_args(&func.rets);
+fn pp_function<'a>(func: &'a Function, docs: &DocCursor<'a>) -> RcDoc<'a> {
+    let args = pp_args(&func.args, docs, DocStep::Arg);
+    let rets = pp_args(&func.rets, docs, DocStep::Ret);
     let modes = pp_modes(&func.modes);
     let items = [args, rets, modes];
     let doc = concat(items.iter().cloned(), ",");
     enclose("(", doc, ")").nest(INDENT_SPACE)
 }
 
-fn pp_args(args: &[Type]) -> RcDoc {
-    let doc = concat(args.iter().map(pp_ty), ",");
+fn pp_args<'a>(args: &'a [Type], docs: &DocCursor<'a>, step: fn(usize) -> DocStep) -> RcDoc<'a> {
+    let doc = concat(
+        args.iter()
+            .enumerate()
+            .map(|(i, t)| pp_ty_with_docs(t, &docs.step(step(i)))),
+        ",",
+    );
     enclose("[", doc, "]")
 }
 
@@ -107,10 +152,15 @@ fn pp_modes(modes: &[crate::parser::types::FuncMode]) -> RcDoc {
     enclose("[", doc, "]")
 }
 
-fn pp_service(serv: &[(String, Type)]) -> RcDoc {
+fn pp_service<'a>(serv: &'a [(String, Type)], docs: &DocCursor<'a>) -> RcDoc<'a> {
     let doc = concat(
-        serv.iter()
-            .map(|(id, func)| quote_ident(id).append(kwd(":")).append(pp_ty(func))),
+        serv.iter().map(|(id, func)| {
+            let docs = docs.step(DocStep::Field(crate::idl_hash(id)));
+            pp_docs(docs.docs())
+                .append(quote_ident(id))
+                .append(kwd(":"))
+                .append(pp_ty_with_docs(func, &docs))
+        }),
         ",",
     );
     enclose_space("({", doc, "})")
@@ -120,6 +170,7 @@ fn pp_defs<'a>(
     env: &'a TypeEnv,
     def_list: &'a [&'a str],
     recs: &'a BTreeSet<&'a str>,
+    docs: &'a DocComments,
 ) -> RcDoc<'a> {
     let recs_doc = lines(
         recs.iter()
@@ -127,24 +178,28 @@ fn pp_defs<'a>(
     );
     let defs = lines(def_list.iter().map(|id| {
         let ty = env.find_type(id).unwrap();
-        if recs.contains(id) {
+        let cursor = DocCursor::new(docs, Some(id));
+        let def = if recs.contains(id) {
             str(id)
                 .append(".fill")
-                .append(enclose("(", pp_ty(ty), ");"))
+                .append(enclose("(", pp_ty_with_docs(ty, &cursor), ");"))
         } else {
             kwd("const")
                 .append(ident(id))
                 .append(kwd("="))
-                .append(pp_ty(ty))
+                .append(pp_ty_with_docs(ty, &cursor))
                 .append(";")
-        }
+        };
+        pp_docs(docs.type_docs(id)).append(def)
     }));
     recs_doc.append(defs)
 }
 
-fn pp_actor<'a>(ty: &'a Type, recs: &'a BTreeSet<&'a str>) -> RcDoc<'a> {
+fn pp_actor<'a>(ty: &'a Type, recs: &'a BTreeSet<&'a str>, docs: &'a DocComments) -> RcDoc<'a> {
     let doc = match ty {
-        Type::Service(_) => pp_ty(ty),
+        Type::Service(ref serv) => {
+            str("IDL.Service").append(pp_service(serv, &DocCursor::new(docs, None)))
+        }
         Type::Var(id) => {
             if recs.contains(&*id.clone()) {
                 str(id).append(".getType()")
@@ -158,18 +213,23 @@ fn pp_actor<'a>(ty: &'a Type, recs: &'a BTreeSet<&'a str>) -> RcDoc<'a> {
 }
 
 pub fn compile(env: &TypeEnv, actor: &Option<Type>) -> String {
+    compile_with_docs(env, actor, &DocComments::default())
+}
+
+/// Same as `compile`, and emits the doc comments as JSDoc comments.
+pub fn compile_with_docs(env: &TypeEnv, actor: &Option<Type>, docs: &DocComments) -> String {
     match actor {
         None => {
             let def_list: Vec<_> = env.0.iter().map(|pair| pair.0.as_ref()).collect();
             let recs = infer_rec(env, &def_list).unwrap();
-            let doc = pp_defs(env, &def_list, &recs);
+            let doc = pp_defs(env, &def_list, &recs, docs);
             doc.pretty(LINE_WIDTH).to_string()
         }
         Some(actor) => {
             let def_list = chase_actor(env, actor).unwrap();
             let recs = infer_rec(env, &def_list).unwrap();
-            let defs = pp_defs(env, &def_list, &recs);
-            let actor = pp_actor(actor, &recs);
+            let defs = pp_defs(env, &def_list, &recs, docs);
+            let actor = pp_actor(actor, &recs, docs);
             let body = defs.append(actor);
             let doc = str("export default ({ IDL }) => ").append(enclose_space("{", body, "};"));
             doc.pretty(LINE_WIDTH).to_string()
@@ -246,6 +306,7 @@ pub mod value {
 pub mod test {
     use super::value;
     use crate::parser::test::{HostAssert, HostTest, Test};
+    use crate::parser::types::{DocCursor, DocStep};
     use crate::pretty::*;
     use crate::TypeEnv;
     use pretty::RcDoc;
@@ -257,7 +318,7 @@ pub mod test {
     }
     fn pp_encode<'a>(args: &'a crate::IDLArgs, tys: &'a [crate::types::Type]) -> RcDoc<'a> {
         let vals = value::pp_args(&args);
-        let tys = super::pp_args(&tys);
+        let tys = super::pp_args(&tys, &DocCursor::none(), DocStep::Arg);
         let items = [tys, vals];
         let params = concat(items.iter().cloned(), ",");
         str("IDL.encode").append(enclose("(", params, ")"))
@@ -265,7 +326,7 @@ pub mod test {
 
     fn pp_decode<'a>(bytes: &'a [u8], tys: &'a [crate::types::Type]) -> RcDoc<'a> {
         let hex = pp_hex(&bytes);
-        let tys = super::pp_args(&tys);
+        let tys = super::pp_args(&tys, &DocCursor::none(), DocStep::Arg);
         let items = [tys, hex];
         let params = concat(items.iter().cloned(), ",");
         str("IDL.decode").append(enclose("(", params, ")"))
@@ -301,7 +362,10 @@ import { Principal } from './principal';
                 use HostAssert::*;
                 let test_func = match cmd {
                     Encode(args, tys, _, _) | NotEncode(args, tys) => {
-                        let items = [super::pp_args(&tys), pp_encode(&args, &tys)];
+                        let items = [
+                            super::pp_args(&tys, &DocCursor::none(), DocStep::Arg),
+                            pp_encode(&args, &tys),
+                        ];
                         let params = concat(items.iter().cloned(), ",");
                         str("IDL.decode").append(enclose("(", params, ")"))
                     }
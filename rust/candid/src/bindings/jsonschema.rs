@@ -0,0 +1,372 @@
+//! JSON Schema and OpenAPI descriptions of a service.
+//!
+//! The schemas describe the JSON mapping of Candid values, which is the same as in the
+//! JavaScript binding:
+//!
+//! * `null` is `null`, `bool` is a boolean, `text` is a string and floats are numbers;
+//! * `nat8` to `nat32` and `int8` to `int32` are integers. `nat`, `int`, `nat64` and `int64`
+//!   are decimal strings, as they may not fit in a double;
+//! * `opt t` is `[]` or `[v]`, so that `opt opt t` stays unambiguous;
+//! * `vec t` is an array, including `blob`;
+//! * records are objects keyed by field name, or `_n_` for a numeric id. Tuples are arrays;
+//! * variants are objects with exactly one key;
+//! * principals and service references are textual principals. Function references are
+//!   `[principal, method]`;
+//! * `reserved` accepts any value, and `empty` none.
+//!
+//! Named types are emitted as definitions and referenced with `$ref`, so that recursive types
+//! can be described. Method arguments and results are arrays, as in `IDLArgs`.
+use super::analysis::chase_actor;
+use crate::parser::types::FuncMode;
+use crate::parser::typing::TypeEnv;
+use crate::types::{Field, Function, Label, Type};
+
+// A JSON document, which keeps the order of object keys.
+enum Json {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn obj(fields: Vec<(&str, Json)>) -> Self {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+    fn str(s: &str) -> Self {
+        Json::Str(s.to_string())
+    }
+    fn pp(&self, indent: usize, out: &mut String) {
+        match self {
+            Json::Bool(b) => out.push_str(&b.to_string()),
+            Json::Int(n) => out.push_str(&n.to_string()),
+            Json::Str(s) => pp_str(s, out),
+            Json::Array(vs) if vs.is_empty() => out.push_str("[]"),
+            Json::Object(fs) if fs.is_empty() => out.push_str("{}"),
+            Json::Array(vs) => {
+                out.push('[');
+                for (i, v) in vs.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    out.push_str(&"  ".repeat(indent + 1));
+                    v.pp(indent + 1, out);
+                }
+                out.push('\n');
+                out.push_str(&"  ".repeat(indent));
+                out.push(']');
+            }
+            Json::Object(fs) => {
+                out.push('{');
+                for (i, (k, v)) in fs.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    out.push_str(&"  ".repeat(indent + 1));
+                    pp_str(k, out);
+                    out.push_str(": ");
+                    v.pp(indent + 1, out);
+                }
+                out.push('\n');
+                out.push_str(&"  ".repeat(indent));
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn pp_str(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+static PRINCIPAL_PATTERN: &str = "^[a-z2-7]{1,5}(-[a-z2-7]{1,5})*$";
+
+// The definition of tuple is language specific.
+fn is_tuple(fs: &[Field]) -> bool {
+    !fs.is_empty()
+        && fs
+            .iter()
+            .enumerate()
+            .all(|(i, field)| field.id.get_id() == (i as u32))
+}
+
+fn label(id: &Label) -> String {
+    match id {
+        Label::Named(id) => id.clone(),
+        Label::Id(n) | Label::Unnamed(n) => format!("_{}_", n),
+    }
+}
+
+fn integer(min: i64, max: i64) -> Json {
+    Json::obj(vec![
+        ("type", Json::str("integer")),
+        ("minimum", Json::Int(min)),
+        ("maximum", Json::Int(max)),
+    ])
+}
+
+fn decimal(pattern: &str) -> Json {
+    Json::obj(vec![
+        ("type", Json::str("string")),
+        ("pattern", Json::str(pattern)),
+    ])
+}
+
+fn principal() -> Json {
+    Json::obj(vec![
+        ("type", Json::str("string")),
+        ("pattern", Json::str(PRINCIPAL_PATTERN)),
+    ])
+}
+
+fn tuple(items: Vec<Json>) -> Json {
+    let len = items.len() as i64;
+    let mut fs = vec![("type", Json::str("array"))];
+    if !items.is_empty() {
+        fs.push(("prefixItems", Json::Array(items)));
+    }
+    fs.push(("items", Json::Bool(false)));
+    fs.push(("minItems", Json::Int(len)));
+    Json::obj(fs)
+}
+
+struct Writer<'a> {
+    env: &'a TypeEnv,
+    // Prefix of the `$ref` to a named type
+    refs: &'a str,
+}
+
+impl<'a> Writer<'a> {
+    fn ty(&self, t: &Type) -> Json {
+        use Type::*;
+        match t {
+            Null => Json::obj(vec![("type", Json::str("null"))]),
+            Bool => Json::obj(vec![("type", Json::str("boolean"))]),
+            Nat | Nat64 => decimal("^[0-9]+$"),
+            Int | Int64 => decimal("^-?[0-9]+$"),
+            Nat8 => integer(0, u8::MAX as i64),
+            Nat16 => integer(0, u16::MAX as i64),
+            Nat32 => integer(0, u32::MAX as i64),
+            Int8 => integer(i8::MIN as i64, i8::MAX as i64),
+            Int16 => integer(i16::MIN as i64, i16::MAX as i64),
+            Int32 => integer(i32::MIN as i64, i32::MAX as i64),
+            Float32 | Float64 => Json::obj(vec![("type", Json::str("number"))]),
+            Text => Json::obj(vec![("type", Json::str("string"))]),
+            Reserved => Json::Bool(true),
+            Empty => Json::Bool(false),
+            Principal | Service(_) => principal(),
+            Var(id) => Json::obj(vec![("$ref", Json::Str(format!("{}{}", self.refs, id)))]),
+            Opt(t) => Json::obj(vec![
+                ("type", Json::str("array")),
+                ("items", self.ty(t)),
+                ("maxItems", Json::Int(1)),
+            ]),
+            Vec(t) => Json::obj(vec![("type", Json::str("array")), ("items", self.ty(t))]),
+            Record(fs) if is_tuple(fs) => tuple(fs.iter().map(|f| self.ty(&f.ty)).collect()),
+            Record(fs) => self.record(fs),
+            Variant(fs) => {
+                let cases = fs
+                    .iter()
+                    .map(|f| {
+                        let id = label(&f.id);
+                        Json::obj(vec![
+                            ("type", Json::str("object")),
+                            (
+                                "properties",
+                                Json::Object(vec![(id.clone(), self.ty(&f.ty))]),
+                            ),
+                            ("required", Json::Array(vec![Json::Str(id)])),
+                            ("maxProperties", Json::Int(1)),
+                        ])
+                    })
+                    .collect();
+                Json::obj(vec![("oneOf", Json::Array(cases))])
+            }
+            Func(_) => tuple(vec![
+                principal(),
+                Json::obj(vec![("type", Json::str("string"))]),
+            ]),
+            _ => unreachable!(),
+        }
+    }
+
+    // Fields that are absent decode as `null` when their type accepts it.
+    fn is_optional(&self, t: &Type) -> bool {
+        let t = match t {
+            Type::Var(id) => self.env.rec_find_type(id).unwrap(),
+            t => t,
+        };
+        matches!(t, Type::Opt(_) | Type::Null | Type::Reserved)
+    }
+
+    fn record(&self, fs: &[Field]) -> Json {
+        let props = fs.iter().map(|f| (label(&f.id), self.ty(&f.ty))).collect();
+        let required = fs
+            .iter()
+            .filter(|f| !self.is_optional(&f.ty))
+            .map(|f| Json::Str(label(&f.id)))
+            .collect::<std::vec::Vec<_>>();
+        let mut res = vec![
+            ("type", Json::str("object")),
+            ("properties", Json::Object(props)),
+        ];
+        if !required.is_empty() {
+            res.push(("required", Json::Array(required)));
+        }
+        Json::obj(res)
+    }
+
+    fn args(&self, args: &[Type]) -> Json {
+        tuple(args.iter().map(|t| self.ty(t)).collect())
+    }
+
+    fn defs(&self, def_list: &[&str]) -> Json {
+        let defs = def_list
+            .iter()
+            .map(|id| (id.to_string(), self.ty(self.env.find_type(id).unwrap())))
+            .collect();
+        Json::Object(defs)
+    }
+}
+
+fn mode(func: &Function) -> &'static str {
+    if func.modes.contains(&FuncMode::Oneway) {
+        "oneway"
+    } else if func.is_query() {
+        "query"
+    } else {
+        "update"
+    }
+}
+
+fn def_list<'a>(env: &'a TypeEnv, actor: &'a Option<Type>) -> Vec<&'a str> {
+    match actor {
+        Some(actor) => chase_actor(env, actor).unwrap(),
+        None => env.0.keys().map(|id| id.as_str()).collect(),
+    }
+}
+
+// Characters other than the unreserved ones of RFC 3986 are percent-encoded.
+fn path(method: &str) -> String {
+    let mut res = String::from("/");
+    for b in method.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            res.push(b as char);
+        } else {
+            res.push_str(&format!("%{:02X}", b));
+        }
+    }
+    res
+}
+
+/// Converts the type definitions and the service to a JSON Schema (draft 2020-12) document.
+/// Named types are under `$defs`, and each method is described under `methods` by its mode
+/// and the schemas of its arguments and results.
+pub fn compile(env: &TypeEnv, actor: &Option<Type>) -> String {
+    let w = Writer {
+        env,
+        refs: "#/$defs/",
+    };
+    let def_list = def_list(env, actor);
+    let mut doc = vec![
+        (
+            "$schema",
+            Json::str("https://json-schema.org/draft/2020-12/schema"),
+        ),
+        ("$defs", w.defs(&def_list)),
+    ];
+    if let Some(actor) = actor {
+        let methods = env
+            .as_service(actor)
+            .unwrap()
+            .iter()
+            .map(|(id, t)| {
+                let func = env.as_func(t).unwrap();
+                let method = Json::obj(vec![
+                    ("mode", Json::str(mode(func))),
+                    ("arguments", w.args(&func.args)),
+                    ("results", w.args(&func.rets)),
+                ]);
+                (id.clone(), method)
+            })
+            .collect();
+        doc.push(("methods", Json::Object(methods)));
+    }
+    let mut out = String::new();
+    Json::obj(doc).pp(0, &mut out);
+    out
+}
+
+/// Converts the service to an OpenAPI 3.1 document, where each method is a `POST` operation
+/// on `/{method}`. The request body holds the arguments and the response the results, both as
+/// JSON arrays. Named types are under `components/schemas`.
+pub fn compile_openapi(env: &TypeEnv, actor: &Option<Type>) -> String {
+    let w = Writer {
+        env,
+        refs: "#/components/schemas/",
+    };
+    let def_list = def_list(env, actor);
+    let mut paths = Vec::new();
+    if let Some(actor) = actor {
+        for (id, t) in env.as_service(actor).unwrap().iter() {
+            let func = env.as_func(t).unwrap();
+            let content = |schema| {
+                Json::obj(vec![(
+                    "application/json",
+                    Json::obj(vec![("schema", schema)]),
+                )])
+            };
+            let request = Json::obj(vec![
+                ("required", Json::Bool(true)),
+                ("content", content(w.args(&func.args))),
+            ]);
+            let response = if func.modes.contains(&FuncMode::Oneway) {
+                Json::obj(vec![("description", Json::str("The call is accepted"))])
+            } else {
+                Json::obj(vec![
+                    ("description", Json::str("The results of the call")),
+                    ("content", content(w.args(&func.rets))),
+                ])
+            };
+            let op = Json::obj(vec![
+                ("operationId", Json::Str(id.clone())),
+                ("x-candid-mode", Json::str(mode(func))),
+                ("requestBody", request),
+                ("responses", Json::obj(vec![("200", response)])),
+            ]);
+            paths.push((path(id), Json::obj(vec![("post", op)])));
+        }
+    }
+    let doc = Json::obj(vec![
+        ("openapi", Json::str("3.1.0")),
+        (
+            "info",
+            Json::obj(vec![
+                ("title", Json::str("Candid service")),
+                ("version", Json::str("1.0.0")),
+            ]),
+        ),
+        ("paths", Json::Object(paths)),
+        (
+            "components",
+            Json::obj(vec![("schemas", w.defs(&def_list))]),
+        ),
+    ]);
+    let mut out = String::new();
+    doc.pp(0, &mut out);
+    out
+}
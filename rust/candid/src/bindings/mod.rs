@@ -3,4 +3,10 @@
 
 pub mod analysis;
 pub mod candid;
+pub mod doc;
+pub mod go;
 pub mod javascript;
+pub mod jsonschema;
+pub mod motoko;
+pub mod protobuf;
+pub mod python;
@@ -0,0 +1,263 @@
+use crate::parser::types::FuncMode;
+use crate::parser::typing::TypeEnv;
+use crate::pretty::*;
+use crate::types::{Field, Function, Label, Type};
+use pretty::RcDoc;
+
+// The definition of tuple is language specific.
+fn is_tuple(t: &Type) -> bool {
+    match t {
+        Type::Record(ref fs) => {
+            if fs.len() <= 1 {
+                return false;
+            }
+            for (i, field) in fs.iter().enumerate() {
+                if field.id.get_id() != (i as u32) {
+                    return false;
+                }
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+static KEYWORDS: [&str; 43] = [
+    "actor",
+    "and",
+    "assert",
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "continue",
+    "debug",
+    "debug_show",
+    "do",
+    "else",
+    "false",
+    "flexible",
+    "for",
+    "func",
+    "if",
+    "ignore",
+    "import",
+    "in",
+    "label",
+    "let",
+    "loop",
+    "module",
+    "not",
+    "null",
+    "object",
+    "or",
+    "private",
+    "public",
+    "query",
+    "return",
+    "shared",
+    "stable",
+    "switch",
+    "system",
+    "throw",
+    "true",
+    "try",
+    "type",
+    "var",
+];
+
+fn is_valid_as_id(id: &str) -> bool {
+    if id.is_empty() || !id.is_ascii() {
+        return false;
+    }
+    for (i, c) in id.char_indices() {
+        if i == 0 {
+            if !c.is_ascii_alphabetic() && c != '_' {
+                return false;
+            }
+        } else if !c.is_ascii_alphanumeric() && c != '_' {
+            return false;
+        }
+    }
+    true
+}
+
+// Motoko drops a trailing `_` from field names, so keywords and names ending with `_`
+// get an extra one. Other names are replaced by their hash.
+fn escape(id: &str) -> RcDoc<'_> {
+    if KEYWORDS.contains(&id) || (is_valid_as_id(id) && id.ends_with('_')) {
+        str(id).append("_")
+    } else if is_valid_as_id(id) {
+        str(id)
+    } else {
+        RcDoc::text(format!("_{}_", crate::idl_hash(id)))
+    }
+}
+
+fn pp_ty(ty: &Type) -> RcDoc<'_> {
+    use Type::*;
+    match *ty {
+        Null => str("Null"),
+        Bool => str("Bool"),
+        Nat => str("Nat"),
+        Int => str("Int"),
+        Nat8 => str("Nat8"),
+        Nat16 => str("Nat16"),
+        Nat32 => str("Nat32"),
+        Nat64 => str("Nat64"),
+        Int8 => str("Int8"),
+        Int16 => str("Int16"),
+        Int32 => str("Int32"),
+        Int64 => str("Int64"),
+        // Motoko has no 32-bit floats
+        Float32 => str("Float"),
+        Float64 => str("Float"),
+        Text => str("Text"),
+        Reserved => str("Any"),
+        Empty => str("None"),
+        Var(ref s) => escape(s),
+        Principal => str("Principal"),
+        Opt(ref t) => str("?").append(pp_ty(t)),
+        Vec(ref t) if **t == Nat8 => str("Blob"),
+        Vec(ref t) => enclose("[", pp_ty(t), "]"),
+        Record(ref fs) => {
+            if is_tuple(ty) {
+                let tuple = concat(fs.iter().map(|f| pp_ty(&f.ty)), ",");
+                enclose("(", tuple, ")")
+            } else {
+                pp_record_fields(fs)
+            }
+        }
+        Variant(ref fs) => pp_variant_fields(fs),
+        Func(ref func) => pp_function(func),
+        Service(ref serv) => kwd("actor").append(pp_service(serv)),
+        _ => unreachable!(),
+    }
+}
+
+fn pp_label(id: &Label) -> RcDoc<'_> {
+    match id {
+        Label::Named(id) => escape(id),
+        Label::Id(n) | Label::Unnamed(n) => RcDoc::text(format!("_{}_", n)),
+    }
+}
+
+fn pp_record_field(field: &Field) -> RcDoc<'_> {
+    pp_label(&field.id)
+        .append(kwd(" :"))
+        .append(pp_ty(&field.ty))
+}
+
+fn pp_record_fields(fs: &[Field]) -> RcDoc<'_> {
+    let fields = concat(fs.iter().map(pp_record_field), ";");
+    enclose_space("{", fields, "}")
+}
+
+fn pp_variant_field(field: &Field) -> RcDoc<'_> {
+    let label = str("#").append(pp_label(&field.id));
+    if field.ty == Type::Null {
+        label
+    } else {
+        label.append(kwd(" :")).append(pp_ty(&field.ty))
+    }
+}
+
+fn pp_variant_fields(fs: &[Field]) -> RcDoc<'_> {
+    if fs.is_empty() {
+        return str("{#}");
+    }
+    let fields = concat(fs.iter().map(pp_variant_field), ";");
+    enclose_space("{", fields, "}")
+}
+
+fn pp_function(func: &Function) -> RcDoc<'_> {
+    let args = pp_args(&func.args);
+    let rets = pp_rets(&func.rets);
+    let is_oneway = func.modes.contains(&FuncMode::Oneway);
+    let modes = if func.is_query() {
+        kwd("shared query")
+    } else {
+        kwd("shared")
+    };
+    let rets = if is_oneway {
+        str("()")
+    } else {
+        kwd("async").append(rets)
+    };
+    modes
+        .append(args)
+        .append(" ->")
+        .append(RcDoc::space())
+        .append(rets)
+        .nest(INDENT_SPACE)
+}
+
+// A single argument of tuple type needs parentheses to not be taken as several arguments.
+fn pp_args(args: &[Type]) -> RcDoc<'_> {
+    match args {
+        [ty] if !is_tuple(ty) => pp_ty(ty),
+        _ => {
+            let doc = concat(args.iter().map(pp_ty), ",");
+            enclose("(", doc, ")")
+        }
+    }
+}
+
+fn pp_rets(rets: &[Type]) -> RcDoc<'_> {
+    pp_args(rets)
+}
+
+fn pp_service(serv: &[(String, Type)]) -> RcDoc<'_> {
+    let doc = concat(
+        serv.iter().map(|(id, func)| {
+            let func_doc = match func {
+                Type::Func(ref f) => pp_function(f),
+                Type::Var(_) => pp_ty(func),
+                _ => unreachable!(),
+            };
+            escape(id).append(kwd(" :")).append(func_doc)
+        }),
+        ";",
+    );
+    enclose_space("{", doc, "}")
+}
+
+fn pp_defs(env: &TypeEnv) -> Vec<RcDoc<'_>> {
+    env.0
+        .iter()
+        .map(|(id, ty)| {
+            kwd("public type")
+                .append(escape(id))
+                .append(kwd(" ="))
+                .append(pp_ty(ty))
+                .append(";")
+        })
+        .collect()
+}
+
+fn pp_actor(ty: &Type) -> RcDoc<'_> {
+    let doc = match ty {
+        Type::Service(ref serv) => kwd("actor").append(pp_service(serv)),
+        Type::Var(_) => pp_ty(ty),
+        _ => unreachable!(),
+    };
+    kwd("public type Self =").append(doc).append(";")
+}
+
+pub fn compile(env: &TypeEnv, actor: &Option<Type>) -> String {
+    let header = r#"// This is a generated Motoko binding.
+// Please use `import service "ic:canister_id"` instead to call canisters on the IC if possible.
+"#;
+    let mut items = pp_defs(env);
+    if let Some(actor) = actor {
+        items.push(pp_actor(actor));
+    }
+    let body = RcDoc::intersperse(items, RcDoc::hardline());
+    let doc = str("module {")
+        .append(RcDoc::hardline().append(body).nest(INDENT_SPACE))
+        .append(RcDoc::hardline())
+        .append("}");
+    format!("{}\n{}", header, doc.pretty(LINE_WIDTH))
+}
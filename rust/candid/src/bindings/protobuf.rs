@@ -0,0 +1,975 @@
+//! Protocol Buffers interop.
+//!
+//! [`compile`] turns type definitions into proto3 messages:
+//!
+//! * records become messages, whose field numbers are derived from the field ids, so that they
+//!   stay the same when fields are added or removed;
+//! * variants become messages with a single `oneof value`;
+//! * `opt t` becomes an `optional` field and `vec t` a `repeated` one. Nested options and
+//!   vectors, and the ones inside `oneof`, are wrapped in a message with a `value` field;
+//! * `nat` and `int` become the `CandidNat` and `CandidInt` messages, which hold the decimal
+//!   digits as a string;
+//! * `null`, `reserved` and `empty` become `google.protobuf.Empty`;
+//! * other named types become messages with a single `value` field;
+//! * the service becomes `service Service`, with a request and a response message per method.
+//!
+//! [`parse`] goes the other way, from a `.proto` file to a Candid program: messages become
+//! records, enums and messages made of a single `oneof` become variants, singular message
+//! fields and `optional` fields become `opt`, `repeated` fields become `vec`, and maps become
+//! `vec record { key; value }`. Methods with `idempotency_level = NO_SIDE_EFFECTS` are queries.
+use crate::parser::types::{
+    Binding, Dec, FuncMode, FuncType, IDLProg, IDLType, PrimType, TypeField,
+};
+use crate::parser::typing::TypeEnv;
+use crate::types::{Label, Type};
+use crate::{idl_hash, Error, Result};
+use std::collections::{BTreeMap, BTreeSet};
+
+static HEADER: &str = "// This is a generated Protocol Buffers binding.
+syntax = \"proto3\";
+";
+
+static NAT: &str = "
+message CandidNat {
+  // Decimal digits of a natural number
+  string value = 1;
+}
+";
+
+static INT: &str = "
+message CandidInt {
+  // Decimal digits of an integer, with an optional minus sign
+  string value = 1;
+}
+";
+
+static FUNC: &str = "
+message CandidFunc {
+  // Principal of the canister
+  bytes principal = 1;
+  string method = 2;
+}
+";
+
+fn is_valid_as_id(id: &str) -> bool {
+    if id.is_empty() || !id.is_ascii() {
+        return false;
+    }
+    for (i, c) in id.char_indices() {
+        if i == 0 {
+            if !c.is_ascii_alphabetic() && c != '_' {
+                return false;
+            }
+        } else if !c.is_ascii_alphanumeric() && c != '_' {
+            return false;
+        }
+    }
+    true
+}
+
+fn escape(id: &str) -> String {
+    if is_valid_as_id(id) {
+        id.to_string()
+    } else {
+        format!("_{}_", idl_hash(id))
+    }
+}
+
+fn field_name(id: &Label) -> String {
+    match id {
+        Label::Named(id) => escape(id),
+        Label::Id(n) | Label::Unnamed(n) => format!("_{}_", n),
+    }
+}
+
+// Candid field ids are 32-bit, while field numbers are below 2^29 and skip the range from
+// 19000 to 19999, which is reserved by the protobuf implementation.
+fn field_number(id: &Label) -> u32 {
+    let n = id.get_id() % 536_870_911 + 1;
+    if (19000..20000).contains(&n) {
+        n + 1000
+    } else {
+        n
+    }
+}
+
+fn camel_case(id: &str) -> String {
+    let mut res = String::new();
+    for part in id.split('_').filter(|p| !p.is_empty()) {
+        let mut chars = part.chars();
+        res.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+        res.extend(chars);
+    }
+    if res.is_empty() || res.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("M{}", res)
+    } else {
+        res
+    }
+}
+
+struct Writer<'a> {
+    env: &'a TypeEnv,
+    // Message names, which are unique in the whole file so that nested messages never shadow
+    // a top-level one.
+    used: BTreeSet<String>,
+    uses_empty: bool,
+    uses_nat: bool,
+    uses_int: bool,
+    uses_func: bool,
+}
+
+impl<'a> Writer<'a> {
+    fn fresh(&mut self, hint: &str) -> String {
+        let base = camel_case(hint);
+        let mut name = base.clone();
+        let mut i = 1;
+        while self.used.contains(&name) {
+            name = format!("{}{}", base, i);
+            i += 1;
+        }
+        self.used.insert(name.clone());
+        name
+    }
+
+    // The type of a singular field. Anonymous messages are added to `nested`.
+    fn ty(&mut self, t: &Type, hint: &str, indent: usize, nested: &mut String) -> Result<String> {
+        use Type::*;
+        Ok(match t {
+            Null | Reserved | Empty => {
+                self.uses_empty = true;
+                "google.protobuf.Empty".to_string()
+            }
+            Bool => "bool".to_string(),
+            Nat => {
+                self.uses_nat = true;
+                "CandidNat".to_string()
+            }
+            Int => {
+                self.uses_int = true;
+                "CandidInt".to_string()
+            }
+            Nat8 | Nat16 | Nat32 => "uint32".to_string(),
+            Nat64 => "uint64".to_string(),
+            Int8 | Int16 | Int32 => "int32".to_string(),
+            Int64 => "int64".to_string(),
+            Float32 => "float".to_string(),
+            Float64 => "double".to_string(),
+            Text => "string".to_string(),
+            Principal | Service(_) => "bytes".to_string(),
+            Vec(t) if **t == Nat8 => "bytes".to_string(),
+            Var(id) => id.to_string(),
+            Func(_) => {
+                self.uses_func = true;
+                "CandidFunc".to_string()
+            }
+            Record(_) | Variant(_) | Opt(_) | Vec(_) => {
+                let name = self.fresh(hint);
+                *nested += &self.message(&name, t, indent)?;
+                name
+            }
+            _ => unreachable!(),
+        })
+    }
+
+    fn field(
+        &mut self,
+        t: &Type,
+        hint: &str,
+        in_oneof: bool,
+        indent: usize,
+        nested: &mut String,
+    ) -> Result<String> {
+        Ok(match t {
+            Type::Opt(t) if !in_oneof => format!("optional {}", self.ty(t, hint, indent, nested)?),
+            Type::Vec(t) if !in_oneof && **t != Type::Nat8 => {
+                format!("repeated {}", self.ty(t, hint, indent, nested)?)
+            }
+            _ => self.ty(t, hint, indent, nested)?,
+        })
+    }
+
+    // Each field is given by its name, number and type. `indent` is the one of the message body.
+    fn fields(
+        &mut self,
+        fs: &[(String, u32, &Type)],
+        in_oneof: bool,
+        indent: usize,
+        nested: &mut String,
+    ) -> Result<String> {
+        let pad = "  ".repeat(indent + in_oneof as usize);
+        // Fields share the scope of nested messages, and shadow types with the same name.
+        let names: BTreeSet<_> = fs.iter().map(|f| f.0.clone()).collect();
+        self.used.extend(names.iter().cloned());
+        let mut res = String::new();
+        for (name, n, t) in fs.iter() {
+            let ty = self.field(t, name, in_oneof, indent, nested)?;
+            let ty = match ty.rsplit_once(' ') {
+                Some((label, ty)) if names.contains(ty) => format!("{} .{}", label, ty),
+                None if names.contains(&ty) => format!(".{}", ty),
+                _ => ty,
+            };
+            res += &format!("{}{} {} = {};\n", pad, ty, name, n);
+        }
+        Ok(res)
+    }
+
+    fn message(&mut self, name: &str, t: &Type, indent: usize) -> Result<String> {
+        let pad = "  ".repeat(indent);
+        let mut nested = String::new();
+        let body = match t {
+            Type::Record(fs) | Type::Variant(fs) => {
+                let mut numbers = BTreeMap::new();
+                for f in fs.iter() {
+                    let n = field_number(&f.id);
+                    if let Some(prev) = numbers.insert(n, &f.id) {
+                        return Err(Error::msg(format!(
+                            "fields {} and {} of {} have the same field number {}",
+                            prev, f.id, name, n
+                        )));
+                    }
+                }
+                let fs: std::vec::Vec<_> = fs
+                    .iter()
+                    .map(|f| (field_name(&f.id), field_number(&f.id), &f.ty))
+                    .collect();
+                match t {
+                    Type::Variant(_) if fs.is_empty() => String::new(),
+                    Type::Variant(_) => format!(
+                        "{}  oneof value {{\n{}{}  }}\n",
+                        pad,
+                        self.fields(&fs, true, indent + 1, &mut nested)?,
+                        pad
+                    ),
+                    _ => self.fields(&fs, false, indent + 1, &mut nested)?,
+                }
+            }
+            _ => self.fields(
+                &[("value".to_string(), 1, t)],
+                false,
+                indent + 1,
+                &mut nested,
+            )?,
+        };
+        if nested.is_empty() && body.is_empty() {
+            return Ok(format!("{}message {} {{}}\n", pad, name));
+        }
+        Ok(format!(
+            "{}message {} {{\n{}{}{}}}\n",
+            pad, name, nested, body, pad
+        ))
+    }
+
+    fn args(&mut self, args: &[Type], hint: &str, prefix: &str) -> Result<(String, String)> {
+        if args.is_empty() {
+            self.uses_empty = true;
+            return Ok(("google.protobuf.Empty".to_string(), String::new()));
+        }
+        let name = self.fresh(hint);
+        let fs: std::vec::Vec<_> = args
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (format!("{}{}", prefix, i), i as u32 + 1, t))
+            .collect();
+        let mut nested = String::new();
+        let body = self.fields(&fs, false, 1, &mut nested)?;
+        Ok((
+            name.clone(),
+            format!("\nmessage {} {{\n{}{}}}\n", name, nested, body),
+        ))
+    }
+
+    fn service(&mut self, name: &str, actor: &Type) -> Result<String> {
+        let mut messages = String::new();
+        let mut rpcs = String::new();
+        for (id, t) in self.env.as_service(actor)?.iter() {
+            let func = self.env.as_func(t)?;
+            let method = escape(id);
+            let (req, msg) = self.args(&func.args, &format!("{}_request", method), "arg")?;
+            messages += &msg;
+            let (res, msg) = if func.modes.contains(&FuncMode::Oneway) {
+                self.args(&[], "", "")?
+            } else {
+                self.args(&func.rets, &format!("{}_response", method), "ret")?
+            };
+            messages += &msg;
+            rpcs += &format!("  rpc {} ({}) returns ({})", method, req, res);
+            if func.is_query() {
+                rpcs += " {\n    option idempotency_level = NO_SIDE_EFFECTS;\n  }\n";
+            } else {
+                rpcs += ";\n";
+            }
+        }
+        Ok(format!("{}\nservice {} {{\n{}}}\n", messages, name, rpcs))
+    }
+}
+
+/// Converts the type definitions and the service to a proto3 file.
+pub fn compile(env: &TypeEnv, actor: &Option<Type>) -> Result<String> {
+    let mut used: BTreeSet<String> = env.0.keys().cloned().collect();
+    for name in ["CandidNat", "CandidInt", "CandidFunc"].iter() {
+        used.insert(name.to_string());
+    }
+    let mut w = Writer {
+        env,
+        used,
+        uses_empty: false,
+        uses_nat: false,
+        uses_int: false,
+        uses_func: false,
+    };
+    let service = w.fresh("Service");
+    let mut body = String::new();
+    for (id, t) in env.0.iter() {
+        body += "\n";
+        body += &w.message(id, t, 0)?;
+    }
+    if let Some(actor) = actor {
+        body += &w.service(&service, actor)?;
+    }
+    let mut res = HEADER.to_string();
+    if w.uses_empty {
+        res += "\nimport \"google/protobuf/empty.proto\";\n";
+    }
+    for (used, helper) in [(w.uses_nat, NAT), (w.uses_int, INT), (w.uses_func, FUNC)].iter() {
+        if *used {
+            res += helper;
+        }
+    }
+    Ok(res + &body)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Sym(char),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '/' {
+            chars.next();
+            match chars.next() {
+                Some('/') => {
+                    for c in chars.by_ref() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                Some('*') => {
+                    let mut prev = ' ';
+                    loop {
+                        match chars.next() {
+                            Some('/') if prev == '*' => break,
+                            Some(c) => prev = c,
+                            None => return Err(Error::msg("unclosed comment")),
+                        }
+                    }
+                }
+                _ => return Err(Error::msg("unexpected character /")),
+            }
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '.' {
+            let mut id = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                    id.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Ident(id));
+        } else if c.is_ascii_digit() || c == '-' || c == '+' {
+            let mut n = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_alphanumeric() || "-+.".contains(c) {
+                    n.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Number(n));
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some('\\') => s.extend(chars.next()),
+                    Some(q) if q == c => break,
+                    Some(c) => s.push(c),
+                    None => return Err(Error::msg("unclosed string literal")),
+                }
+            }
+            tokens.push(Token::Str(s));
+        } else if "{}()[]<>=;,:".contains(c) {
+            tokens.push(Token::Sym(c));
+            chars.next();
+        } else {
+            return Err(Error::msg(format!("unexpected character {}", c)));
+        }
+    }
+    Ok(tokens)
+}
+
+enum Item {
+    Message(Message),
+    Enum(String, Vec<String>),
+}
+
+struct Message {
+    name: String,
+    members: Vec<Member>,
+    nested: Vec<Item>,
+}
+
+enum Member {
+    Field(FieldDef),
+    Oneof(String, Vec<FieldDef>),
+}
+
+enum FieldType {
+    Named(String),
+    Map(String, String),
+}
+
+struct FieldDef {
+    label: Option<String>,
+    ty: FieldType,
+    name: String,
+}
+
+struct Rpc {
+    name: String,
+    arg: String,
+    ret: String,
+    is_query: bool,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+    fn next(&mut self) -> Result<Token> {
+        let tok = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| Error::msg("unexpected end of file"))?;
+        self.pos += 1;
+        Ok(tok)
+    }
+    fn is_sym(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Sym(c))
+    }
+    fn is_kwd(&self, kwd: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(id)) if id == kwd)
+    }
+    fn expect(&mut self, c: char) -> Result<()> {
+        match self.next()? {
+            Token::Sym(s) if s == c => Ok(()),
+            tok => Err(Error::msg(format!("expected {}, found {:?}", c, tok))),
+        }
+    }
+    fn ident(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Ident(id) => Ok(id),
+            tok => Err(Error::msg(format!(
+                "expected an identifier, found {:?}",
+                tok
+            ))),
+        }
+    }
+    // Skips a statement such as `option` or `reserved`, up to its `;`.
+    fn skip_statement(&mut self) -> Result<()> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Sym('{') | Token::Sym('[') | Token::Sym('(') => depth += 1,
+                Token::Sym('}') | Token::Sym(']') | Token::Sym(')') => depth -= 1,
+                Token::Sym(';') if depth == 0 => return Ok(()),
+                _ => (),
+            }
+        }
+    }
+    // Skips the `[...]` options of a field.
+    fn skip_options(&mut self) -> Result<()> {
+        if self.is_sym('[') {
+            let mut depth = 0;
+            loop {
+                match self.next()? {
+                    Token::Sym('[') => depth += 1,
+                    Token::Sym(']') => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Ok(());
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn field(&mut self, label: Option<String>) -> Result<FieldDef> {
+        let ty = if self.is_kwd("map") && self.tokens.get(self.pos + 1) == Some(&Token::Sym('<')) {
+            self.pos += 2;
+            let key = self.ident()?;
+            self.expect(',')?;
+            let value = self.ident()?;
+            self.expect('>')?;
+            FieldType::Map(key, value)
+        } else {
+            FieldType::Named(self.ident()?)
+        };
+        let name = self.ident()?;
+        self.expect('=')?;
+        self.next()?;
+        self.skip_options()?;
+        self.expect(';')?;
+        Ok(FieldDef { label, ty, name })
+    }
+
+    fn message(&mut self) -> Result<Message> {
+        let name = self.ident()?;
+        self.expect('{')?;
+        let mut members = Vec::new();
+        let mut nested = Vec::new();
+        while !self.is_sym('}') {
+            match self.next()? {
+                Token::Sym(';') => (),
+                Token::Ident(kwd) if kwd == "message" => {
+                    nested.push(Item::Message(self.message()?))
+                }
+                Token::Ident(kwd) if kwd == "enum" => nested.push(self.enumeration()?),
+                Token::Ident(kwd) if kwd == "oneof" => {
+                    let name = self.ident()?;
+                    self.expect('{')?;
+                    let mut fields = Vec::new();
+                    while !self.is_sym('}') {
+                        if self.is_kwd("option") {
+                            self.skip_statement()?;
+                        } else if self.is_sym(';') {
+                            self.pos += 1;
+                        } else {
+                            fields.push(self.field(None)?);
+                        }
+                    }
+                    self.expect('}')?;
+                    members.push(Member::Oneof(name, fields));
+                }
+                Token::Ident(kwd)
+                    if ["option", "reserved", "extensions"].contains(&kwd.as_str()) =>
+                {
+                    self.skip_statement()?
+                }
+                Token::Ident(kwd) if kwd == "extend" || kwd == "group" => {
+                    return Err(Error::msg(format!("{} is not supported", kwd)))
+                }
+                Token::Ident(kwd)
+                    if ["optional", "repeated", "required"].contains(&kwd.as_str()) =>
+                {
+                    members.push(Member::Field(self.field(Some(kwd))?))
+                }
+                Token::Ident(_) => {
+                    self.pos -= 1;
+                    members.push(Member::Field(self.field(None)?));
+                }
+                tok => {
+                    return Err(Error::msg(format!(
+                        "unexpected {:?} in message {}",
+                        tok, name
+                    )))
+                }
+            }
+        }
+        self.expect('}')?;
+        Ok(Message {
+            name,
+            members,
+            nested,
+        })
+    }
+
+    fn enumeration(&mut self) -> Result<Item> {
+        let name = self.ident()?;
+        self.expect('{')?;
+        let mut values = Vec::new();
+        while !self.is_sym('}') {
+            if self.is_sym(';') {
+                self.pos += 1;
+            } else if self.is_kwd("option") || self.is_kwd("reserved") {
+                self.skip_statement()?;
+            } else {
+                values.push(self.ident()?);
+                self.skip_statement()?;
+            }
+        }
+        self.expect('}')?;
+        Ok(Item::Enum(name, values))
+    }
+
+    fn rpc_type(&mut self) -> Result<String> {
+        self.expect('(')?;
+        if self.is_kwd("stream") {
+            return Err(Error::msg("streaming methods are not supported"));
+        }
+        let ty = self.ident()?;
+        self.expect(')')?;
+        Ok(ty)
+    }
+
+    fn service(&mut self) -> Result<Vec<Rpc>> {
+        self.ident()?;
+        self.expect('{')?;
+        let mut rpcs = Vec::new();
+        while !self.is_sym('}') {
+            match self.next()? {
+                Token::Sym(';') => (),
+                Token::Ident(kwd) if kwd == "option" => self.skip_statement()?,
+                Token::Ident(kwd) if kwd == "rpc" => {
+                    let name = self.ident()?;
+                    let arg = self.rpc_type()?;
+                    if !self.is_kwd("returns") {
+                        return Err(Error::msg(format!("expected returns in rpc {}", name)));
+                    }
+                    self.pos += 1;
+                    let ret = self.rpc_type()?;
+                    let mut is_query = false;
+                    if self.is_sym('{') {
+                        self.pos += 1;
+                        while !self.is_sym('}') {
+                            let start = self.pos;
+                            self.skip_statement()?;
+                            is_query |= self.tokens[start..self.pos]
+                                == [
+                                    Token::Ident("option".to_string()),
+                                    Token::Ident("idempotency_level".to_string()),
+                                    Token::Sym('='),
+                                    Token::Ident("NO_SIDE_EFFECTS".to_string()),
+                                    Token::Sym(';'),
+                                ];
+                        }
+                        self.pos += 1;
+                    } else {
+                        self.expect(';')?;
+                    }
+                    rpcs.push(Rpc {
+                        name,
+                        arg,
+                        ret,
+                        is_query,
+                    });
+                }
+                tok => return Err(Error::msg(format!("unexpected {:?} in service", tok))),
+            }
+        }
+        self.expect('}')?;
+        Ok(rpcs)
+    }
+}
+
+struct Converter {
+    package: String,
+    // Full names of the messages and enums, without the leading dot, and whether it is an enum
+    names: BTreeMap<String, bool>,
+    decs: Vec<Dec>,
+}
+
+fn prim(t: PrimType) -> IDLType {
+    IDLType::PrimT(t)
+}
+
+fn scalar(name: &str) -> Option<IDLType> {
+    Some(match name {
+        "double" => prim(PrimType::Float64),
+        "float" => prim(PrimType::Float32),
+        "int32" | "sint32" | "sfixed32" => prim(PrimType::Int32),
+        "int64" | "sint64" | "sfixed64" => prim(PrimType::Int64),
+        "uint32" | "fixed32" => prim(PrimType::Nat32),
+        "uint64" | "fixed64" => prim(PrimType::Nat64),
+        "bool" => prim(PrimType::Bool),
+        "string" => prim(PrimType::Text),
+        "bytes" => IDLType::VecT(Box::new(prim(PrimType::Nat8))),
+        _ => return None,
+    })
+}
+
+impl Converter {
+    fn register(&mut self, scope: &str, items: &[Item]) {
+        for item in items.iter() {
+            let name = match item {
+                Item::Message(m) => &m.name,
+                Item::Enum(name, _) => name,
+            };
+            let full = format!("{}{}", scope, name);
+            if let Item::Message(m) = item {
+                self.register(&format!("{}.", full), &m.nested);
+            }
+            self.names.insert(full, matches!(item, Item::Enum(..)));
+        }
+    }
+
+    // The Candid name of a message or enum drops the package, and joins nested names with `_`.
+    fn candid_name(&self, full: &str) -> String {
+        let name = if self.package.is_empty() {
+            full
+        } else {
+            &full[self.package.len() + 1..]
+        };
+        name.replace('.', "_")
+    }
+
+    // Resolves a type name in `scope` as protoc does, from the innermost scope outwards.
+    // Returns the Candid type, and whether it is a message type, which has presence, unlike
+    // scalars and enums.
+    fn resolve(&self, scope: &str, name: &str) -> Result<(IDLType, bool)> {
+        if let Some(t) = scalar(name) {
+            return Ok((t, false));
+        }
+        let mut found = None;
+        if let Some(full) = name.strip_prefix('.') {
+            if self.names.contains_key(full) {
+                found = Some(full.to_string());
+            }
+        } else {
+            let mut scope = scope.to_string();
+            loop {
+                let full = if scope.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}.{}", scope, name)
+                };
+                if self.names.contains_key(&full) {
+                    found = Some(full);
+                    break;
+                }
+                match scope.rfind('.') {
+                    Some(i) => scope.truncate(i),
+                    None if scope.is_empty() => break,
+                    None => scope.clear(),
+                }
+            }
+        }
+        let full = match found {
+            Some(full) => full,
+            None => {
+                let name = name.trim_start_matches('.');
+                return match name {
+                    "google.protobuf.Empty" => Ok((prim(PrimType::Null), false)),
+                    "google.protobuf.StringValue" => Ok((prim(PrimType::Text), true)),
+                    "google.protobuf.BytesValue" => Ok((scalar("bytes").unwrap(), true)),
+                    "google.protobuf.BoolValue" => Ok((prim(PrimType::Bool), true)),
+                    "google.protobuf.Int32Value" => Ok((prim(PrimType::Int32), true)),
+                    "google.protobuf.Int64Value" => Ok((prim(PrimType::Int64), true)),
+                    "google.protobuf.UInt32Value" => Ok((prim(PrimType::Nat32), true)),
+                    "google.protobuf.UInt64Value" => Ok((prim(PrimType::Nat64), true)),
+                    "google.protobuf.FloatValue" => Ok((prim(PrimType::Float32), true)),
+                    "google.protobuf.DoubleValue" => Ok((prim(PrimType::Float64), true)),
+                    _ => Err(Error::msg(format!("unknown type {}", name))),
+                };
+            }
+        };
+        let name = self.candid_name(&full);
+        Ok(match name.as_str() {
+            "CandidNat" => (prim(PrimType::Nat), false),
+            "CandidInt" => (prim(PrimType::Int), false),
+            _ => (IDLType::VarT(name), !self.names[&full]),
+        })
+    }
+
+    fn field(&self, scope: &str, f: &FieldDef, in_oneof: bool) -> Result<TypeField> {
+        let typ = match &f.ty {
+            FieldType::Map(key, value) => {
+                let key = self.resolve(scope, key)?.0;
+                let value = self.resolve(scope, value)?.0;
+                let entry = IDLType::RecordT(vec![
+                    TypeField {
+                        label: Label::Unnamed(0),
+                        typ: key,
+                        docs: vec![],
+                    },
+                    TypeField {
+                        label: Label::Unnamed(1),
+                        typ: value,
+                        docs: vec![],
+                    },
+                ]);
+                IDLType::VecT(Box::new(entry))
+            }
+            FieldType::Named(name) => {
+                let (t, has_presence) = self.resolve(scope, name)?;
+                match f.label.as_deref() {
+                    Some("repeated") => IDLType::VecT(Box::new(t)),
+                    Some("optional") => IDLType::OptT(Box::new(t)),
+                    _ if has_presence && !in_oneof => IDLType::OptT(Box::new(t)),
+                    _ => t,
+                }
+            }
+        };
+        Ok(TypeField {
+            label: Label::Named(f.name.clone()),
+            typ,
+            docs: vec![],
+        })
+    }
+
+    fn convert(&mut self, scope: &str, items: &[Item]) -> Result<()> {
+        for item in items.iter() {
+            match item {
+                Item::Enum(name, values) => {
+                    let fs = values
+                        .iter()
+                        .map(|v| TypeField {
+                            label: Label::Named(v.clone()),
+                            typ: prim(PrimType::Null),
+                            docs: vec![],
+                        })
+                        .collect();
+                    let full = format!("{}{}", scope, name);
+                    self.decs.push(Dec::TypD(Binding {
+                        id: self.candid_name(&full),
+                        typ: IDLType::VariantT(fs),
+                        docs: vec![],
+                    }));
+                }
+                Item::Message(m) => {
+                    let full = format!("{}{}", scope, m.name);
+                    let name = self.candid_name(&full);
+                    if name == "CandidNat" || name == "CandidInt" {
+                        continue;
+                    }
+                    let typ = match m.members.as_slice() {
+                        // A message made of a single `oneof` is a variant.
+                        [Member::Oneof(_, fs)] => {
+                            let fs = fs
+                                .iter()
+                                .map(|f| self.field(&full, f, true))
+                                .collect::<Result<_>>()?;
+                            IDLType::VariantT(fs)
+                        }
+                        members => {
+                            let mut fs = Vec::new();
+                            for member in members.iter() {
+                                match member {
+                                    Member::Field(f) => fs.push(self.field(&full, f, false)?),
+                                    Member::Oneof(name, cases) => {
+                                        let cases = cases
+                                            .iter()
+                                            .map(|f| self.field(&full, f, true))
+                                            .collect::<Result<_>>()?;
+                                        fs.push(TypeField {
+                                            label: Label::Named(name.clone()),
+                                            typ: IDLType::OptT(Box::new(IDLType::VariantT(cases))),
+                                            docs: vec![],
+                                        });
+                                    }
+                                }
+                            }
+                            IDLType::RecordT(fs)
+                        }
+                    };
+                    self.decs.push(Dec::TypD(Binding {
+                        id: name,
+                        typ,
+                        docs: vec![],
+                    }));
+                    self.convert(&format!("{}.", full), &m.nested)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn args(&self, name: &str) -> Result<Vec<IDLType>> {
+        let scope = self.package.clone();
+        match self.resolve(&scope, name)?.0 {
+            IDLType::PrimT(PrimType::Null) => Ok(vec![]),
+            t => Ok(vec![t]),
+        }
+    }
+}
+
+/// Parses a `.proto` file as a Candid program, with a type definition per message and enum,
+/// and the service, if any, as the main actor. Imports other than the well-known types
+/// `google.protobuf.Empty` and `google.protobuf.*Value` are not followed.
+pub fn parse(input: &str) -> Result<IDLProg> {
+    let mut p = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+    };
+    let mut package = String::new();
+    let mut items = Vec::new();
+    let mut services = Vec::new();
+    while let Some(tok) = p.peek().cloned() {
+        p.pos += 1;
+        match tok {
+            Token::Sym(';') => (),
+            Token::Ident(kwd) if kwd == "package" => {
+                package = p.ident()?;
+                p.expect(';')?;
+            }
+            Token::Ident(kwd)
+                if ["syntax", "edition", "import", "option"].contains(&kwd.as_str()) =>
+            {
+                p.skip_statement()?
+            }
+            Token::Ident(kwd) if kwd == "message" => items.push(Item::Message(p.message()?)),
+            Token::Ident(kwd) if kwd == "enum" => items.push(p.enumeration()?),
+            Token::Ident(kwd) if kwd == "service" => services.push(p.service()?),
+            tok => return Err(Error::msg(format!("unexpected {:?}", tok))),
+        }
+    }
+    let mut c = Converter {
+        package: package.clone(),
+        names: BTreeMap::new(),
+        decs: Vec::new(),
+    };
+    let scope = if package.is_empty() {
+        String::new()
+    } else {
+        format!("{}.", package)
+    };
+    c.register(&scope, &items);
+    c.convert(&scope, &items)?;
+    let actor = match services.as_slice() {
+        [] => None,
+        [rpcs] => {
+            let mut meths = Vec::new();
+            for rpc in rpcs.iter() {
+                let func = FuncType {
+                    modes: if rpc.is_query {
+                        vec![FuncMode::Query]
+                    } else {
+                        vec![]
+                    },
+                    args: c.args(&rpc.arg)?,
+                    rets: c.args(&rpc.ret)?,
+                };
+                meths.push(Binding {
+                    id: rpc.name.clone(),
+                    typ: IDLType::FuncT(func),
+                    docs: vec![],
+                });
+            }
+            Some(IDLType::ServT(meths))
+        }
+        _ => return Err(Error::msg("only one service per file is supported")),
+    };
+    Ok(IDLProg {
+        decs: c.decs,
+        actor,
+    })
+}
@@ -0,0 +1,473 @@
+use super::analysis::{chase_actor, infer_rec};
+use crate::parser::types::FuncMode;
+use crate::parser::typing::TypeEnv;
+use crate::types::{Field, Label, Type};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Python keywords, and the names used by the generated code.
+static RESERVED: [&str; 58] = [
+    "False",
+    "None",
+    "True",
+    "and",
+    "as",
+    "assert",
+    "async",
+    "await",
+    "break",
+    "class",
+    "continue",
+    "def",
+    "del",
+    "elif",
+    "else",
+    "except",
+    "finally",
+    "for",
+    "from",
+    "global",
+    "if",
+    "import",
+    "in",
+    "is",
+    "lambda",
+    "nonlocal",
+    "not",
+    "or",
+    "pass",
+    "raise",
+    "return",
+    "try",
+    "while",
+    "with",
+    "yield",
+    "int",
+    "str",
+    "bytes",
+    "bool",
+    "float",
+    "object",
+    "tuple",
+    "self",
+    "typing",
+    "dataclasses",
+    "Range",
+    "Agent",
+    "ServiceClient",
+    "nat",
+    "nat8",
+    "nat16",
+    "nat32",
+    "nat64",
+    "int8",
+    "int16",
+    "int32",
+    "int64",
+    "principal",
+];
+
+static PRELUDE: &str = r#"# This is a generated Python binding. It requires Python 3.9 or later.
+import dataclasses
+import typing
+
+
+@dataclasses.dataclass(frozen=True)
+class Range:
+    """Inclusive bounds of an integer type, `None` for unbounded."""
+
+    min: typing.Optional[int]
+    max: typing.Optional[int]
+
+
+nat = typing.Annotated[int, Range(0, None)]
+nat8 = typing.Annotated[int, Range(0, 2**8 - 1)]
+nat16 = typing.Annotated[int, Range(0, 2**16 - 1)]
+nat32 = typing.Annotated[int, Range(0, 2**32 - 1)]
+nat64 = typing.Annotated[int, Range(0, 2**64 - 1)]
+int8 = typing.Annotated[int, Range(-(2**7), 2**7 - 1)]
+int16 = typing.Annotated[int, Range(-(2**15), 2**15 - 1)]
+int32 = typing.Annotated[int, Range(-(2**31), 2**31 - 1)]
+int64 = typing.Annotated[int, Range(-(2**63), 2**63 - 1)]
+# Textual representation of a principal
+principal = str
+"#;
+
+static AGENT: &str = r#"
+
+class Agent(typing.Protocol):
+    """Sends a call to a canister. `mode` is "query", "update" or "oneway"."""
+
+    def call(
+        self,
+        canister_id: principal,
+        method: str,
+        args: typing.List[typing.Any],
+        mode: str,
+    ) -> typing.List[typing.Any]:
+        ...
+"#;
+
+// The definition of tuple is language specific.
+fn is_tuple(fs: &[Field]) -> bool {
+    fs.iter()
+        .enumerate()
+        .all(|(i, field)| field.id.get_id() == (i as u32))
+}
+
+fn is_valid_as_id(id: &str) -> bool {
+    if id.is_empty() || !id.is_ascii() {
+        return false;
+    }
+    for (i, c) in id.char_indices() {
+        if i == 0 {
+            if !c.is_ascii_alphabetic() && c != '_' {
+                return false;
+            }
+        } else if !c.is_ascii_alphanumeric() && c != '_' {
+            return false;
+        }
+    }
+    true
+}
+
+fn escape(id: &str) -> String {
+    if RESERVED.contains(&id) {
+        format!("{}_", id)
+    } else if is_valid_as_id(id) {
+        id.to_string()
+    } else {
+        format!("_{}_", crate::idl_hash(id))
+    }
+}
+
+fn field_name(id: &Label) -> String {
+    match id {
+        Label::Named(id) => escape(id),
+        Label::Id(n) | Label::Unnamed(n) => format!("_{}_", n),
+    }
+}
+
+fn py_str(s: &str) -> String {
+    let mut res = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '"' => res.push_str("\\\""),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if c.is_control() => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+// The Python type of the primitive and reference types.
+fn prim(t: &Type) -> String {
+    use Type::*;
+    match t {
+        Null => "None",
+        Bool => "bool",
+        Nat => "nat",
+        Int => "int",
+        Nat8 => "nat8",
+        Nat16 => "nat16",
+        Nat32 => "nat32",
+        Nat64 => "nat64",
+        Int8 => "int8",
+        Int16 => "int16",
+        Int32 => "int32",
+        Int64 => "int64",
+        Float32 | Float64 => "float",
+        Text => "str",
+        Reserved => "typing.Any",
+        Empty => "typing.NoReturn",
+        Principal => "principal",
+        // A reference to a method, given by the canister id and the method name
+        Func(_) => "typing.Tuple[principal, str]",
+        Service(_) => "principal",
+        _ => unreachable!(),
+    }
+    .to_string()
+}
+
+struct Writer<'a> {
+    env: &'a TypeEnv,
+    recs: BTreeSet<&'a str>,
+    used: BTreeSet<String>,
+    // The plain dict counterparts of the definitions that contain records or variants.
+    dicts: BTreeMap<String, String>,
+    out: String,
+}
+
+impl<'a> Writer<'a> {
+    fn fresh(&mut self, base: &str) -> String {
+        let mut name = base.to_string();
+        let mut i = 1;
+        while self.used.contains(&name) {
+            name = format!("{}_{}", base, i);
+            i += 1;
+        }
+        self.used.insert(name.clone());
+        name
+    }
+
+    // Whether the type has records or variants, which are not plain dicts as dataclasses.
+    fn has_dict(&self, t: &'a Type, seen: &mut BTreeSet<&'a str>) -> bool {
+        use Type::*;
+        match t {
+            Var(id) => {
+                seen.insert(id.as_str()) && self.has_dict(self.env.find_type(id).unwrap(), seen)
+            }
+            Opt(t) | Vec(t) => self.has_dict(t, seen),
+            Record(fs) if is_tuple(fs) => fs.iter().any(|f| self.has_dict(&f.ty, seen)),
+            Record(_) | Variant(_) => true,
+            _ => false,
+        }
+    }
+
+    fn ty(&mut self, t: &Type, path: &str) -> String {
+        self.tys(t, path).0
+    }
+
+    // The type of the dataclasses, and the type of the plain dicts, where records and
+    // variants are their `*Dict` types. Records and variants without a name are hoisted
+    // as classes named after `path`.
+    fn tys(&mut self, t: &Type, path: &str) -> (String, String) {
+        use Type::*;
+        match t {
+            Var(id) => {
+                let quote = |name: &str| {
+                    if self.recs.contains(id.as_str()) {
+                        py_str(name)
+                    } else {
+                        name.to_string()
+                    }
+                };
+                let name = escape(id);
+                let dict = self.dicts.get(&name).map_or(quote(&name), |d| quote(d));
+                (quote(&name), dict)
+            }
+            Opt(t) => {
+                let (ty, dict) = self.tys(t, path);
+                (
+                    format!("typing.Optional[{}]", ty),
+                    format!("typing.Optional[{}]", dict),
+                )
+            }
+            Vec(t) if **t == Nat8 => ("bytes".to_string(), "bytes".to_string()),
+            Vec(t) => {
+                let (ty, dict) = self.tys(t, path);
+                (
+                    format!("typing.List[{}]", ty),
+                    format!("typing.List[{}]", dict),
+                )
+            }
+            Record(fs) if is_tuple(fs) => {
+                let (tys, dicts): (std::vec::Vec<_>, std::vec::Vec<_>) = fs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| self.tys(&f.ty, &format!("{}_{}", path, i)))
+                    .unzip();
+                if fs.is_empty() {
+                    let ty = "typing.Tuple[()]".to_string();
+                    (ty.clone(), ty)
+                } else {
+                    (
+                        format!("typing.Tuple[{}]", tys.join(", ")),
+                        format!("typing.Tuple[{}]", dicts.join(", ")),
+                    )
+                }
+            }
+            Record(fs) => {
+                let name = self.fresh(path);
+                let dict = self.record(&name, fs);
+                (name, dict)
+            }
+            Variant(fs) => {
+                let name = self.fresh(path);
+                let dict = self.variant(&name, fs);
+                (name, dict)
+            }
+            _ => {
+                let ty = prim(t);
+                (ty.clone(), ty)
+            }
+        }
+    }
+
+    // Returns the name of the plain dict class.
+    fn dict_name(&mut self, name: &str) -> String {
+        match self.dicts.get(name) {
+            Some(dict) => dict.clone(),
+            None => self.fresh(&format!("{}Dict", name)),
+        }
+    }
+
+    fn record(&mut self, name: &str, fs: &[Field]) -> String {
+        let dict = self.dict_name(name);
+        let (fields, dict_fields): (std::vec::Vec<_>, std::vec::Vec<_>) = fs
+            .iter()
+            .map(|f| {
+                let id = field_name(&f.id);
+                let (ty, dict) = self.tys(&f.ty, &format!("{}_{}", name, id));
+                (
+                    format!("    {}: {}\n", id, ty),
+                    format!("    {}: {}\n", id, dict),
+                )
+            })
+            .unzip();
+        self.out += &format!(
+            "\n\n@dataclasses.dataclass\nclass {}:\n{}",
+            name,
+            fields.concat()
+        );
+        self.out += &format!(
+            "\n\nclass {}(typing.TypedDict):\n    \"\"\"`{}` as a plain dict.\"\"\"\n\n{}",
+            dict,
+            name,
+            dict_fields.concat()
+        );
+        dict
+    }
+
+    fn variant(&mut self, name: &str, fs: &[Field]) -> String {
+        let dict = self.dict_name(name);
+        self.out += &format!(
+            "\n\nclass {}:\n    \"\"\"Base class of the `{}_*` cases.\"\"\"\n",
+            name, name
+        );
+        let mut dict_fields = String::new();
+        for f in fs.iter() {
+            let id = field_name(&f.id);
+            let case = self.fresh(&format!("{}_{}", name, id));
+            let value = if f.ty == Type::Null {
+                dict_fields += &format!("    {}: None\n", id);
+                String::new()
+            } else {
+                let (ty, dict) = self.tys(&f.ty, &format!("{}_value", case));
+                dict_fields += &format!("    {}: {}\n", id, dict);
+                format!("    value: {}\n", ty)
+            };
+            let tag = match &f.id {
+                Label::Named(id) => format!("typing.ClassVar[str] = {}", py_str(id)),
+                Label::Id(n) | Label::Unnamed(n) => format!("typing.ClassVar[int] = {}", n),
+            };
+            self.out += &format!(
+                "\n\n@dataclasses.dataclass\nclass {}({}):\n{}    tag: {}\n",
+                case, name, value, tag
+            );
+        }
+        self.out += &format!(
+            "\n\nclass {}(typing.TypedDict, total=False):\n    \"\"\"`{}` as a plain dict, with the case as its only key.\"\"\"\n\n{}",
+            dict, name, dict_fields
+        );
+        dict
+    }
+
+    fn def(&mut self, id: &str) {
+        let ty = self.env.find_type(id).unwrap();
+        let name = escape(id);
+        match ty {
+            Type::Record(fs) if !is_tuple(fs) => {
+                self.record(&name, fs);
+            }
+            Type::Variant(fs) => {
+                self.variant(&name, fs);
+            }
+            _ => {
+                let (ty, dict) = self.tys(ty, &format!("{}_inner", name));
+                self.out += &format!("\n\n{} = {}\n", name, ty);
+                if let Some(name) = self.dicts.get(&name) {
+                    self.out += &format!("\n\n{} = {}\n", name, dict);
+                }
+            }
+        }
+    }
+
+    fn args(&mut self, args: &[Type], path: &str) -> std::vec::Vec<String> {
+        args.iter()
+            .enumerate()
+            .map(|(i, t)| self.ty(t, &format!("{}{}", path, i)))
+            .collect()
+    }
+
+    fn service(&mut self, actor: &'a Type) {
+        let mut body = String::from(
+            "\n\nclass ServiceClient:\n    \"\"\"Client of the service, which sends the calls through `agent`.\"\"\"\n\n    def __init__(self, agent: Agent, canister_id: principal) -> None:\n        self._agent = agent\n        self._canister_id = canister_id\n",
+        );
+        for (id, t) in self.env.as_service(actor).unwrap() {
+            let func = self.env.as_func(t).unwrap();
+            let name = escape(id);
+            let args = self.args(&func.args, &format!("{}_arg", name));
+            let rets = self.args(&func.rets, &format!("{}_ret", name));
+            let params: String = args
+                .iter()
+                .enumerate()
+                .map(|(i, t)| format!(", arg{}: {}", i, t))
+                .collect();
+            let values: std::vec::Vec<_> = (0..args.len()).map(|i| format!("arg{}", i)).collect();
+            let mode = if func.modes.contains(&FuncMode::Oneway) {
+                "oneway"
+            } else if func.is_query() {
+                "query"
+            } else {
+                "update"
+            };
+            let call = format!(
+                "self._agent.call(self._canister_id, {}, [{}], {})",
+                py_str(id),
+                values.join(", "),
+                py_str(mode)
+            );
+            let (ret, call) = match rets.as_slice() {
+                [] => ("None".to_string(), call),
+                [t] => (t.clone(), format!("return {}[0]", call)),
+                _ => (
+                    format!("typing.Tuple[{}]", rets.join(", ")),
+                    format!("return tuple({})", call),
+                ),
+            };
+            body += &format!(
+                "\n    def {}(self{}) -> {}:\n        {}\n",
+                name, params, ret, call
+            );
+        }
+        self.out += AGENT;
+        self.out += &body;
+    }
+}
+
+pub fn compile(env: &TypeEnv, actor: &Option<Type>) -> String {
+    let def_list: Vec<&str> = match actor {
+        Some(actor) => chase_actor(env, actor).unwrap(),
+        None => env.0.keys().map(|id| id.as_str()).collect(),
+    };
+    let recs = infer_rec(env, &def_list).unwrap();
+    let mut used: BTreeSet<String> = RESERVED.iter().map(|s| s.to_string()).collect();
+    used.extend(def_list.iter().map(|id| escape(id)));
+    let mut w = Writer {
+        env,
+        recs,
+        used,
+        dicts: BTreeMap::new(),
+        out: PRELUDE.to_string(),
+    };
+    for id in def_list.iter() {
+        let ty = env.find_type(id).unwrap();
+        if w.has_dict(ty, &mut BTreeSet::new()) {
+            let name = escape(id);
+            let dict = w.fresh(&format!("{}Dict", name));
+            w.dicts.insert(name, dict);
+        }
+    }
+    for id in def_list.iter() {
+        w.def(id);
+    }
+    if let Some(actor) = actor {
+        w.service(actor);
+    }
+    w.out
+}
@@ -144,7 +144,7 @@ pub trait LanguageBinding {
     fn service(&self, bindings: &[Binding]) -> Result<String> {
         bindings
             .iter()
-            .map(|Binding { id, typ }| match typ {
+            .map(|Binding { id, typ, .. }| match typ {
                 IDLType::FuncT(func_t) => self.service_binding(id, func_t),
                 _ => self.usage(typ),
             })
@@ -41,6 +41,19 @@ pub fn candid_id_to_rust(id: &str) -> String {
     }
 }
 
+/// Returns the doc comments as `///` lines, each followed by a newline.
+pub fn doc_comments(docs: &[String]) -> String {
+    docs.iter()
+        .map(|line| {
+            if line.is_empty() {
+                "///\n".to_string()
+            } else {
+                format!("/// {}\n", line)
+            }
+        })
+        .collect()
+}
+
 /// Allow extra bindings to be passed in for Rust generation. This is higher level
 /// bindings than languages ones.
 ///
@@ -126,18 +139,38 @@ pub trait RustBindings {
     }
 
     fn record(&self, id: &str, fields: &[(String, String)]) -> Result<String> {
-        let all_fields = fields
-            .iter()
-            .map(|(name, ty)| format!("pub {} : {}", name, ty))
-            .collect::<Vec<String>>()
-            .join(" , ");
-        Ok(format!(
-            "#[derive(Clone)] pub struct {} {{ {} }}",
-            id, all_fields
-        ))
+        Ok(declare_struct(id, fields, &[]))
+    }
+
+    /// Same as `record`, with the doc comments of each field. Falls back to `record`
+    /// when no field is documented.
+    fn record_with_docs(
+        &self,
+        id: &str,
+        fields: &[(String, String)],
+        docs: &[Vec<String>],
+    ) -> Result<String> {
+        if docs.iter().all(|d| d.is_empty()) {
+            return self.record(id, fields);
+        }
+        Ok(declare_struct(id, fields, docs))
     }
 }
 
+// The struct of `RustBindings::record`, each field preceded by its doc comments in `docs`, if any.
+fn declare_struct(id: &str, fields: &[(String, String)], docs: &[Vec<String>]) -> String {
+    let all_fields = fields
+        .iter()
+        .enumerate()
+        .map(|(i, (name, ty))| {
+            let docs = docs.get(i).map(|d| doc_comments(d)).unwrap_or_default();
+            format!("{}pub {} : {}", docs, name, ty)
+        })
+        .collect::<Vec<String>>()
+        .join(" , ");
+    format!("#[derive(Clone)] pub struct {} {{ {} }}", id, all_fields)
+}
+
 pub struct Config {
     actor_name: Option<String>,
     bigint_type: Option<String>,
@@ -232,6 +265,7 @@ impl<'a> LanguageBinding for RustLanguageBinding<'a> {
                 Dec::TypD(Binding {
                     id,
                     typ: IDLType::RecordT(_),
+                    ..
                 }) => {
                     if id == var {
                         Some(format!("Box<{}>", var))
@@ -299,7 +333,7 @@ impl<'a> LanguageBinding for RustLanguageBinding<'a> {
     fn declare_record(&self, id: &str, fields: &[TypeField]) -> Result<String> {
         let all_fields = fields
             .iter()
-            .map(|TypeField { label, typ }| {
+            .map(|TypeField { label, typ, .. }| {
                 let field_name = match label {
                     Label::Id(i) => format!("id_{}", i),
                     Label::Unnamed(i) => format!("id_{}", i),
@@ -311,7 +345,10 @@ impl<'a> LanguageBinding for RustLanguageBinding<'a> {
             })
             .collect::<Result<Vec<(String, String)>>>()?;
 
-        self.config.bindings.record(id, &all_fields)
+        let docs: Vec<_> = fields.iter().map(|f| f.docs.clone()).collect();
+        self.config
+            .bindings
+            .record_with_docs(id, &all_fields, &docs)
     }
     fn declare_variant(&self, id: &str, fields: &[TypeField]) -> Result<String> {
         Ok(format!(
@@ -325,7 +362,7 @@ impl<'a> LanguageBinding for RustLanguageBinding<'a> {
     }
 
     fn declaration_binding(&self, binding: &Binding) -> Result<String> {
-        self.declare(&binding.id, &binding.typ)
+        Ok(doc_comments(&binding.docs) + &self.declare(&binding.id, &binding.typ)?)
     }
 
     fn service_binding(&self, _id: &str, _func_t: &FuncType) -> Result<String> {
@@ -341,7 +378,7 @@ impl<'a> LanguageBinding for RustLanguageBinding<'a> {
 
         let all_functions = bindings
             .iter()
-            .map(|Binding { id, typ }| match typ {
+            .map(|Binding { id, typ, docs }| match typ {
                 IDLType::FuncT(func_t) => {
                     let return_type = func_t
                         .rets
@@ -361,12 +398,13 @@ impl<'a> LanguageBinding for RustLanguageBinding<'a> {
                         })
                         .collect::<Result<Vec<(String, String)>>>()?;
 
-                    self.config.bindings.actor_function(
+                    let func = self.config.bindings.actor_function(
                         id,
                         &arguments,
                         &return_type,
                         func_t.is_query(),
-                    )
+                    )?;
+                    Ok(doc_comments(docs) + &func)
                 }
                 _ => self.usage(typ),
             })
@@ -1,14 +1,16 @@
 //! Deserialize Candid binary format to Rust data structures
 
 use super::error::{Error, Result};
-use super::types::internal::Opcode;
+use super::parser::typing::TypeEnv;
+use super::types::internal::{Field, Label, Opcode, Type};
 use super::{idl_hash, Int, Nat};
 use byteorder::{LittleEndian, ReadBytesExt};
 use leb128::read::{signed as sleb128_decode, unsigned as leb128_decode};
 use serde::de::{self, Visitor};
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::convert::TryFrom;
 use std::io::Read;
+use std::rc::Rc;
 
 const MAGIC_NUMBER: &[u8; 4] = b"DIDL";
 
@@ -34,16 +36,51 @@ impl<'de> IDLDeserialize<'de> {
             .types
             .pop_front()
             .ok_or_else(|| Error::msg("No more values to deserialize"))?;
-        self.de.current_type.push_back(ty);
+        self.de.current_type = Some(ty);
 
         let v = T::deserialize(&mut self.de).map_err(|e| self.de.dump_error_state(e))?;
-        if self.de.current_type.is_empty() && self.de.field_name.is_none() {
+        if self.de.current_type.is_none() && self.de.field_name.is_none() {
             Ok(v)
         } else {
             Err(Error::msg("Trailing type after deserializing a value"))
                 .map_err(|e| self.de.dump_error_state(e))
         }
     }
+    /// Deserialize one value, or `null` if there are no more values, as missing trailing
+    /// arguments of `opt` types are `null`.
+    pub(crate) fn get_value_or_null<T>(&mut self) -> Result<T>
+    where
+        T: de::Deserialize<'de>,
+    {
+        if self.is_done() {
+            self.de.types.push_back(WireType::Prim(Opcode::Null));
+        }
+        self.get_value()
+    }
+    /// Types of the values left to deserialize. The entries of the type table are bound in
+    /// the returned environment, as `table0`, `table1`, and so on.
+    pub fn types(&self) -> (TypeEnv, Vec<Type>) {
+        let mut env = TypeEnv::new();
+        for (i, entry) in self.de.table.iter().enumerate() {
+            let fields = |fs: &WireFields| {
+                fs.iter()
+                    .map(|(id, ty)| Field {
+                        id: Label::Id(*id),
+                        ty: to_type(*ty),
+                    })
+                    .collect()
+            };
+            let ty = match entry {
+                TableEntry::Opt(ty) => Type::Opt(Box::new(to_type(*ty))),
+                TableEntry::Vec(ty) => Type::Vec(Box::new(to_type(*ty))),
+                TableEntry::Record(fs) => Type::Record(fields(fs)),
+                TableEntry::Variant(fs) => Type::Variant(fields(fs)),
+            };
+            env.0.insert(format!("table{}", i), ty);
+        }
+        let types = self.de.types.iter().map(|ty| to_type(*ty)).collect();
+        (env, types)
+    }
     /// Check if we finish deserializing all values.
     pub fn is_done(&self) -> bool {
         self.de.types.is_empty()
@@ -61,32 +98,301 @@ impl<'de> IDLDeserialize<'de> {
     }
 }
 
-#[derive(Clone, Debug)]
-enum RawValue {
-    I(i64),
-    U(u32),
+/// Reads back-to-back Candid messages from an `io::Read`, such as a log of recorded calls.
+/// Bytes are pulled from the reader only as needed, and only the current message is kept
+/// in memory.
+///
+/// ```
+/// use candid::{de::IDLStream, Decode, Encode};
+/// let mut log = Encode!(&42u8)?;
+/// log.extend(Encode!(&"text", &true)?);
+///
+/// let mut stream = IDLStream::new(&log[..]);
+/// let message = stream.next_message()?.unwrap();
+/// assert_eq!(Decode!(message, u8)?, 42);
+/// let message = stream.next_message()?.unwrap();
+/// assert_eq!(Decode!(message, String, bool)?, ("text".to_string(), true));
+/// assert!(stream.next_message()?.is_none());
+/// # Ok::<(), candid::Error>(())
+/// ```
+pub struct IDLStream<R> {
+    reader: R,
+    message: Vec<u8>,
 }
-impl RawValue {
-    fn get_i64(&self) -> Result<i64> {
-        match *self {
-            RawValue::I(i) => Ok(i),
-            _ => Err(Error::msg(format!("get_i64 fail: {:?}", *self))),
+
+impl<R: Read> IDLStream<R> {
+    pub fn new(reader: R) -> Self {
+        IDLStream {
+            reader,
+            message: Vec::new(),
         }
     }
-    fn get_u32(&self) -> Result<u32> {
-        match *self {
-            RawValue::U(u) => Ok(u),
-            _ => Err(Error::msg(format!("get_u32 fail: {:?}", *self))),
+    /// Reads the next message, and returns `None` at the end of the input. The message can
+    /// be decoded with `IDLDeserialize` or `IDLArgs::from_bytes`.
+    pub fn next_message(&mut self) -> Result<Option<&[u8]>> {
+        self.message.clear();
+        let mut first = [0u8; 1];
+        loop {
+            match self.reader.read(&mut first) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
         }
+        let mut r = Recorder {
+            reader: (&first[..]).chain(&mut self.reader),
+            bytes: &mut self.message,
+        };
+        let (table, types) = read_table(&mut r)?;
+        for ty in types {
+            skip_value(&mut r, &table, ty, 0)?;
+        }
+        Ok(Some(&self.message))
     }
 }
-fn validate_type_range(ty: i64, len: u64) -> Result<()> {
-    if ty >= 0 && (ty as u64) < len || Opcode::try_from(ty).is_ok() {
-        Ok(())
-    } else {
-        Err(Error::msg(format!("unknown type {}", ty)))
+
+// Keeps the bytes read so far.
+struct Recorder<'a, R> {
+    reader: R,
+    bytes: &'a mut Vec<u8>,
+}
+
+impl<'a, R: Read> Read for Recorder<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+// Reads the bytes of a value of type `ty`, without decoding it. `depth` counts the records
+// nested in records, as in `Deserializer::enter_record`.
+fn skip_value<R: Read>(
+    r: &mut Recorder<R>,
+    table: &[TableEntry],
+    ty: WireType,
+    depth: usize,
+) -> Result<()> {
+    let len = match ty {
+        WireType::Prim(op) => match op {
+            Opcode::Null | Opcode::Reserved => 0,
+            Opcode::Bool | Opcode::Nat8 | Opcode::Int8 => 1,
+            Opcode::Nat16 | Opcode::Int16 => 2,
+            Opcode::Nat32 | Opcode::Int32 | Opcode::Float32 => 4,
+            Opcode::Nat64 | Opcode::Int64 | Opcode::Float64 => 8,
+            Opcode::Nat => Nat::decode(r).map(|_| 0)?,
+            Opcode::Int => Int::decode(r).map(|_| 0)?,
+            Opcode::Text => leb128_decode(&mut *r).map_err(Error::from)?,
+            Opcode::Principal => {
+                if r.read_u8()? != 1u8 {
+                    return Err(Error::msg("Opaque reference not supported"));
+                }
+                leb128_decode(&mut *r).map_err(Error::from)?
+            }
+            Opcode::Empty => return Err(Error::msg("Cannot decode empty type")),
+            Opcode::Opt | Opcode::Vec | Opcode::Record | Opcode::Variant => unreachable!(),
+        },
+        WireType::Index(i) => {
+            match &table[i] {
+                TableEntry::Opt(ty) => match r.read_u8()? {
+                    0 => (),
+                    1 => skip_value(r, table, *ty, 0)?,
+                    _ => return Err(Error::msg("not an option value")),
+                },
+                TableEntry::Vec(ty) => {
+                    let len = leb128_decode(&mut *r).map_err(Error::from)?;
+                    for _ in 0..len {
+                        let start = r.bytes.len();
+                        skip_value(r, table, *ty, 0)?;
+                        // The other elements have no bytes either
+                        if r.bytes.len() == start {
+                            break;
+                        }
+                    }
+                }
+                TableEntry::Record(fs) => {
+                    if depth > table.len() {
+                        return Err(Error::msg("There is an infinite loop in the record definition, the type is isomorphic to an empty type"));
+                    }
+                    for (_, ty) in fs.iter() {
+                        skip_value(r, table, *ty, depth + 1)?;
+                    }
+                }
+                TableEntry::Variant(fs) => {
+                    let index = leb128_decode(&mut *r).map_err(Error::from)?;
+                    let (_, ty) = usize::try_from(index)
+                        .ok()
+                        .and_then(|i| fs.get(i))
+                        .ok_or_else(|| {
+                            Error::msg(format!(
+                                "variant index {} larger than length {}",
+                                index,
+                                fs.len()
+                            ))
+                        })?;
+                    skip_value(r, table, *ty, 0)?;
+                }
+            }
+            0
+        }
+    };
+    let n = std::io::copy(&mut r.by_ref().take(len), &mut std::io::sink())?;
+    if n < len {
+        return Err(Error::msg("unexpected end of message"));
     }
+    Ok(())
 }
+
+// A type on the wire, either a primitive type or an index into the type table.
+#[derive(Clone, Copy, Debug)]
+enum WireType {
+    Prim(Opcode),
+    Index(usize),
+}
+
+// The fields of a record or variant, sorted by their hash.
+type WireFields = Rc<[(u32, WireType)]>;
+
+// The type table is resolved into these entries before decoding any value. They are
+// immutable, and decoding a value only copies `WireType`s or clones `Rc`s.
+#[derive(Debug)]
+enum TableEntry {
+    Opt(WireType),
+    Vec(WireType),
+    Record(WireFields),
+    Variant(WireFields),
+}
+
+impl TableEntry {
+    fn opcode(&self) -> Opcode {
+        match self {
+            TableEntry::Opt(_) => Opcode::Opt,
+            TableEntry::Vec(_) => Opcode::Vec,
+            TableEntry::Record(_) => Opcode::Record,
+            TableEntry::Variant(_) => Opcode::Variant,
+        }
+    }
+}
+
+// Composite types can only be referred to by their index in the table.
+fn wire_type(ty: i64, len: u64) -> Option<WireType> {
+    if ty >= 0 && (ty as u64) < len {
+        return Some(WireType::Index(ty as usize));
+    }
+    match Opcode::try_from(ty) {
+        Ok(Opcode::Opt) | Ok(Opcode::Vec) | Ok(Opcode::Record) | Ok(Opcode::Variant) | Err(_) => {
+            None
+        }
+        Ok(op) => Some(WireType::Prim(op)),
+    }
+}
+
+fn to_type(ty: WireType) -> Type {
+    match ty {
+        WireType::Index(i) => Type::Var(format!("table{}", i)),
+        WireType::Prim(op) => match op {
+            Opcode::Null => Type::Null,
+            Opcode::Bool => Type::Bool,
+            Opcode::Nat => Type::Nat,
+            Opcode::Int => Type::Int,
+            Opcode::Nat8 => Type::Nat8,
+            Opcode::Nat16 => Type::Nat16,
+            Opcode::Nat32 => Type::Nat32,
+            Opcode::Nat64 => Type::Nat64,
+            Opcode::Int8 => Type::Int8,
+            Opcode::Int16 => Type::Int16,
+            Opcode::Int32 => Type::Int32,
+            Opcode::Int64 => Type::Int64,
+            Opcode::Float32 => Type::Float32,
+            Opcode::Float64 => Type::Float64,
+            Opcode::Text => Type::Text,
+            Opcode::Reserved => Type::Reserved,
+            Opcode::Empty => Type::Empty,
+            Opcode::Principal => Type::Principal,
+            // Composite types are always in the table, see `wire_type`
+            Opcode::Opt | Opcode::Vec | Opcode::Record | Opcode::Variant => unreachable!(),
+        },
+    }
+}
+
+// Reads the magic number, the type table, and the types of the values.
+fn read_table<R: Read>(r: &mut R) -> Result<(Vec<TableEntry>, VecDeque<WireType>)> {
+    let mut table = Vec::new();
+    let mut types = VecDeque::new();
+    let mut magic = [0u8; 4];
+    let mut read = 0;
+    while read < magic.len() {
+        match r.read(&mut magic[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if read < magic.len() || magic != *MAGIC_NUMBER {
+        return Err(Error::msg(format!("wrong magic number {:?}", magic)));
+    }
+    let len = leb128_decode(r).map_err(Error::from)?;
+    for _i in 0..len {
+        let ty = sleb128_decode(r).map_err(Error::from)?;
+        let entry = match Opcode::try_from(ty) {
+            Ok(op @ Opcode::Opt) | Ok(op @ Opcode::Vec) => {
+                let ty = table_type(r, len)?;
+                if op == Opcode::Opt {
+                    TableEntry::Opt(ty)
+                } else {
+                    TableEntry::Vec(ty)
+                }
+            }
+            Ok(op @ Opcode::Record) | Ok(op @ Opcode::Variant) => {
+                let obj_len = u32::try_from(leb128_decode(r).map_err(Error::from)?)
+                    .map_err(|_| Error::msg("length out of u32"))?;
+                let mut fields = Vec::new();
+                let mut prev_hash = None;
+                for _ in 0..obj_len {
+                    let hash = u32::try_from(leb128_decode(r).map_err(Error::from)?)
+                        .map_err(|_| Error::msg("field hash out of u32"))?;
+                    if let Some(prev_hash) = prev_hash {
+                        if prev_hash >= hash {
+                            return Err(Error::msg("field id collision or not sorted"));
+                        }
+                    }
+                    prev_hash = Some(hash);
+                    let ty = table_type(r, len)?;
+                    fields.push((hash, ty));
+                }
+                if op == Opcode::Record {
+                    TableEntry::Record(fields.into())
+                } else {
+                    TableEntry::Variant(fields.into())
+                }
+            }
+            _ => {
+                return Err(Error::msg(format!(
+                    "Unsupported op_code {} in type table",
+                    ty
+                )))
+            }
+        };
+        table.push(entry);
+    }
+    let table_len = len;
+    let len = leb128_decode(r).map_err(Error::from)?;
+    for _i in 0..len {
+        let ty = sleb128_decode(r).map_err(Error::from)?;
+        let ty =
+            wire_type(ty, table_len).ok_or_else(|| Error::msg(format!("Unknown opcode {}", ty)))?;
+        types.push_back(ty);
+    }
+    Ok((table, types))
+}
+
+fn table_type<R: Read>(r: &mut R, len: u64) -> Result<WireType> {
+    let ty = sleb128_decode(r).map_err(Error::from)?;
+    wire_type(ty, len).ok_or_else(|| Error::msg(format!("unknown type {}", ty)))
+}
+
 #[derive(Debug)]
 enum FieldLabel {
     Named(&'static str),
@@ -95,21 +401,27 @@ enum FieldLabel {
     Skip,
 }
 
+// The names of the Rust fields or variants, by their hash. `None` reports every label by
+// its id, which is how `IDLValue`s are decoded.
+type FieldNames = Option<Rc<BTreeMap<u32, &'static str>>>;
+
 struct Deserializer<'de> {
     input: &'de [u8],
-    // Raw value of the type description table
-    table: Vec<Vec<RawValue>>,
+    // Resolved type description table
+    table: Vec<TableEntry>,
     // Value types for deserialization
-    types: VecDeque<RawValue>,
-    // The front of current_type queue always points to the type of the value we are deserailizing.
-    // The type info is cloned from table. Someone more familiar with Rust should see if we can
-    // rewrite this to avoid copying.
-    current_type: VecDeque<RawValue>,
+    types: VecDeque<WireType>,
+    // The type of the value we are deserializing. Accessors of compound values set it
+    // to the type of each component before deserializing it.
+    current_type: Option<WireType>,
     // field_name tells deserialize_identifier which field name to process.
     // This field should always be set by set_field_name function.
     field_name: Option<FieldLabel>,
-    // The record nesting depth should be bounded by the length of table to avoid infinite loop.
+    // Records nested in records, without other types in between. The depth is bounded by the
+    // length of table, as a deeper nesting is an infinite loop, as in `type T = record { T }`.
     record_nesting_depth: usize,
+    // Field names of Rust structs and enums, by the address of their static list.
+    field_names: HashMap<(usize, usize), Rc<BTreeMap<u32, &'static str>>>,
 }
 
 impl<'de> Deserializer<'de> {
@@ -118,9 +430,10 @@ impl<'de> Deserializer<'de> {
             input,
             table: Vec::new(),
             types: VecDeque::new(),
-            current_type: VecDeque::new(),
+            current_type: None,
             field_name: None,
             record_nesting_depth: 0,
+            field_names: HashMap::new(),
         }
     }
 
@@ -136,10 +449,7 @@ impl<'de> Deserializer<'de> {
     }
 
     fn leb128_read(&mut self) -> Result<u64> {
-        leb128_decode(&mut self.input).map_err(Error::msg)
-    }
-    fn sleb128_read(&mut self) -> Result<i64> {
-        sleb128_decode(&mut self.input).map_err(Error::msg)
+        leb128_decode(&mut self.input).map_err(Error::from)
     }
     fn parse_byte(&mut self) -> Result<u8> {
         let mut buf = [0u8; 1];
@@ -159,116 +469,103 @@ impl<'de> Deserializer<'de> {
         let buf = self.parse_bytes(len)?;
         String::from_utf8(buf).map_err(Error::msg)
     }
-    fn parse_magic(&mut self) -> Result<()> {
-        let mut buf = [0u8; 4];
-        match self.input.read(&mut buf) {
-            Ok(4) if buf == *MAGIC_NUMBER => Ok(()),
-            _ => Err(Error::msg(format!("wrong magic number {:?}", buf))),
-        }
-    }
     // Parse magic number, type table, and type seq from input.
     fn parse_table(&mut self) -> Result<()> {
-        self.parse_magic()?;
-        let len = self.leb128_read()?;
-        for _i in 0..len {
-            let mut buf = Vec::new();
-            let ty = self.sleb128_read()?;
-            buf.push(RawValue::I(ty));
-            match Opcode::try_from(ty) {
-                Ok(Opcode::Opt) | Ok(Opcode::Vec) => {
-                    let ty = self.sleb128_read()?;
-                    validate_type_range(ty, len)?;
-                    buf.push(RawValue::I(ty));
-                }
-                Ok(Opcode::Record) | Ok(Opcode::Variant) => {
-                    let obj_len = u32::try_from(self.leb128_read()?)
-                        .map_err(|_| Error::msg(Error::msg("length out of u32")))?;
-                    buf.push(RawValue::U(obj_len));
-                    let mut prev_hash = None;
-                    for _ in 0..obj_len {
-                        let hash = u32::try_from(self.leb128_read()?)
-                            .map_err(|_| Error::msg(Error::msg("field hash out of u32")))?;
-                        if let Some(prev_hash) = prev_hash {
-                            if prev_hash >= hash {
-                                return Err(Error::msg("field id collision or not sorted"));
-                            }
-                        }
-                        prev_hash = Some(hash);
-                        buf.push(RawValue::U(hash));
-                        let ty = self.sleb128_read()?;
-                        validate_type_range(ty, len)?;
-                        buf.push(RawValue::I(ty));
-                    }
-                }
-                _ => {
-                    return Err(Error::msg(format!(
-                        "Unsupported op_code {} in type table",
-                        ty
-                    )))
-                }
-            };
-            self.table.push(buf);
-        }
-        let len = self.leb128_read()?;
-        for _i in 0..len {
-            let ty = self.sleb128_read()?;
-            self.types.push_back(RawValue::I(ty));
-        }
+        let (table, types) = read_table(&mut self.input)?;
+        self.table = table;
+        self.types = types;
         Ok(())
     }
-    fn pop_current_type(&mut self) -> Result<RawValue> {
-        self.current_type
-            .pop_front()
-            .ok_or_else(|| Error::msg("empty current_type"))
+    fn opcode(&self, ty: WireType) -> Opcode {
+        match ty {
+            WireType::Prim(op) => op,
+            WireType::Index(i) => self.table[i].opcode(),
+        }
     }
-    fn peek_current_type(&self) -> Result<&RawValue> {
+    // Take the type of the current value.
+    fn parse_type(&mut self) -> Result<WireType> {
         self.current_type
-            .front()
+            .take()
             .ok_or_else(|| Error::msg("empty current_type"))
     }
-    fn rawvalue_to_opcode(&self, v: &RawValue) -> Result<Opcode> {
-        let mut op = v.get_i64()?;
-        if op >= 0 && op < self.table.len() as i64 {
-            op = self.table[op as usize][0].get_i64()?;
-        }
-        Opcode::try_from(op).map_err(|_| Error::msg(format!("Unknown opcode {}", op)))
-    }
-    // Pop type opcode from the front of current_type.
-    // If the opcode is an index (>= 0), we push the corresponding entry from table,
-    // to current_type queue, and pop the opcode from the front.
-    fn parse_type(&mut self) -> Result<Opcode> {
-        let mut op = self.pop_current_type()?.get_i64()?;
-        if op >= 0 && op < self.table.len() as i64 {
-            let ty = &self.table[op as usize];
-            for x in ty.iter().rev() {
-                self.current_type.push_front(x.clone());
-            }
-            op = self.pop_current_type()?.get_i64()?;
-        }
-        let r = Opcode::try_from(op).map_err(|_| Error::msg(format!("Unknown opcode {}", op)))?;
-        Ok(r)
-    }
-    // Same logic as parse_type, but not poping the current_type queue.
+    // Same as parse_type, but leaves the current type in place.
     fn peek_type(&self) -> Result<Opcode> {
-        let op = self.peek_current_type()?;
-        self.rawvalue_to_opcode(op)
+        let ty = self
+            .current_type
+            .ok_or_else(|| Error::msg("empty current_type"))?;
+        Ok(self.opcode(ty))
     }
-    // Check if current_type matches the provided type
-    fn check_type(&mut self, expected: Opcode) -> Result<()> {
-        let wire_type = self.parse_type()?;
+    // Check if current_type matches the provided type, and take it.
+    fn check_type(&mut self, expected: Opcode) -> Result<WireType> {
+        let ty = self.parse_type()?;
+        let wire_type = self.opcode(ty);
         if wire_type != expected {
             return Err(Error::msg(format!(
                 "Type mismatch. Type on the wire: {:?}; Provided type: {:?}",
                 wire_type, expected
             )));
         }
+        Ok(ty)
+    }
+    fn entry(&self, ty: WireType) -> &TableEntry {
+        match ty {
+            WireType::Index(i) => &self.table[i],
+            // check_type only lets composite types through, which are always in the table.
+            WireType::Prim(_) => unreachable!(),
+        }
+    }
+    fn inner_type(&mut self, expected: Opcode) -> Result<WireType> {
+        let ty = self.check_type(expected)?;
+        match self.entry(ty) {
+            TableEntry::Opt(t) | TableEntry::Vec(t) => Ok(*t),
+            _ => unreachable!(),
+        }
+    }
+    fn fields(&mut self, expected: Opcode) -> Result<WireFields> {
+        let ty = self.check_type(expected)?;
+        match self.entry(ty) {
+            TableEntry::Record(fs) | TableEntry::Variant(fs) => Ok(fs.clone()),
+            _ => unreachable!(),
+        }
+    }
+    // The names of the fields of a Rust struct or enum, computed once per list.
+    fn field_names(&mut self, fields: &'static [&'static str]) -> Result<FieldNames> {
+        let key = (fields.as_ptr() as usize, fields.len());
+        if let Some(names) = self.field_names.get(&key) {
+            return Ok(Some(names.clone()));
+        }
+        let mut fs = BTreeMap::new();
+        for s in fields.iter() {
+            if fs.insert(idl_hash(s), *s).is_some() {
+                return Err(Error::msg(format!("hash collision {}", s)));
+            }
+        }
+        let fs = Rc::new(fs);
+        self.field_names.insert(key, fs.clone());
+        Ok(Some(fs))
+    }
+    fn enter_record(&mut self) -> Result<()> {
+        if self.record_nesting_depth > self.table.len() {
+            return Err(Error::msg("There is an infinite loop in the record definition, the type is isomorphic to an empty type"));
+        }
+        self.record_nesting_depth += 1;
         Ok(())
     }
+    fn leave_record(&mut self) {
+        self.record_nesting_depth -= 1;
+    }
+    // Components of options, vectors and variants start a new nesting of records.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let depth = std::mem::replace(&mut self.record_nesting_depth, 0);
+        let res = f(self)?;
+        self.record_nesting_depth = depth;
+        Ok(res)
+    }
     // Should always call set_field_name to set the field_name. After deserialize_identifier
     // processed the field_name, field_name will be reset to None.
     fn set_field_name(&mut self, field: FieldLabel) {
         if self.field_name.is_some() {
-            panic!(format!("field_name already taken {:?}", self.field_name));
+            panic!("field_name already taken {:?}", self.field_name);
         }
         self.field_name = Some(field);
     }
@@ -356,11 +653,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         if self.field_name.is_some() {
             return self.deserialize_identifier(visitor);
         }
-        let t = self.peek_type()?;
-        if t != Opcode::Record {
-            self.record_nesting_depth = 0;
-        }
-        match t {
+        match self.peek_type()? {
             Opcode::Int => self.deserialize_int(visitor),
             Opcode::Nat => self.deserialize_nat(visitor),
             Opcode::Nat8 => self.deserialize_u8(visitor),
@@ -387,24 +680,14 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     // Used for deserializing to IDLValue
-    fn deserialize_any<V>(mut self, visitor: V) -> Result<V::Value>
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
         if self.field_name.is_some() {
             return self.deserialize_identifier(visitor);
         }
-        let t = self.peek_type()?;
-        match t {
-            Opcode::Record => {
-                if self.record_nesting_depth > self.table.len() {
-                    return Err(Error::msg("There is an infinite loop in the record definition, the type is isomorphic to an empty type"));
-                }
-                self.record_nesting_depth += 1;
-            }
-            _ => self.record_nesting_depth = 0,
-        }
-        match t {
+        match self.peek_type()? {
             Opcode::Int => self.deserialize_int(visitor),
             Opcode::Nat => self.deserialize_nat(visitor),
             Opcode::Nat8 => self.deserialize_u8(visitor),
@@ -425,28 +708,21 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             Opcode::Vec => self.deserialize_seq(visitor),
             Opcode::Opt => self.deserialize_option(visitor),
             Opcode::Record => {
-                self.check_type(Opcode::Record)?;
-                let len = self.pop_current_type()?.get_u32()?;
-                let mut fs = BTreeMap::new();
-                for i in 0..len {
-                    let hash = self.current_type[2 * i as usize].get_u32()?;
-                    if fs.insert(hash, None) != None {
-                        return Err(Error::msg(format!("hash collision {}", hash)));
-                    }
-                }
-                visitor.visit_map(Compound::new(&mut self, Style::Struct { len, fs }))
+                let fields = self.fields(Opcode::Record)?;
+                let style = Style::Struct {
+                    fields,
+                    index: 0,
+                    names: None,
+                };
+                Compound::new(self, style).visit_map(visitor)
             }
             Opcode::Variant => {
-                self.check_type(Opcode::Variant)?;
-                let len = self.pop_current_type()?.get_u32()?;
-                let mut fs = BTreeMap::new();
-                for i in 0..len {
-                    let hash = self.current_type[2 * i as usize].get_u32()?;
-                    if fs.insert(hash, None) != None {
-                        return Err(Error::msg(format!("hash collision {}", hash)));
-                    }
-                }
-                visitor.visit_enum(Compound::new(&mut self, Style::Enum { len, fs }))
+                let fields = self.fields(Opcode::Variant)?;
+                let style = Style::Enum {
+                    fields,
+                    names: None,
+                };
+                self.nested(|de| visitor.visit_enum(Compound::new(de, style)))
             }
             Opcode::Principal => self.deserialize_principal(visitor),
         }
@@ -502,14 +778,18 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        self.check_type(Opcode::Opt)?;
+        // null <: opt t
+        if self.peek_type()? == Opcode::Null {
+            self.check_type(Opcode::Null)?;
+            return visitor.visit_none();
+        }
+        let ty = self.inner_type(Opcode::Opt)?;
         let bit = self.parse_byte()?;
         if bit == 0u8 {
-            // Skip the type T of Option<T>
-            self.pop_current_type()?;
             visitor.visit_none()
         } else if bit == 1u8 {
-            visitor.visit_some(self)
+            self.current_type = Some(ty);
+            self.nested(|de| visitor.visit_some(de))
         } else {
             Err(de::Error::custom("not an option value"))
         }
@@ -533,21 +813,19 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         visitor.visit_newtype_struct(self)
     }
-    fn deserialize_seq<V>(mut self, visitor: V) -> Result<V::Value>
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        match self.parse_type()? {
+        match self.peek_type()? {
             Opcode::Vec => {
+                let ty = self.inner_type(Opcode::Vec)?;
                 let len = self.leb128_read()?;
-                let value = visitor.visit_seq(Compound::new(&mut self, Style::Vector { len }));
-                // Skip the type T of Vec<T>.
-                self.pop_current_type()?;
-                value
+                Compound::new(self, Style::Vector { len, ty }).visit_seq(visitor)
             }
             Opcode::Record => {
-                let len = self.pop_current_type()?.get_u32()?;
-                visitor.visit_seq(Compound::new(&mut self, Style::Tuple { len, index: 0 }))
+                let fields = self.fields(Opcode::Record)?;
+                Compound::new(self, Style::Tuple { fields, index: 0 }).visit_seq(visitor)
             }
             _ => Err(Error::msg("seq only takes vector or tuple")),
         }
@@ -570,7 +848,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         self.deserialize_seq(visitor)
     }
     fn deserialize_struct<V>(
-        mut self,
+        self,
         _name: &'static str,
         fields: &'static [&'static str],
         visitor: V,
@@ -578,24 +856,18 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        if self.record_nesting_depth > self.table.len() {
-            return Err(Error::msg("There is an infinite loop in the record definition, the type is isomorphic to an empty type"));
-        }
-        self.record_nesting_depth += 1;
-        self.check_type(Opcode::Record)?;
-        let len = self.pop_current_type()?.get_u32()?;
-        let mut fs = BTreeMap::new();
-        for s in fields.iter() {
-            if fs.insert(idl_hash(s), Some(*s)) != None {
-                return Err(Error::msg(format!("hash collision {}", s)));
-            }
-        }
-        let value = visitor.visit_map(Compound::new(&mut self, Style::Struct { len, fs }))?;
-        Ok(value)
+        let record = self.fields(Opcode::Record)?;
+        let names = self.field_names(fields)?;
+        let style = Style::Struct {
+            fields: record,
+            index: 0,
+            names,
+        };
+        Compound::new(self, style).visit_map(visitor)
     }
 
     fn deserialize_enum<V>(
-        mut self,
+        self,
         _name: &'static str,
         variants: &'static [&'static str],
         visitor: V,
@@ -603,16 +875,10 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        self.check_type(Opcode::Variant)?;
-        let len = self.pop_current_type()?.get_u32()?;
-        let mut fs = BTreeMap::new();
-        for s in variants.iter() {
-            if fs.insert(idl_hash(s), Some(*s)) != None {
-                return Err(Error::msg(format!("hash collision {}", s)));
-            }
-        }
-        let value = visitor.visit_enum(Compound::new(&mut self, Style::Enum { len, fs }))?;
-        Ok(value)
+        let fields = self.fields(Opcode::Variant)?;
+        let names = self.field_names(variants)?;
+        let style = Style::Enum { fields, names };
+        self.nested(|de| visitor.visit_enum(Compound::new(de, style)))
     }
     /// Deserialize identifier.
     /// # Panics
@@ -641,19 +907,21 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 #[derive(Debug)]
 enum Style {
     Tuple {
-        len: u32,
-        index: u32,
+        fields: WireFields,
+        index: usize,
     },
     Vector {
         len: u64, // non-vector length can only be u32, because field ids is u32.
+        ty: WireType,
     },
     Struct {
-        len: u32,
-        fs: BTreeMap<u32, Option<&'static str>>,
+        fields: WireFields,
+        index: usize,
+        names: FieldNames,
     },
     Enum {
-        len: u32,
-        fs: BTreeMap<u32, Option<&'static str>>,
+        fields: WireFields,
+        names: FieldNames,
     },
 }
 
@@ -666,6 +934,52 @@ impl<'a, 'de> Compound<'a, 'de> {
     fn new(de: &'a mut Deserializer<'de>, style: Style) -> Self {
         Compound { de, style }
     }
+    fn visit_seq<V>(mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let value = match self.style {
+            Style::Vector { .. } => {
+                let depth = std::mem::replace(&mut self.de.record_nesting_depth, 0);
+                let value = visitor.visit_seq(&mut self)?;
+                self.de.record_nesting_depth = depth;
+                value
+            }
+            _ => {
+                self.de.enter_record()?;
+                let value = visitor.visit_seq(&mut self)?;
+                self.de.leave_record();
+                value
+            }
+        };
+        self.check_done()?;
+        Ok(value)
+    }
+    fn visit_map<V>(mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.de.enter_record()?;
+        let value = visitor.visit_map(&mut self)?;
+        self.de.leave_record();
+        self.check_done()?;
+        Ok(value)
+    }
+    // The visitor may stop before the end, which leaves values in the input.
+    fn check_done(&self) -> Result<()> {
+        match self.style {
+            Style::Vector { len, .. } if len > 0 => {
+                Err(Error::msg(format!("{} trailing vector elements", len)))
+            }
+            Style::Tuple { ref fields, index }
+            | Style::Struct {
+                ref fields, index, ..
+            } if index < fields.len() => {
+                Err(Error::msg("Trailing type after deserializing a value"))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 impl<'de, 'a> de::SeqAccess<'de> for Compound<'a, 'de> {
@@ -677,29 +991,32 @@ impl<'de, 'a> de::SeqAccess<'de> for Compound<'a, 'de> {
     {
         match self.style {
             Style::Tuple {
-                ref len,
+                ref fields,
                 ref mut index,
             } => {
-                if *index == *len {
-                    return Ok(None);
-                }
-                let t_idx = self.de.pop_current_type()?.get_u32()?;
-                if t_idx != *index {
+                let (hash, ty) = match fields.get(*index) {
+                    None => return Ok(None),
+                    Some(field) => *field,
+                };
+                if hash as usize != *index {
                     return Err(Error::msg(format!(
                         "Expect vector index {}, but get {}",
-                        index, t_idx
+                        index, hash
                     )));
                 }
                 *index += 1;
+                self.de.current_type = Some(ty);
                 seed.deserialize(&mut *self.de).map(Some)
             }
-            Style::Vector { ref mut len } => {
+            Style::Vector {
+                ref mut len,
+                ref ty,
+            } => {
                 if *len == 0 {
                     return Ok(None);
                 }
-                let ty = self.de.peek_current_type()?.clone();
-                self.de.current_type.push_front(ty);
                 *len -= 1;
+                self.de.current_type = Some(*ty);
                 seed.deserialize(&mut *self.de).map(Some)
             }
             _ => Err(Error::msg("expect vector or tuple")),
@@ -715,23 +1032,28 @@ impl<'de, 'a> de::MapAccess<'de> for Compound<'a, 'de> {
     {
         match self.style {
             Style::Struct {
-                ref mut len,
-                ref fs,
+                ref fields,
+                ref mut index,
+                ref names,
             } => {
-                if *len == 0 {
-                    return Ok(None);
-                }
-                *len -= 1;
-                let hash = self.de.pop_current_type()?.get_u32()?;
-                match fs.get(&hash) {
-                    Some(None) => self.de.set_field_name(FieldLabel::Id(hash)),
-                    Some(Some(field)) => self.de.set_field_name(FieldLabel::Named(field)),
-                    None => {
-                        // This triggers seed.deserialize to call deserialize_ignore_any
-                        // to skip both type and value of this unknown field.
-                        self.de.set_field_name(FieldLabel::Skip);
-                    }
+                let (hash, ty) = match fields.get(*index) {
+                    None => return Ok(None),
+                    Some(field) => *field,
+                };
+                *index += 1;
+                match names {
+                    None => self.de.set_field_name(FieldLabel::Id(hash)),
+                    Some(names) => match names.get(&hash) {
+                        Some(field) => self.de.set_field_name(FieldLabel::Named(field)),
+                        None => {
+                            // This triggers seed.deserialize to call deserialize_ignore_any
+                            // to skip both type and value of this unknown field.
+                            self.de.set_field_name(FieldLabel::Skip);
+                        }
+                    },
                 }
+                // The type of the value, which is deserialized by next_value_seed.
+                self.de.current_type = Some(ty);
                 seed.deserialize(&mut *self.de).map(Some)
             }
             _ => Err(Error::msg("expect struct")),
@@ -754,54 +1076,43 @@ impl<'de, 'a> de::EnumAccess<'de> for Compound<'a, 'de> {
         V: de::DeserializeSeed<'de>,
     {
         match self.style {
-            Style::Enum { len, ref fs } => {
+            Style::Enum {
+                ref fields,
+                ref names,
+            } => {
                 let index = u32::try_from(self.de.leb128_read()?)
                     .map_err(|_| Error::msg("variant index out of u32"))?;
-                if index >= len {
-                    return Err(Error::msg(format!(
-                        "variant index {} larger than length {}",
-                        index, len
-                    )));
-                }
-                let mut index_ty = None;
-                for i in 0..len {
-                    let hash = self.de.pop_current_type()?.get_u32()?;
-                    let ty = self.de.pop_current_type()?;
-                    if i == index {
-                        match fs.get(&hash) {
-                            Some(None) => {
-                                let opcode = self.de.rawvalue_to_opcode(&ty)?;
-                                let accessor = match opcode {
-                                    Opcode::Null => "unit",
-                                    Opcode::Record => "struct",
-                                    _ => "newtype",
-                                };
-                                self.de.set_field_name(FieldLabel::Variant(format!(
-                                    "{},{}",
-                                    hash, accessor
-                                )));
-                            }
-                            Some(Some(field)) => {
-                                self.de.set_field_name(FieldLabel::Named(field));
-                            }
-                            None => {
-                                if !fs.is_empty() {
-                                    return Err(Error::msg(format!(
-                                        "Unknown variant hash {}",
-                                        hash
-                                    )));
-                                } else {
-                                    // Actual enum won't have empty fs. This can only be generated
-                                    // from deserialize_ignored_any
-                                    self.de.set_field_name(FieldLabel::Skip);
-                                }
-                            }
-                        }
-                        index_ty = Some(ty);
+                let (hash, ty) = match fields.get(index as usize) {
+                    Some(field) => *field,
+                    None => {
+                        return Err(Error::msg(format!(
+                            "variant index {} larger than length {}",
+                            index,
+                            fields.len()
+                        )))
                     }
+                };
+                match names {
+                    None => {
+                        let accessor = match self.de.opcode(ty) {
+                            Opcode::Null => "unit",
+                            Opcode::Record => "struct",
+                            _ => "newtype",
+                        };
+                        self.de
+                            .set_field_name(FieldLabel::Variant(format!("{},{}", hash, accessor)));
+                    }
+                    Some(names) => match names.get(&hash) {
+                        Some(field) => self.de.set_field_name(FieldLabel::Named(field)),
+                        None if !names.is_empty() => {
+                            return Err(Error::msg(format!("Unknown variant hash {}", hash)));
+                        }
+                        // Actual enum won't have empty names. This can only be generated
+                        // from deserialize_ignored_any
+                        None => self.de.set_field_name(FieldLabel::Skip),
+                    },
                 }
-                // Okay to unwrap, as index_ty always has a value here.
-                self.de.current_type.push_front(index_ty.unwrap());
+                self.de.current_type = Some(ty);
                 let val = seed.deserialize(&mut *self.de)?;
                 Ok((val, self))
             }
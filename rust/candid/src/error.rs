@@ -68,6 +68,16 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<leb128::read::Error> for Error {
+    fn from(e: leb128::read::Error) -> Error {
+        match e {
+            // The `Display` of leb128 0.2 goes through the deprecated `io::Error::description`
+            leb128::read::Error::IoError(e) => Error::msg(format!("leb128::read::Error: {}", e)),
+            leb128::read::Error::Overflow => Error::msg(e),
+        }
+    }
+}
+
 impl From<crate::parser::ParserError> for Error {
     fn from(e: crate::parser::ParserError) -> Error {
         Error::msg(format!("Candid parser error: {}", e))
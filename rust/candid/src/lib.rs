@@ -246,8 +246,15 @@ pub use parser::value::IDLArgs;
 pub mod de;
 pub mod ser;
 
+pub mod service;
+
+pub mod stable;
+
 pub mod pretty;
 
+#[cfg(feature = "random")]
+pub mod mock;
+
 // Candid hash function comes from
 // https://caml.inria.fr/pub/papers/garrigue-polymorphic_variants-ml98.pdf
 // Not public API. Only used by tests.
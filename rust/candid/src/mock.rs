@@ -0,0 +1,112 @@
+//! An in-process mock of a service, which honours the method types of a did file.
+//!
+//! Arguments are decoded and checked against the method types. Replies are the scripted
+//! responses of the method, in order, and random values of the result types afterwards.
+//! All valid calls are recorded, so that tests can make assertions on them.
+//!
+//! ```
+//! use candid::{check_prog, mock::MockService, IDLArgs, IDLProg, TypeEnv};
+//! let did_file = "service : { greet : (text) -> (text) }";
+//! let mut env = TypeEnv::new();
+//! let actor = check_prog(&mut env, &did_file.parse::<IDLProg>()?)?.unwrap();
+//! let mut mock = MockService::new(env, actor)?;
+//! mock.respond("greet", "(\"Hello, world!\")".parse()?)?;
+//!
+//! let reply = mock.call("greet", &IDLArgs::new(&[]).to_bytes()?);
+//! assert!(reply.is_err());
+//! let reply = mock.call("greet", &"(\"world\")".parse::<IDLArgs>()?.to_bytes()?)?;
+//! assert_eq!(IDLArgs::from_bytes(&reply)?.to_string(), "(\"Hello, world!\")");
+//! assert_eq!(mock.calls()[0].args.to_string(), "(\"world\")");
+//! # Ok::<(), candid::Error>(())
+//! ```
+
+use crate::parser::random::{random_args, GenConfig};
+use crate::parser::typing::TypeEnv;
+use crate::parser::value::IDLArgs;
+use crate::types::Type;
+use crate::{Error, Result};
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::{HashMap, VecDeque};
+
+/// A call received by the mock service.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub method: String,
+    /// Arguments, annotated with the argument types of the method.
+    pub args: IDLArgs,
+}
+
+pub struct MockService {
+    env: TypeEnv,
+    actor: Type,
+    responses: HashMap<String, VecDeque<IDLArgs>>,
+    calls: Vec<Call>,
+    rng: StdRng,
+    config: GenConfig,
+}
+
+impl MockService {
+    /// Creates a mock of the service `actor`, with type definitions in `env`.
+    pub fn new(env: TypeEnv, actor: Type) -> Result<Self> {
+        env.as_service(&actor)?;
+        Ok(MockService {
+            env,
+            actor,
+            responses: HashMap::new(),
+            calls: Vec::new(),
+            rng: StdRng::from_entropy(),
+            config: GenConfig::new(),
+        })
+    }
+    /// Makes the random responses deterministic.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+    /// Bounds for the random responses.
+    pub fn with_config(mut self, config: GenConfig) -> Self {
+        self.config = config;
+        self
+    }
+    /// Queues a response for the next call of `method` that is not answered by a previous
+    /// response. The response has to match the result types of the method.
+    pub fn respond(&mut self, method: &str, args: IDLArgs) -> Result<&mut Self> {
+        let func = self.env.get_method(&self.actor, method)?;
+        args.to_bytes_with_types(&self.env, &func.rets)
+            .map_err(|e| Error::msg(format!("response of {}: {}", method, e)))?;
+        self.responses
+            .entry(method.to_string())
+            .or_default()
+            .push_back(args);
+        Ok(self)
+    }
+    /// Calls `method` with the encoded arguments `args`, and returns the encoded reply.
+    pub fn call(&mut self, method: &str, args: &[u8]) -> Result<Vec<u8>> {
+        let func = self.env.get_method(&self.actor, method)?.clone();
+        let args = IDLArgs::from_bytes_with_types(args, &self.env, &func.args)
+            .map_err(|e| Error::msg(format!("arguments of {}: {}", method, e)))?;
+        self.calls.push(Call {
+            method: method.to_string(),
+            args,
+        });
+        let reply = match self.responses.get_mut(method).and_then(|r| r.pop_front()) {
+            Some(reply) => reply,
+            None => random_args(&mut self.rng, &self.env, &func.rets, &self.config)?,
+        };
+        reply.to_bytes_with_types(&self.env, &func.rets)
+    }
+    /// The calls received so far, in order.
+    pub fn calls(&self) -> &[Call] {
+        &self.calls
+    }
+    /// Forgets the calls received so far.
+    pub fn clear_calls(&mut self) {
+        self.calls.clear();
+    }
+    pub fn env(&self) -> &TypeEnv {
+        &self.env
+    }
+    pub fn actor(&self) -> &Type {
+        &self.actor
+    }
+}
@@ -0,0 +1,561 @@
+//! Formatter for Candid files.
+//!
+//! Unlike `bindings::candid::compile`, the formatter works on the concrete syntax of the file:
+//! it keeps comments, imports, the order of declarations and fields, and the original spelling of
+//! labels and type names. A comment is attached to the closest enclosing declaration, field or
+//! method. Comments in the middle of a type are moved before the enclosing item.
+
+use super::lexer::{Lexer, Token};
+use super::types::IDLProg;
+use crate::pretty::*;
+use crate::{Error, Result};
+use pretty::RcDoc;
+
+/// Formats the Candid file `input` with the line `width`.
+pub fn format(input: &str, width: usize) -> Result<String> {
+    // Report syntax errors with the same messages as the parser.
+    input.parse::<IDLProg>()?;
+    let mut parser = Parser::new(input)?;
+    let prog = parser.prog()?;
+    let doc = pp_prog(&prog);
+    let mut res = String::new();
+    for line in doc.pretty(width).to_string().lines() {
+        res.push_str(line.trim_end());
+        res.push('\n');
+    }
+    Ok(res)
+}
+
+#[derive(Debug)]
+struct Comment<'a> {
+    text: &'a str,
+    /// No line break between the previous token and the comment
+    same_line: bool,
+    blank_before: bool,
+}
+
+#[derive(Debug)]
+struct Tok<'a> {
+    /// `None` for the end of file
+    token: Option<Token>,
+    text: &'a str,
+    comments: Vec<Comment<'a>>,
+    blank_before: bool,
+}
+
+struct Item<'a, T> {
+    leading: Vec<Comment<'a>>,
+    blank_before: bool,
+    /// Blank line between the comments before the item and the item itself
+    blank_after: Option<usize>,
+    node: T,
+    trailing: Option<Comment<'a>>,
+}
+
+struct List<'a, T> {
+    items: Vec<Item<'a, T>>,
+    dangling: Vec<Comment<'a>>,
+}
+
+enum Ty<'a> {
+    Name(&'a str),
+    Opt(Box<Ty<'a>>),
+    Vec(Box<Ty<'a>>),
+    Record(List<'a, Field<'a>>),
+    Variant(List<'a, Field<'a>>),
+    Func(Func<'a>),
+    Service(List<'a, Method<'a>>),
+}
+
+struct Field<'a> {
+    label: Option<&'a str>,
+    ty: Option<Ty<'a>>,
+}
+
+struct Func<'a> {
+    args: List<'a, Field<'a>>,
+    rets: List<'a, Field<'a>>,
+    modes: Vec<&'a str>,
+}
+
+enum MethodType<'a> {
+    Func(Func<'a>),
+    Name(&'a str),
+}
+
+struct Method<'a> {
+    name: &'a str,
+    ty: MethodType<'a>,
+}
+
+enum Dec<'a> {
+    Type(&'a str, Ty<'a>),
+    Import(&'a str),
+}
+
+enum ActorType<'a> {
+    Service(List<'a, Method<'a>>),
+    Name(&'a str),
+}
+
+struct Actor<'a> {
+    name: Option<&'a str>,
+    ty: ActorType<'a>,
+}
+
+struct Prog<'a> {
+    decs: List<'a, Dec<'a>>,
+    actor: Option<Item<'a, Actor<'a>>>,
+}
+
+fn blank_line(gap: &str) -> bool {
+    gap.matches('\n').count() >= 2
+}
+
+struct Parser<'a> {
+    tokens: Vec<Tok<'a>>,
+    pos: usize,
+    /// Comments of the consumed tokens, not yet attached to an item
+    pending: Vec<Comment<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Result<Self> {
+        let mut tokens = Vec::new();
+        let mut comments = Vec::new();
+        let lexed = Lexer::with_comments(input)
+            .map(|tok| tok.map_err(|e| Error::msg(format!("{}", e))))
+            .collect::<Result<Vec<_>>>()?;
+        let starts: Vec<_> = lexed.iter().map(|(start, _, _)| *start).collect();
+        let mut last = 0;
+        for (i, (start, token, _)) in lexed.into_iter().enumerate() {
+            let gap = &input[last..start];
+            // A token runs until the next one, which only relies on the lexer for the starts
+            let next = starts.get(i + 1).copied().unwrap_or(input.len());
+            let text = input[start..next].trim_end();
+            last = start + text.len();
+            match token {
+                Token::LineComment(_) | Token::BlockComment(_) => comments.push(Comment {
+                    text,
+                    same_line: !gap.contains('\n'),
+                    blank_before: blank_line(gap),
+                }),
+                token => tokens.push(Tok {
+                    token: Some(token),
+                    text,
+                    comments: std::mem::take(&mut comments),
+                    blank_before: blank_line(gap),
+                }),
+            }
+        }
+        tokens.push(Tok {
+            token: None,
+            text: "",
+            comments,
+            blank_before: blank_line(&input[last..]),
+        });
+        Ok(Parser {
+            tokens,
+            pos: 0,
+            pending: Vec::new(),
+        })
+    }
+    fn peek(&self) -> Option<&Token> {
+        self.tokens[self.pos].token.as_ref()
+    }
+    fn peek2(&self) -> Option<&Token> {
+        self.tokens.get(self.pos + 1).and_then(|t| t.token.as_ref())
+    }
+    fn bump(&mut self) -> Result<&'a str> {
+        let tok = &mut self.tokens[self.pos];
+        if tok.token.is_none() {
+            return Err(Error::msg("unexpected end of file"));
+        }
+        self.pending.append(&mut tok.comments);
+        self.pos += 1;
+        Ok(tok.text)
+    }
+    fn expect(&mut self, token: Token) -> Result<&'a str> {
+        if self.peek() == Some(&token) {
+            self.bump()
+        } else {
+            Err(Error::msg(format!(
+                "expect {} but found {}",
+                token, self.tokens[self.pos].text
+            )))
+        }
+    }
+    fn eat(&mut self, token: Token) -> Result<bool> {
+        if self.peek() == Some(&token) {
+            self.bump()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+    /// Parses an item with `f`, and attaches the comments inside the item.
+    fn item<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<Item<'a, T>> {
+        let outer = std::mem::take(&mut self.pending);
+        let tok = &self.tokens[self.pos];
+        let blank_before = match tok.comments.first() {
+            Some(c) => c.blank_before,
+            None => tok.blank_before,
+        };
+        let blank_after = if !tok.comments.is_empty() && tok.blank_before {
+            Some(tok.comments.len())
+        } else {
+            None
+        };
+        let node = f(self)?;
+        let leading = std::mem::replace(&mut self.pending, outer);
+        Ok(Item {
+            leading,
+            blank_before,
+            blank_after,
+            node,
+            trailing: None,
+        })
+    }
+    /// Takes the comment on the same line as the previous token.
+    fn trailing(&mut self) -> Option<Comment<'a>> {
+        let comments = &mut self.tokens[self.pos].comments;
+        match comments.first() {
+            Some(c) if c.same_line => Some(comments.remove(0)),
+            _ => None,
+        }
+    }
+    /// Parses a list of items separated by `sep`. When `attach` is false, the comments inside
+    /// the list are attached to the enclosing item.
+    fn list<T>(
+        &mut self,
+        sep: Token,
+        close: Token,
+        attach: bool,
+        mut f: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<List<'a, T>> {
+        let outer = if attach {
+            std::mem::take(&mut self.pending)
+        } else {
+            Vec::new()
+        };
+        let mut items = Vec::new();
+        while self.peek() != Some(&close) {
+            let mut item = if attach {
+                self.item(&mut f)?
+            } else {
+                Item {
+                    leading: Vec::new(),
+                    blank_before: false,
+                    blank_after: None,
+                    node: f(self)?,
+                    trailing: None,
+                }
+            };
+            let has_sep = self.eat(sep.clone())?;
+            if attach {
+                // comments before the separator
+                item.leading.append(&mut self.pending);
+                item.trailing = self.trailing();
+            }
+            items.push(item);
+            if !has_sep {
+                break;
+            }
+        }
+        self.expect(close)?;
+        if !attach {
+            return Ok(List {
+                items,
+                dangling: Vec::new(),
+            });
+        }
+        let dangling = std::mem::replace(&mut self.pending, outer);
+        Ok(List { items, dangling })
+    }
+    /// Consumes the optional semicolon and the trailing comment of a top-level item.
+    fn end_item<T>(&mut self, item: &mut Item<'a, T>) -> Result<()> {
+        self.eat(Token::Semi)?;
+        item.leading.append(&mut self.pending);
+        item.trailing = self.trailing();
+        Ok(())
+    }
+    fn prog(&mut self) -> Result<Prog<'a>> {
+        let mut items = Vec::new();
+        while let Some(Token::Type) | Some(Token::Import) = self.peek() {
+            let mut item = self.item(|p| p.dec())?;
+            self.end_item(&mut item)?;
+            items.push(item);
+        }
+        let actor = if self.peek() == Some(&Token::Service) {
+            let mut item = self.item(|p| p.actor())?;
+            self.end_item(&mut item)?;
+            Some(item)
+        } else {
+            None
+        };
+        if self.peek().is_some() {
+            return Err(Error::msg(format!(
+                "unexpected token {}",
+                self.tokens[self.pos].text
+            )));
+        }
+        let mut dangling = std::mem::take(&mut self.pending);
+        dangling.append(&mut self.tokens[self.pos].comments);
+        let decs = List { items, dangling };
+        Ok(Prog { decs, actor })
+    }
+    fn dec(&mut self) -> Result<Dec<'a>> {
+        if self.eat(Token::Import)? {
+            let path = self.bump()?;
+            Ok(Dec::Import(path))
+        } else {
+            self.expect(Token::Type)?;
+            let id = self.bump()?;
+            self.expect(Token::Equals)?;
+            Ok(Dec::Type(id, self.ty()?))
+        }
+    }
+    fn actor(&mut self) -> Result<Actor<'a>> {
+        self.expect(Token::Service)?;
+        let name = match self.peek() {
+            Some(Token::Id(_)) => Some(self.bump()?),
+            _ => None,
+        };
+        self.expect(Token::Colon)?;
+        let ty = if self.eat(Token::LBrace)? {
+            ActorType::Service(self.methods()?)
+        } else {
+            ActorType::Name(self.bump()?)
+        };
+        Ok(Actor { name, ty })
+    }
+    fn ty(&mut self) -> Result<Ty<'a>> {
+        let tok = self.peek().cloned();
+        let text = self.bump()?;
+        Ok(match tok {
+            Some(Token::Opt) => Ty::Opt(Box::new(self.ty()?)),
+            Some(Token::Vec) => Ty::Vec(Box::new(self.ty()?)),
+            Some(Token::Record) => {
+                self.expect(Token::LBrace)?;
+                Ty::Record(self.list(Token::Semi, Token::RBrace, true, |p| p.field(false))?)
+            }
+            Some(Token::Variant) => {
+                self.expect(Token::LBrace)?;
+                Ty::Variant(self.list(Token::Semi, Token::RBrace, true, |p| p.field(true))?)
+            }
+            Some(Token::Func) => Ty::Func(self.func()?),
+            Some(Token::Service) => {
+                self.expect(Token::LBrace)?;
+                Ty::Service(self.methods()?)
+            }
+            _ => Ty::Name(text),
+        })
+    }
+    fn is_label(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(Token::Id(_)) | Some(Token::Text(_)) | Some(Token::Number(_))
+        )
+    }
+    fn field(&mut self, is_variant: bool) -> Result<Field<'a>> {
+        if self.is_label() && self.peek2() == Some(&Token::Colon) {
+            let label = self.bump()?;
+            self.bump()?;
+            Ok(Field {
+                label: Some(label),
+                ty: Some(self.ty()?),
+            })
+        } else if is_variant {
+            Ok(Field {
+                label: Some(self.bump()?),
+                ty: None,
+            })
+        } else {
+            Ok(Field {
+                label: None,
+                ty: Some(self.ty()?),
+            })
+        }
+    }
+    fn func(&mut self) -> Result<Func<'a>> {
+        self.expect(Token::LParen)?;
+        let args = self.list(Token::Comma, Token::RParen, false, |p| p.field(false))?;
+        self.expect(Token::Arrow)?;
+        self.expect(Token::LParen)?;
+        let rets = self.list(Token::Comma, Token::RParen, false, |p| p.field(false))?;
+        let mut modes = Vec::new();
+        while let Some(Token::Query) | Some(Token::Oneway) = self.peek() {
+            modes.push(self.bump()?);
+        }
+        Ok(Func { args, rets, modes })
+    }
+    fn methods(&mut self) -> Result<List<'a, Method<'a>>> {
+        self.list(Token::Semi, Token::RBrace, true, |p| {
+            let name = p.bump()?;
+            p.expect(Token::Colon)?;
+            let ty = match p.peek() {
+                Some(Token::LParen) => MethodType::Func(p.func()?),
+                _ => MethodType::Name(p.bump()?),
+            };
+            Ok(Method { name, ty })
+        })
+    }
+}
+
+// Pretty printing
+
+fn pp_comment<'a>(c: &Comment<'a>) -> RcDoc<'a> {
+    str(c.text)
+}
+
+fn has_comments<T>(list: &List<T>) -> bool {
+    !list.dangling.is_empty()
+        || list.items.iter().enumerate().any(|(i, item)| {
+            !item.leading.is_empty() || item.trailing.is_some() || (i > 0 && item.blank_before)
+        })
+}
+
+/// Prints the item on its own lines, with the leading and trailing comments.
+fn pp_item<'a, T>(
+    item: &'a Item<'a, T>,
+    first: bool,
+    sep: &'a str,
+    f: &impl Fn(&'a T) -> RcDoc<'a>,
+) -> RcDoc<'a> {
+    let mut doc = RcDoc::nil();
+    if !first && item.blank_before {
+        doc = doc.append(RcDoc::hardline());
+    }
+    for (i, c) in item.leading.iter().enumerate() {
+        if i > 0 && c.blank_before {
+            doc = doc.append(RcDoc::hardline());
+        }
+        doc = doc.append(pp_comment(c)).append(RcDoc::hardline());
+        if item.blank_after == Some(i + 1) {
+            doc = doc.append(RcDoc::hardline());
+        }
+    }
+    doc = doc.append(f(&item.node)).append(sep);
+    if let Some(c) = &item.trailing {
+        doc = doc.append(" ").append(pp_comment(c));
+    }
+    doc
+}
+
+fn pp_dangling<'a>(comments: &'a [Comment<'a>]) -> impl Iterator<Item = RcDoc<'a>> {
+    comments.iter().enumerate().map(|(i, c)| {
+        if i > 0 && c.blank_before {
+            RcDoc::hardline().append(pp_comment(c))
+        } else {
+            pp_comment(c)
+        }
+    })
+}
+
+fn pp_list<'a, T>(
+    (left, sep, right): (&'a str, &'a str, &'a str),
+    space: bool,
+    list: &'a List<'a, T>,
+    f: impl Fn(&'a T) -> RcDoc<'a>,
+) -> RcDoc<'a> {
+    if !has_comments(list) {
+        let doc = concat(list.items.iter().map(|item| f(&item.node)), sep);
+        return if space {
+            enclose_space(left, doc, right)
+        } else {
+            enclose(left, doc, right)
+        };
+    }
+    let items = list
+        .items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| pp_item(item, i == 0, sep, &f))
+        .chain(pp_dangling(&list.dangling));
+    str(left)
+        .append(RcDoc::hardline())
+        .append(RcDoc::intersperse(items, RcDoc::hardline()))
+        .nest(INDENT_SPACE)
+        .append(RcDoc::hardline())
+        .append(right)
+}
+
+fn pp_ty<'a>(ty: &'a Ty<'a>) -> RcDoc<'a> {
+    match ty {
+        Ty::Name(id) => str(id),
+        Ty::Opt(t) => kwd("opt").append(pp_ty(t)),
+        Ty::Vec(t) => kwd("vec").append(pp_ty(t)),
+        Ty::Record(fs) => kwd("record").append(pp_list(("{", ";", "}"), true, fs, pp_field)),
+        Ty::Variant(fs) => kwd("variant").append(pp_list(("{", ";", "}"), true, fs, pp_field)),
+        Ty::Func(func) => kwd("func").append(pp_func(func)),
+        Ty::Service(ms) => kwd("service").append(pp_methods(ms)),
+    }
+}
+
+fn pp_field<'a>(field: &'a Field<'a>) -> RcDoc<'a> {
+    match (field.label, &field.ty) {
+        (Some(label), Some(ty)) => str(label).append(kwd(" :")).append(pp_ty(ty)),
+        (Some(label), None) => str(label),
+        (None, Some(ty)) => pp_ty(ty),
+        (None, None) => unreachable!(),
+    }
+}
+
+fn pp_func<'a>(func: &'a Func<'a>) -> RcDoc<'a> {
+    let args = pp_list(("(", ",", ")"), false, &func.args, pp_field);
+    let rets = pp_list(("(", ",", ")"), false, &func.rets, pp_field);
+    let modes = RcDoc::concat(func.modes.iter().map(|m| RcDoc::space().append(*m)));
+    args.append(" ->")
+        .append(RcDoc::space())
+        .append(rets.append(modes))
+        .nest(INDENT_SPACE)
+}
+
+fn pp_method_type<'a>(ty: &'a MethodType<'a>) -> RcDoc<'a> {
+    match ty {
+        MethodType::Func(func) => pp_func(func),
+        MethodType::Name(id) => str(id),
+    }
+}
+
+fn pp_methods<'a>(ms: &'a List<'a, Method<'a>>) -> RcDoc<'a> {
+    pp_list(("{", ";", "}"), true, ms, |m| {
+        str(m.name).append(kwd(" :")).append(pp_method_type(&m.ty))
+    })
+}
+
+fn pp_dec<'a>(dec: &'a Dec<'a>) -> RcDoc<'a> {
+    match dec {
+        Dec::Type(id, ty) => kwd("type")
+            .append(ident(id))
+            .append(kwd("="))
+            .append(pp_ty(ty)),
+        Dec::Import(path) => kwd("import").append(*path),
+    }
+}
+
+fn pp_actor<'a>(actor: &'a Actor<'a>) -> RcDoc<'a> {
+    let name = match actor.name {
+        Some(id) => ident(id),
+        None => RcDoc::nil(),
+    };
+    let ty = match &actor.ty {
+        ActorType::Service(ms) => pp_methods(ms),
+        ActorType::Name(id) => str(id),
+    };
+    kwd("service").append(name).append(kwd(":")).append(ty)
+}
+
+fn pp_prog<'a>(prog: &'a Prog<'a>) -> RcDoc<'a> {
+    let decs = prog
+        .decs
+        .items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| pp_item(item, i == 0, ";", &pp_dec));
+    let actor = prog
+        .actor
+        .iter()
+        .map(|item| pp_item(item, prog.decs.items.is_empty(), "", &pp_actor));
+    lines(decs.chain(actor).chain(pp_dangling(&prog.decs.dangling)))
+}
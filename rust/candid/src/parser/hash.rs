@@ -0,0 +1,112 @@
+//! Representation-independent hashing of values, in the style of the Internet Computer.
+//!
+//! Values are hashed with SHA-256 after being annotated with their type, so the same logical
+//! value has the same hash, however it was encoded or parsed:
+//!
+//! * integers of any type are hashed as the SLEB128 encoding of their value, and floats as
+//!   the little-endian bytes of their value as `float64`;
+//! * texts and principals are hashed as their bytes, and booleans as one byte;
+//! * `null`, `reserved` and `opt` values are hashed as vectors of zero or one element;
+//! * vectors are hashed as the concatenation of the hashes of their elements;
+//! * records are hashed as the concatenation of `hash(id) · hash(value)` for each field,
+//!   sorted by field id, where `id` is the LEB128 encoding of the field id;
+//! * variants are hashed like a record with only the field of the case.
+//!
+//! The type is not part of the hash, so values of different types can have the same hash,
+//! such as `opt 5` and `vec { 5 }`.
+//!
+//! ```
+//! use candid::parser::hash::hash_value;
+//! use candid::{check_prog, IDLProg, TypeEnv};
+//! let mut env = TypeEnv::new();
+//! check_prog(&mut env, &"type T = record { a: nat8; b: text }".parse::<IDLProg>()?)?;
+//! let ty = env.find_type("T")?.clone();
+//! let h1 = hash_value(&env, &ty, &"record { a = 1; b = \"x\" }".parse()?)?;
+//! let h2 = hash_value(&env, &ty, &"record { b = \"x\"; 97 = 1; c = 3 }".parse()?)?;
+//! assert_eq!(h1, h2);
+//! # Ok::<(), candid::Error>(())
+//! ```
+
+use super::typing::TypeEnv;
+use super::value::{IDLArgs, IDLValue};
+use crate::types::Type;
+use crate::{Error, Int, Result};
+use num_bigint::BigInt;
+use sha2::{Digest, Sha256};
+
+pub type Hash = [u8; 32];
+
+/// Hashes `v` as a value of type `t`. Record fields that are not in `t` are ignored.
+pub fn hash_value(env: &TypeEnv, t: &Type, v: &IDLValue) -> Result<Hash> {
+    let v = v.annotate_type(true, env, t)?;
+    hash_annotated(&v)
+}
+
+/// Hashes the arguments `args` of types `types`, as a vector of arguments.
+pub fn hash_args(env: &TypeEnv, types: &[Type], args: &IDLArgs) -> Result<Hash> {
+    if types.len() != args.args.len() {
+        return Err(Error::msg("wrong number of argument values"));
+    }
+    let mut bytes = Vec::new();
+    for (v, t) in args.args.iter().zip(types.iter()) {
+        bytes.extend_from_slice(&hash_value(env, t, v)?);
+    }
+    Ok(sha256(&bytes))
+}
+
+fn hash_annotated(v: &IDLValue) -> Result<Hash> {
+    use IDLValue::*;
+    let mut bytes = std::vec::Vec::new();
+    match v {
+        Null | None | Reserved => (),
+        Bool(b) => bytes.push(*b as u8),
+        Text(s) => bytes.extend_from_slice(s.as_bytes()),
+        Principal(id) => bytes.extend_from_slice(&id.0),
+        Float64(n) => bytes.extend_from_slice(&n.to_le_bytes()),
+        Float32(n) => bytes.extend_from_slice(&(*n as f64).to_le_bytes()),
+        Int(n) => n.encode(&mut bytes)?,
+        Nat(n) => crate::Int(BigInt::from(n.0.clone())).encode(&mut bytes)?,
+        Nat8(n) => integer(*n, &mut bytes)?,
+        Nat16(n) => integer(*n, &mut bytes)?,
+        Nat32(n) => integer(*n, &mut bytes)?,
+        Nat64(n) => integer(*n, &mut bytes)?,
+        Int8(n) => integer(*n, &mut bytes)?,
+        Int16(n) => integer(*n, &mut bytes)?,
+        Int32(n) => integer(*n, &mut bytes)?,
+        Int64(n) => integer(*n, &mut bytes)?,
+        Opt(v) => bytes.extend_from_slice(&hash_annotated(v)?),
+        Vec(vs) => {
+            for v in vs.iter() {
+                bytes.extend_from_slice(&hash_annotated(v)?);
+            }
+        }
+        Record(fs) => {
+            let mut fs: std::vec::Vec<_> = fs.iter().collect();
+            fs.sort_by_key(|f| f.id.get_id());
+            for f in fs {
+                field(f.id.get_id(), &f.val, &mut bytes)?;
+            }
+        }
+        Variant(f, _) => field(f.id.get_id(), &f.val, &mut bytes)?,
+        Number(_) => return Err(Error::msg(format!("untyped number {}", v))),
+    }
+    Ok(sha256(&bytes))
+}
+
+fn integer<T: Into<BigInt>>(n: T, bytes: &mut std::vec::Vec<u8>) -> Result<()> {
+    Int(n.into()).encode(bytes)
+}
+
+fn field(id: u32, v: &IDLValue, bytes: &mut std::vec::Vec<u8>) -> Result<()> {
+    let mut key = std::vec::Vec::new();
+    leb128::write::unsigned(&mut key, id.into())?;
+    bytes.extend_from_slice(&sha256(&key));
+    bytes.extend_from_slice(&hash_annotated(v)?);
+    Ok(())
+}
+
+fn sha256(bytes: &[u8]) -> Hash {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&Sha256::digest(bytes));
+    hash
+}
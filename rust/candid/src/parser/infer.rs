@@ -0,0 +1,182 @@
+//! Type inference for values without type annotations.
+//!
+//! The element type of a vector is the least upper bound of the types of its elements,
+//! e.g., `vec { null; opt 5 }` has type `vec opt int`, and a record field missing from
+//! some elements gets its type from the other elements, as long as it is optional.
+
+use super::value::{IDLField, IDLValue};
+use crate::types::{Field, Label, Type};
+use crate::{Error, Result};
+use num_bigint::{BigInt, Sign};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Infer the type of `v`, so that it can be encoded with `IDLArgs::to_bytes_with_types`.
+/// Number literals have type `int`, or, when `narrow` is set, the narrowest number type
+/// that fits all the literals found at the same position.
+pub fn infer_type(v: &IDLValue, narrow: bool) -> Result<Type> {
+    Ok(shape(v)?.to_type(narrow))
+}
+
+/// The type of a value during inference.
+enum Shape {
+    /// Elements of an empty vector
+    Empty,
+    /// Number literals, between the two bounds
+    Number(BigInt, BigInt),
+    Null,
+    Reserved,
+    Prim(Type),
+    Opt(Box<Shape>),
+    Vec(Box<Shape>),
+    Record(BTreeMap<u32, (Label, Shape)>),
+    Variant(BTreeMap<u32, (Label, Shape)>),
+}
+
+fn shape(v: &IDLValue) -> Result<Shape> {
+    use IDLValue::*;
+    Ok(match v {
+        Null => Shape::Null,
+        Reserved => Shape::Reserved,
+        Number(n) => {
+            let n = n
+                .parse::<BigInt>()
+                .map_err(|_| Error::msg(format!("not a number {}", n)))?;
+            Shape::Number(n.clone(), n)
+        }
+        None => Shape::Opt(Box::new(Shape::Empty)),
+        Opt(v) => Shape::Opt(Box::new(shape(v)?)),
+        Vec(vs) => {
+            let mut elem = Shape::Empty;
+            for (i, v) in vs.iter().enumerate() {
+                elem = lub(elem, shape(v)?)
+                    .map_err(|e| Error::msg(format!("vector element {}: {}", i, e)))?;
+            }
+            Shape::Vec(Box::new(elem))
+        }
+        Record(fs) => Shape::Record(fields(fs)?),
+        Variant(f, _) => Shape::Variant(fields(std::slice::from_ref(f))?),
+        _ => Shape::Prim(v.value_ty()),
+    })
+}
+
+fn fields(fs: &[IDLField]) -> Result<BTreeMap<u32, (Label, Shape)>> {
+    fs.iter()
+        .map(|f| Ok((f.id.get_id(), (f.id.clone(), shape(&f.val)?))))
+        .collect()
+}
+
+/// The least upper bound of two shapes.
+fn lub(s1: Shape, s2: Shape) -> Result<Shape> {
+    use Shape::*;
+    Ok(match (s1, s2) {
+        (Empty, s) | (s, Empty) => s,
+        (Reserved, _) | (_, Reserved) => Reserved,
+        (Null, Null) => Null,
+        (Null, Opt(s)) | (Opt(s), Null) => Opt(s),
+        (Number(l1, h1), Number(l2, h2)) => Number(l1.min(l2), h1.max(h2)),
+        (Number(l, h), Prim(t)) | (Prim(t), Number(l, h)) if fits(&l, &h, &t) => Prim(t),
+        (Prim(t1), Prim(t2)) if t1 == t2 => Prim(t1),
+        (Opt(s1), Opt(s2)) => Opt(Box::new(lub(*s1, *s2)?)),
+        (Vec(s1), Vec(s2)) => Vec(Box::new(lub(*s1, *s2)?)),
+        (Record(fs1), Record(mut fs2)) => {
+            let mut fs = BTreeMap::new();
+            for (id, (label, s1)) in fs1.into_iter() {
+                let s = match fs2.remove(&id) {
+                    Some((_, s2)) => lub(s1, s2)
+                        .map_err(|e| Error::msg(format!("record field {}: {}", label, e)))?,
+                    None => missing(&label, s1)?,
+                };
+                fs.insert(id, (label, s));
+            }
+            for (id, (label, s2)) in fs2.into_iter() {
+                let s = missing(&label, s2)?;
+                fs.insert(id, (label, s));
+            }
+            Record(fs)
+        }
+        (Variant(mut fs1), Variant(fs2)) => {
+            for (id, (label, s)) in fs2.into_iter() {
+                let s = match fs1.remove(&id) {
+                    Some((_, s1)) => lub(s1, s)
+                        .map_err(|e| Error::msg(format!("variant case {}: {}", label, e)))?,
+                    None => s,
+                };
+                fs1.insert(id, (label, s));
+            }
+            Variant(fs1)
+        }
+        (s1, s2) => return Err(Error::msg(format!("no common type for {} and {}", s1, s2))),
+    })
+}
+
+/// A field missing from a record is read as `null`, so its type has to be optional.
+fn missing(label: &Label, s: Shape) -> Result<Shape> {
+    lub(Shape::Null, s).map_err(|_| {
+        Error::msg(format!(
+            "record field {} is missing from some records, but is not optional",
+            label
+        ))
+    })
+}
+
+/// Check if the number literals between `l` and `h` can be of type `t`.
+fn fits(l: &BigInt, h: &BigInt, t: &Type) -> bool {
+    let (min, max): (BigInt, BigInt) = match t {
+        Type::Nat8 => (u8::MIN.into(), u8::MAX.into()),
+        Type::Nat16 => (u16::MIN.into(), u16::MAX.into()),
+        Type::Nat32 => (u32::MIN.into(), u32::MAX.into()),
+        Type::Nat64 => (u64::MIN.into(), u64::MAX.into()),
+        Type::Int8 => (i8::MIN.into(), i8::MAX.into()),
+        Type::Int16 => (i16::MIN.into(), i16::MAX.into()),
+        Type::Int32 => (i32::MIN.into(), i32::MAX.into()),
+        Type::Int64 => (i64::MIN.into(), i64::MAX.into()),
+        Type::Nat => return l.sign() != Sign::Minus,
+        Type::Int | Type::Float32 | Type::Float64 => return true,
+        _ => return false,
+    };
+    &min <= l && h <= &max
+}
+
+impl Shape {
+    fn to_type(&self, narrow: bool) -> Type {
+        match self {
+            // Same as `IDLValue::value_ty` for empty vectors
+            Shape::Empty => Type::Null,
+            Shape::Number(l, h) if narrow => {
+                let types = if l.sign() == Sign::Minus {
+                    [Type::Int8, Type::Int16, Type::Int32, Type::Int64, Type::Int]
+                } else {
+                    [Type::Nat8, Type::Nat16, Type::Nat32, Type::Nat64, Type::Nat]
+                };
+                types.iter().find(|t| fits(l, h, t)).unwrap().clone()
+            }
+            Shape::Number(_, _) => Type::Int,
+            Shape::Null => Type::Null,
+            Shape::Reserved => Type::Reserved,
+            Shape::Prim(t) => t.clone(),
+            Shape::Opt(s) => Type::Opt(Box::new(s.to_type(narrow))),
+            Shape::Vec(s) => Type::Vec(Box::new(s.to_type(narrow))),
+            Shape::Record(fs) => Type::Record(to_fields(fs, narrow)),
+            Shape::Variant(fs) => Type::Variant(to_fields(fs, narrow)),
+        }
+    }
+}
+
+fn to_fields(fs: &BTreeMap<u32, (Label, Shape)>, narrow: bool) -> Vec<Field> {
+    fs.values()
+        .map(|(id, s)| Field {
+            id: id.clone(),
+            ty: s.to_type(narrow),
+        })
+        .collect()
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Shape::Number(_, _) => write!(f, "number"),
+            s => write!(f, "{}", s.to_type(false)),
+        }
+    }
+}
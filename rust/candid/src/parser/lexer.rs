@@ -1,4 +1,5 @@
 use lalrpop_util::ParseError;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::iter::Peekable;
 use std::str::CharIndices;
@@ -70,6 +71,12 @@ pub enum Token {
     Bytes(Vec<u8>),
     Number(String),
     Boolean(bool),
+    /// Only emitted by `Lexer::with_comments`
+    LineComment(String),
+    /// Only emitted by `Lexer::with_comments`
+    BlockComment(String),
+    /// `///` comment, only emitted by `Lexer::tokenize`
+    DocComment(String),
 }
 
 fn hex_to_char(hex: &str) -> Result<char, LexicalError> {
@@ -89,18 +96,67 @@ impl fmt::Display for Token {
     }
 }
 pub struct Lexer<'input> {
+    text: &'input str,
     input: Peekable<CharIndices<'input>>,
+    comments: bool,
+    docs: bool,
+}
+
+/// Doc comments in the input, keyed by the start position of the token that follows them.
+#[derive(Debug, Default)]
+pub struct DocTable(BTreeMap<usize, Vec<String>>);
+
+impl DocTable {
+    /// Returns the doc comments before the token at `pos`.
+    pub fn get(&self, pos: usize) -> Vec<String> {
+        self.0.get(&pos).cloned().unwrap_or_default()
+    }
 }
 
 impl<'input> Lexer<'input> {
     pub fn new(input: &'input str) -> Lexer<'input> {
         let mut lexer = Lexer {
+            text: input,
             input: input.char_indices().peekable(),
+            comments: false,
+            docs: false,
         };
         lexer.consume_whitespace();
         lexer
     }
 
+    /// Same as `new`, but also emits comments as tokens. The parser does not accept comment tokens.
+    pub fn with_comments(input: &'input str) -> Lexer<'input> {
+        let mut lexer = Lexer::new(input);
+        lexer.comments = true;
+        lexer
+    }
+
+    /// Lexes the whole input, and separates the doc comments from the other tokens.
+    /// Lexing stops at the first error.
+    pub fn tokenize(mut self) -> (Vec<Spanned<Token, usize, LexicalError>>, DocTable) {
+        self.docs = true;
+        let mut tokens = Vec::new();
+        let mut table = BTreeMap::new();
+        let mut docs = Vec::new();
+        for tok in self {
+            match tok {
+                Ok((_, Token::DocComment(doc), _)) => docs.push(doc),
+                Ok((l, tok, r)) => {
+                    if !docs.is_empty() {
+                        table.insert(l, std::mem::take(&mut docs));
+                    }
+                    tokens.push(Ok((l, tok, r)));
+                }
+                Err(e) => {
+                    tokens.push(Err(e));
+                    break;
+                }
+            }
+        }
+        (tokens, DocTable(table))
+    }
+
     fn next_char(&mut self) -> Option<(usize, char)> {
         self.input.next()
     }
@@ -254,16 +310,27 @@ impl<'input> Iterator for Lexer<'input> {
                 _ => Some(Ok((i, Token::Sign('-'), i + 1))),
             },
             Some((i, '"')) => Some(self.read_string_literal(i, true)),
-            Some((_, '/')) => {
+            Some((i, '/')) => {
                 match self.peek() {
                     Some((_, '/')) => {
                         // line comment
                         self.next_char();
-                        while let Some((_, c)) = self.next_char() {
+                        let mut end = self.text.len();
+                        while let Some((j, c)) = self.next_char() {
                             if c == '\n' || c == '\r' {
+                                end = j;
                                 break;
                             }
                         }
+                        let comment = &self.text[i..end];
+                        if self.comments {
+                            return Some(Ok((i, Token::LineComment(comment.to_string()), end)));
+                        }
+                        if self.docs && comment.starts_with("///") && !comment.starts_with("////") {
+                            let doc = &comment[3..];
+                            let doc = doc.strip_prefix(' ').unwrap_or(doc);
+                            return Some(Ok((i, Token::DocComment(doc.to_string()), end)));
+                        }
                         self.next()
                     }
                     Some((_, '*')) => {
@@ -271,15 +338,19 @@ impl<'input> Iterator for Lexer<'input> {
                         // TODO handle nested comments
                         self.next_char();
                         let mut seen_star = false;
-                        loop {
-                            if let Some((_, c)) = self.next_char() {
+                        let end = loop {
+                            if let Some((j, c)) = self.next_char() {
                                 if seen_star && c == '/' {
-                                    break;
+                                    break j + 1;
                                 }
                                 seen_star = c == '*';
                             } else {
                                 return Some(Err(LexicalError::Eof));
                             }
+                        };
+                        if self.comments {
+                            let comment = self.text[i..end].to_string();
+                            return Some(Ok((i, Token::BlockComment(comment), end)));
                         }
                         self.next()
                     }
@@ -296,18 +367,35 @@ impl<'input> Iterator for Lexer<'input> {
                             Ok(len) => len,
                             Err(e) => return Some(Err(e)),
                         };
-                        let res = match u64::from_str_radix(&res, 16) {
-                            Ok(n) => n.to_string(),
-                            Err(_) => return Some(Err(LexicalError::ParseError(res))),
+                        let res = match num_bigint::BigUint::parse_bytes(res.as_bytes(), 16) {
+                            Some(n) => n.to_string(),
+                            None => return Some(Err(LexicalError::ParseError(res))),
                         };
-                        Some(Ok((i, Token::Number(res), i + len)))
+                        Some(Ok((i, Token::Number(res), i + len + 2)))
                     } else {
                         Some(Err(LexicalError::UnknownChar('x')))
                     }
                 } else {
                     // Parse decimal number
                     let mut res = c.to_string();
-                    let len = self.read_num(&mut res, Radix::Decimal).unwrap_or(0) + 1;
+                    let mut len = self.read_num(&mut res, Radix::Decimal).unwrap_or(0) + 1;
+                    if let Some((_, e)) = self.peek() {
+                        if e == 'e' || e == 'E' {
+                            // Parse exponent, the number is then a float
+                            res.push(self.next_char().unwrap().1);
+                            len += 1;
+                            if let Some((_, sign)) = self.peek() {
+                                if sign == '+' || sign == '-' {
+                                    res.push(self.next_char().unwrap().1);
+                                    len += 1;
+                                }
+                            }
+                            match self.read_num(&mut res, Radix::Decimal) {
+                                Ok(n) => len += n,
+                                Err(e) => return Some(Err(e)),
+                            }
+                        }
+                    }
                     Some(Ok((i, Token::Number(res), i + len)))
                 }
             }
@@ -10,6 +10,15 @@ pub mod value;
 
 pub mod typing;
 
+pub mod infer;
+
+pub mod hash;
+
+pub mod format;
+
+#[cfg(feature = "random")]
+pub mod random;
+
 pub mod test;
 
 pub type ParserError = lalrpop_util::ParseError<usize, lexer::Token, lexer::LexicalError>;
@@ -0,0 +1,253 @@
+//! Generate random Candid values directed by Candid types.
+//!
+//! The generator is useful for randomized round-trip tests, fuzzing decoders, and making mock data.
+//! Recursive types are supported: once the depth budget is used up, the generator picks the
+//! smallest inhabitant for the remaining part of the value.
+
+use super::typing::TypeEnv;
+use super::value::{IDLArgs, IDLField, IDLValue};
+use crate::types::{Field, Type};
+use crate::{Error, Result};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use std::collections::BTreeSet;
+
+/// Bounds for the generated values.
+#[derive(Debug, Clone)]
+pub struct GenConfig {
+    /// Maximal nesting depth of opt, vec, record and variant values.
+    pub depth: usize,
+    /// Maximal length of vectors and text, and maximal byte length of principals.
+    pub size: usize,
+}
+
+impl Default for GenConfig {
+    fn default() -> Self {
+        GenConfig { depth: 5, size: 10 }
+    }
+}
+
+impl GenConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+    pub fn with_size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+}
+
+/// Generate a random value of type `ty`.
+pub fn random_value<R: Rng + ?Sized>(
+    rng: &mut R,
+    env: &TypeEnv,
+    ty: &Type,
+    config: &GenConfig,
+) -> Result<IDLValue> {
+    let gen = Generator { env, config };
+    gen.value(rng, ty, config.depth)
+}
+
+/// Generate a random argument sequence of types `types`.
+pub fn random_args<R: Rng + ?Sized>(
+    rng: &mut R,
+    env: &TypeEnv,
+    types: &[Type],
+    config: &GenConfig,
+) -> Result<IDLArgs> {
+    let mut args = Vec::new();
+    for ty in types.iter() {
+        args.push(random_value(rng, env, ty, config)?);
+    }
+    Ok(IDLArgs { args })
+}
+
+struct Generator<'a> {
+    env: &'a TypeEnv,
+    config: &'a GenConfig,
+}
+
+impl<'a> Generator<'a> {
+    fn value<R: Rng + ?Sized>(&self, rng: &mut R, ty: &Type, depth: usize) -> Result<IDLValue> {
+        use Type::*;
+        Ok(match ty {
+            Var(id) => self.value(rng, self.env.rec_find_type(id)?, depth)?,
+            Knot(id) => {
+                let ty = crate::types::internal::find_type(*id)
+                    .ok_or_else(|| Error::msg("knot TypeId not found"))?;
+                self.value(rng, &ty, depth)?
+            }
+            Null => IDLValue::Null,
+            Bool => IDLValue::Bool(rng.gen()),
+            Nat => IDLValue::Nat(crate::Nat::from(rng.gen::<u64>())),
+            Int => IDLValue::Int(crate::Int::from(rng.gen::<i64>())),
+            Nat8 => IDLValue::Nat8(rng.gen()),
+            Nat16 => IDLValue::Nat16(rng.gen()),
+            Nat32 => IDLValue::Nat32(rng.gen()),
+            Nat64 => IDLValue::Nat64(rng.gen()),
+            Int8 => IDLValue::Int8(rng.gen()),
+            Int16 => IDLValue::Int16(rng.gen()),
+            Int32 => IDLValue::Int32(rng.gen()),
+            Int64 => IDLValue::Int64(rng.gen()),
+            Float32 => IDLValue::Float32(rng.gen()),
+            Float64 => IDLValue::Float64(rng.gen()),
+            Text => {
+                let len = rng.gen_range(0, self.config.size + 1);
+                IDLValue::Text(rng.sample_iter(&Alphanumeric).take(len).collect())
+            }
+            Reserved => IDLValue::Reserved,
+            Principal => {
+                let len = rng.gen_range(0, self.config.size.min(29) + 1);
+                let bytes: std::vec::Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+                IDLValue::Principal(crate::Principal::from_bytes(bytes))
+            }
+            Opt(t) => {
+                if depth == 0 || rng.gen_bool(0.5) || self.min_depth(t).is_none() {
+                    IDLValue::None
+                } else {
+                    IDLValue::Opt(Box::new(self.value(rng, t, depth - 1)?))
+                }
+            }
+            Vec(t) => {
+                let len = if depth == 0 || self.min_depth(t).is_none() {
+                    0
+                } else {
+                    rng.gen_range(0, self.config.size + 1)
+                };
+                let mut vec = std::vec::Vec::with_capacity(len);
+                for _ in 0..len {
+                    vec.push(self.value(rng, t, depth - 1)?);
+                }
+                IDLValue::Vec(vec)
+            }
+            Record(fs) => {
+                if self.min_depth(ty).is_none() {
+                    return Err(Error::msg(format!(
+                        "cannot generate a value of type {}",
+                        ty
+                    )));
+                }
+                let depth = depth.saturating_sub(1);
+                let mut res = std::vec::Vec::with_capacity(fs.len());
+                for Field { id, ty } in fs.iter() {
+                    let val = self.value(rng, ty, depth)?;
+                    res.push(IDLField {
+                        id: id.clone(),
+                        val,
+                    });
+                }
+                IDLValue::Record(res)
+            }
+            Variant(fs) => {
+                let candidates: std::vec::Vec<_> = fs
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, f)| self.min_depth(&f.ty).map(|d| (i, d)))
+                    .collect();
+                let (idx, _) = if depth == 0 {
+                    candidates.iter().min_by_key(|(_, d)| *d)
+                } else {
+                    candidates.get(rng.gen_range(0, candidates.len().max(1)))
+                }
+                .ok_or_else(|| Error::msg(format!("cannot generate a value of type {}", ty)))?;
+                let f = &fs[*idx];
+                let val = self.value(rng, &f.ty, depth.saturating_sub(1))?;
+                let field = IDLField {
+                    id: f.id.clone(),
+                    val,
+                };
+                IDLValue::Variant(Box::new(field), *idx as u64)
+            }
+            Empty | Func(_) | Service(_) | Unknown => {
+                return Err(Error::msg(format!(
+                    "cannot generate a value of type {}",
+                    ty
+                )))
+            }
+        })
+    }
+    /// The minimal nesting depth of a value of type `ty`, or `None` if we cannot generate such a value.
+    fn min_depth(&self, ty: &Type) -> Option<usize> {
+        self.min_depth_rec(&mut BTreeSet::new(), ty)
+    }
+    fn min_depth_rec(&self, seen: &mut BTreeSet<String>, ty: &Type) -> Option<usize> {
+        use Type::*;
+        match ty {
+            Var(id) => {
+                if !seen.insert(id.to_string()) {
+                    return None;
+                }
+                let res = self
+                    .env
+                    .find_type(id)
+                    .ok()
+                    .and_then(|t| self.min_depth_rec(seen, t));
+                seen.remove(id);
+                res
+            }
+            Knot(id) => {
+                let name = format!("{:?}", id);
+                if !seen.insert(name.clone()) {
+                    return None;
+                }
+                let res = crate::types::internal::find_type(*id)
+                    .and_then(|t| self.min_depth_rec(seen, &t));
+                seen.remove(&name);
+                res
+            }
+            Opt(_) | Vec(_) => Some(0),
+            Record(fs) => fs.iter().try_fold(0, |acc, f| {
+                self.min_depth_rec(seen, &f.ty).map(|d| acc.max(d + 1))
+            }),
+            Variant(fs) => fs
+                .iter()
+                .filter_map(|f| self.min_depth_rec(seen, &f.ty))
+                .min()
+                .map(|d| d + 1),
+            Empty | Func(_) | Service(_) | Unknown => None,
+            _ => Some(0),
+        }
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+pub use self::strategy::ValueStrategy;
+
+#[cfg(feature = "arbitrary")]
+mod strategy {
+    use super::*;
+    use proptest::strategy::{Just, NewTree, Strategy};
+    use proptest::test_runner::TestRunner;
+
+    /// A `proptest` strategy that generates values of a Candid type. Generated values do not shrink.
+    #[derive(Debug, Clone)]
+    pub struct ValueStrategy {
+        env: TypeEnv,
+        ty: Type,
+        config: GenConfig,
+    }
+
+    impl ValueStrategy {
+        pub fn new(env: &TypeEnv, ty: &Type, config: GenConfig) -> Self {
+            ValueStrategy {
+                env: env.clone(),
+                ty: ty.clone(),
+                config,
+            }
+        }
+    }
+
+    impl Strategy for ValueStrategy {
+        type Tree = Just<IDLValue>;
+        type Value = IDLValue;
+        fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+            random_value(runner.rng(), &self.env, &self.ty, &self.config)
+                .map(Just)
+                .map_err(|e| e.to_string().into())
+        }
+    }
+}
@@ -68,8 +68,8 @@ impl Input {
 impl std::str::FromStr for Test {
     type Err = Error;
     fn from_str(str: &str) -> std::result::Result<Self, Self::Err> {
-        let lexer = super::lexer::Lexer::new(str);
-        Ok(super::grammar::TestParser::new().parse(lexer)?)
+        let (tokens, docs) = super::lexer::Lexer::new(str).tokenize();
+        Ok(super::grammar::TestParser::new().parse(&docs, tokens)?)
     }
 }
 
@@ -1,6 +1,7 @@
 use crate::types::Label;
 use crate::Result;
 use pretty::RcDoc;
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone)]
 pub enum IDLType {
@@ -93,6 +94,7 @@ impl FuncType {
 pub struct TypeField {
     pub label: Label,
     pub typ: IDLType,
+    pub docs: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -105,6 +107,7 @@ pub enum Dec {
 pub struct Binding {
     pub id: String,
     pub typ: IDLType,
+    pub docs: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -116,24 +119,165 @@ pub struct IDLProg {
 impl std::str::FromStr for IDLProg {
     type Err = crate::Error;
     fn from_str(str: &str) -> Result<Self> {
-        let lexer = super::lexer::Lexer::new(str);
-        Ok(super::grammar::IDLProgParser::new().parse(lexer)?)
+        let (tokens, docs) = super::lexer::Lexer::new(str).tokenize();
+        Ok(super::grammar::IDLProgParser::new().parse(&docs, tokens)?)
     }
 }
 
 impl std::str::FromStr for IDLType {
     type Err = crate::Error;
     fn from_str(str: &str) -> Result<Self> {
-        let lexer = super::lexer::Lexer::new(str);
-        Ok(super::grammar::TypParser::new().parse(lexer)?)
+        let (tokens, docs) = super::lexer::Lexer::new(str).tokenize();
+        Ok(super::grammar::TypParser::new().parse(&docs, tokens)?)
     }
 }
 
 impl std::str::FromStr for IDLTypes {
     type Err = crate::Error;
     fn from_str(str: &str) -> Result<Self> {
-        let lexer = super::lexer::Lexer::new(str);
-        Ok(super::grammar::TypsParser::new().parse(lexer)?)
+        let (tokens, docs) = super::lexer::Lexer::new(str).tokenize();
+        Ok(super::grammar::TypsParser::new().parse(&docs, tokens)?)
+    }
+}
+
+/// A step from a type to one of its parts, on the path to a doc comment. `opt` and `vec` are
+/// transparent, and the methods of a service are fields with the hash of their name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DocStep {
+    /// A record field or variant case, by id.
+    Field(u32),
+    /// An argument of a function, by position.
+    Arg(usize),
+    /// A result of a function, by position.
+    Ret(usize),
+}
+
+/// Doc comments of an `IDLProg`, indexed for the bindings that work on a `TypeEnv`.
+#[derive(Debug, Default)]
+pub struct DocComments {
+    /// Doc comments of type definitions.
+    pub types: BTreeMap<String, Vec<String>>,
+    /// Doc comments of the fields, variant cases and methods, keyed by the type definition
+    /// they are in, or `None` for the main service, and by their path from there.
+    pub fields: BTreeMap<(Option<String>, Vec<DocStep>), Vec<String>>,
+}
+
+impl DocComments {
+    pub fn from_prog(prog: &IDLProg) -> Self {
+        let mut res = DocComments::default();
+        for dec in prog.decs.iter() {
+            if let Dec::TypD(Binding { id, typ, docs }) = dec {
+                if !docs.is_empty() {
+                    res.types.insert(id.clone(), docs.clone());
+                }
+                res.collect(&Some(id.clone()), &mut Vec::new(), typ);
+            }
+        }
+        if let Some(actor) = &prog.actor {
+            res.collect(&None, &mut Vec::new(), actor);
+        }
+        res
+    }
+    fn collect(&mut self, scope: &Option<String>, path: &mut Vec<DocStep>, ty: &IDLType) {
+        match ty {
+            IDLType::OptT(t) | IDLType::VecT(t) => self.collect(scope, path, t),
+            IDLType::RecordT(fs) | IDLType::VariantT(fs) => {
+                for f in fs.iter() {
+                    let step = DocStep::Field(f.label.get_id());
+                    self.nested(scope, path, step, &f.docs, &f.typ);
+                }
+            }
+            IDLType::ServT(ms) => {
+                for m in ms.iter() {
+                    let step = DocStep::Field(crate::idl_hash(&m.id));
+                    self.nested(scope, path, step, &m.docs, &m.typ);
+                }
+            }
+            IDLType::FuncT(func) => {
+                for (i, t) in func.args.iter().enumerate() {
+                    self.nested(scope, path, DocStep::Arg(i), &[], t);
+                }
+                for (i, t) in func.rets.iter().enumerate() {
+                    self.nested(scope, path, DocStep::Ret(i), &[], t);
+                }
+            }
+            IDLType::PrimT(_) | IDLType::VarT(_) | IDLType::PrincipalT => (),
+        }
+    }
+    fn nested(
+        &mut self,
+        scope: &Option<String>,
+        path: &mut Vec<DocStep>,
+        step: DocStep,
+        docs: &[String],
+        ty: &IDLType,
+    ) {
+        path.push(step);
+        if !docs.is_empty() {
+            self.fields
+                .insert((scope.clone(), path.clone()), docs.to_vec());
+        }
+        self.collect(scope, path, ty);
+        path.pop();
+    }
+    pub fn type_docs(&self, id: &str) -> &[String] {
+        self.types.get(id).map_or(&[], |d| d)
+    }
+    /// Doc comments at `path` in the type definition `scope`, or in the main service if `None`.
+    pub fn field_docs(&self, scope: Option<&str>, path: &[DocStep]) -> &[String] {
+        if self.fields.is_empty() {
+            return &[];
+        }
+        let key = (scope.map(|s| s.to_string()), path.to_vec());
+        self.fields.get(&key).map_or(&[], |d| d)
+    }
+    /// Doc comments of the method `id` of the main service.
+    pub fn method_docs(&self, id: &str) -> &[String] {
+        self.field_docs(None, &[DocStep::Field(crate::idl_hash(id))])
+    }
+}
+
+/// A position in a type definition or in the main service, to find the doc comments of the
+/// parts of a type while printing it.
+#[derive(Debug, Clone)]
+pub struct DocCursor<'a> {
+    docs: Option<&'a DocComments>,
+    scope: Option<&'a str>,
+    path: Vec<DocStep>,
+}
+
+impl<'a> DocCursor<'a> {
+    /// A cursor without doc comments.
+    pub fn none() -> Self {
+        DocCursor {
+            docs: None,
+            scope: None,
+            path: Vec::new(),
+        }
+    }
+    /// The start of the type definition `scope`, or of the main service if `None`.
+    pub fn new(docs: &'a DocComments, scope: Option<&'a str>) -> Self {
+        DocCursor {
+            docs: Some(docs),
+            scope,
+            path: Vec::new(),
+        }
+    }
+    pub fn step(&self, step: DocStep) -> Self {
+        let mut path = self.path.clone();
+        path.push(step);
+        DocCursor {
+            docs: self.docs,
+            scope: self.scope,
+            path,
+        }
+    }
+    /// Doc comments at the cursor.
+    pub fn docs(&self) -> &'a [String] {
+        match self.docs {
+            Some(docs) => docs.field_docs(self.scope, &self.path),
+            None => &[],
+        }
     }
 }
 
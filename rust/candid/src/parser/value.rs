@@ -1,15 +1,19 @@
-use super::lexer::error;
 use super::typing::TypeEnv;
+use crate::types::subtype::{is_nullable, subtype, unroll, Gamma};
 use crate::types::{Field, Label, Type};
 use crate::{Error, Result};
 use crate::{Int, Nat};
 use serde::de;
 use serde::de::{Deserialize, Visitor};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 
-#[derive(Debug, PartialEq, Clone)]
+/// Values are compared and hashed structurally. Floats are ordered by `total_cmp`, so `NaN`
+/// equals itself, and `0.0` is different from `-0.0`.
+#[derive(Debug, Clone)]
 pub enum IDLValue {
     Bool(bool),
     Null,
@@ -37,17 +41,119 @@ pub enum IDLValue {
     Reserved,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub struct IDLField {
     pub id: Label,
     pub val: IDLValue,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub struct IDLArgs {
     pub args: Vec<IDLValue>,
 }
 
+impl IDLValue {
+    // Order of the variants in the declaration, which orders values of different variants.
+    fn rank(&self) -> u8 {
+        match self {
+            IDLValue::Bool(_) => 0,
+            IDLValue::Null => 1,
+            IDLValue::Text(_) => 2,
+            IDLValue::Number(_) => 3,
+            IDLValue::Float64(_) => 4,
+            IDLValue::Opt(_) => 5,
+            IDLValue::Vec(_) => 6,
+            IDLValue::Record(_) => 7,
+            IDLValue::Variant(_, _) => 8,
+            IDLValue::Principal(_) => 9,
+            IDLValue::None => 10,
+            IDLValue::Int(_) => 11,
+            IDLValue::Nat(_) => 12,
+            IDLValue::Nat8(_) => 13,
+            IDLValue::Nat16(_) => 14,
+            IDLValue::Nat32(_) => 15,
+            IDLValue::Nat64(_) => 16,
+            IDLValue::Int8(_) => 17,
+            IDLValue::Int16(_) => 18,
+            IDLValue::Int32(_) => 19,
+            IDLValue::Int64(_) => 20,
+            IDLValue::Float32(_) => 21,
+            IDLValue::Reserved => 22,
+        }
+    }
+}
+
+impl Ord for IDLValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        use IDLValue::*;
+        match (self, other) {
+            (Bool(a), Bool(b)) => a.cmp(b),
+            (Text(a), Text(b)) | (Number(a), Number(b)) => a.cmp(b),
+            (Float64(a), Float64(b)) => a.total_cmp(b),
+            (Opt(a), Opt(b)) => a.cmp(b),
+            (Vec(a), Vec(b)) => a.cmp(b),
+            (Record(a), Record(b)) => a.cmp(b),
+            (Variant(a, i), Variant(b, j)) => (a, i).cmp(&(b, j)),
+            (Principal(a), Principal(b)) => a.cmp(b),
+            (Int(a), Int(b)) => a.cmp(b),
+            (Nat(a), Nat(b)) => a.cmp(b),
+            (Nat8(a), Nat8(b)) => a.cmp(b),
+            (Nat16(a), Nat16(b)) => a.cmp(b),
+            (Nat32(a), Nat32(b)) => a.cmp(b),
+            (Nat64(a), Nat64(b)) => a.cmp(b),
+            (Int8(a), Int8(b)) => a.cmp(b),
+            (Int16(a), Int16(b)) => a.cmp(b),
+            (Int32(a), Int32(b)) => a.cmp(b),
+            (Int64(a), Int64(b)) => a.cmp(b),
+            (Float32(a), Float32(b)) => a.total_cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for IDLValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for IDLValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for IDLValue {}
+
+impl Hash for IDLValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        use IDLValue::*;
+        self.rank().hash(state);
+        match self {
+            Bool(b) => b.hash(state),
+            Text(s) | Number(s) => s.hash(state),
+            Float64(n) => n.to_bits().hash(state),
+            Opt(v) => v.hash(state),
+            Vec(vs) => vs.hash(state),
+            Record(fs) => fs.hash(state),
+            Variant(f, i) => (f, i).hash(state),
+            Principal(id) => id.hash(state),
+            Int(n) => n.hash(state),
+            Nat(n) => n.hash(state),
+            Nat8(n) => n.hash(state),
+            Nat16(n) => n.hash(state),
+            Nat32(n) => n.hash(state),
+            Nat64(n) => n.hash(state),
+            Int8(n) => n.hash(state),
+            Int16(n) => n.hash(state),
+            Int32(n) => n.hash(state),
+            Int64(n) => n.hash(state),
+            Float32(n) => n.to_bits().hash(state),
+            Null | None | Reserved => (),
+        }
+    }
+}
+
 impl IDLArgs {
     pub fn new(args: &[IDLValue]) -> Self {
         IDLArgs {
@@ -65,6 +171,25 @@ impl IDLArgs {
         }
         Ok(IDLArgs { args })
     }
+    /// Converts arguments of types `from` to the types `to`, as with `IDLValue::coerce_to`.
+    /// Extra arguments are dropped, and missing arguments of `opt` types read as `null`.
+    pub fn coerce_types(&self, env: &TypeEnv, from: &[Type], to: &[Type]) -> Result<Self> {
+        if from.len() != self.args.len() {
+            return Err(Error::msg("wrong number of argument values"));
+        }
+        let mut args = Vec::new();
+        for (i, ty) in to.iter().enumerate() {
+            let v = match (self.args.get(i), from.get(i)) {
+                (Some(v), Some(t)) => v
+                    .coerce_to(env, t, ty)
+                    .map_err(|e| Error::msg(format!("argument {}: {}", i, e)))?,
+                _ if is_nullable(env, ty) => IDLValue::Null.coerce_to(env, &Type::Null, ty)?,
+                _ => return Err(Error::msg(format!("argument {} is missing", i))),
+            };
+            args.push(v);
+        }
+        Ok(IDLArgs { args })
+    }
     /// Encode IDLArgs with the given types. Note that this is not equivalent to
     /// `idl_args.annotate_types(true, env, types).to_bytes()` for recursive types.
     pub fn to_bytes_with_types(&self, env: &TypeEnv, types: &[Type]) -> Result<Vec<u8>> {
@@ -113,11 +238,24 @@ impl IDLArgs {
     }
 }
 
+impl IDLArgs {
+    /// Parses the arguments, and returns the types of the arguments annotated in the text,
+    /// such as `(42 : nat8, vec {} : vec text)`.
+    pub fn parse_annotated(str: &str) -> Result<(Self, Vec<Option<Type>>)> {
+        let lexer = super::lexer::Lexer::new(str);
+        let docs = super::lexer::DocTable::default();
+        let args = super::grammar::AnnArgsParser::new().parse(&docs, lexer)?;
+        let (args, types) = args.into_iter().unzip();
+        Ok((IDLArgs { args }, types))
+    }
+}
+
 impl std::str::FromStr for IDLArgs {
     type Err = Error;
     fn from_str(str: &str) -> std::result::Result<Self, Self::Err> {
         let lexer = super::lexer::Lexer::new(str);
-        Ok(super::grammar::ArgsParser::new().parse(lexer)?)
+        let docs = super::lexer::DocTable::default();
+        Ok(super::grammar::ArgsParser::new().parse(&docs, lexer)?)
     }
 }
 
@@ -125,7 +263,8 @@ impl std::str::FromStr for IDLValue {
     type Err = Error;
     fn from_str(str: &str) -> std::result::Result<Self, Self::Err> {
         let lexer = super::lexer::Lexer::new(str);
-        Ok(super::grammar::ArgParser::new().parse(lexer)?)
+        let docs = super::lexer::DocTable::default();
+        Ok(super::grammar::ArgParser::new().parse(&docs, lexer)?)
     }
 }
 
@@ -185,6 +324,15 @@ impl fmt::Display for IDLValue {
     }
 }
 
+impl IDLField {
+    fn untyped(self) -> Self {
+        IDLField {
+            id: self.id,
+            val: self.val.untyped(),
+        }
+    }
+}
+
 impl fmt::Display for IDLField {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} = {}", self.id, self.val)
@@ -201,22 +349,28 @@ impl IDLValue {
                 self.annotate_type(from_parser, env, ty)?
             }
             (_, Type::Knot(id)) => {
-                let ty = crate::types::internal::find_type(*id).unwrap();
+                let ty = crate::types::internal::find_type(*id)
+                    .ok_or_else(|| Error::msg(format!("unknown type {:?}", id)))?;
                 self.annotate_type(from_parser, env, &ty)?
             }
             (IDLValue::Null, Type::Opt(_)) if from_parser => IDLValue::None,
             (IDLValue::Float64(n), Type::Float32) if from_parser => IDLValue::Float32(*n as f32),
+            (IDLValue::Text(s), Type::Principal) if from_parser => {
+                IDLValue::Principal(crate::Principal::from_text(s)?)
+            }
             (IDLValue::Number(str), t) if from_parser => match t {
                 Type::Int => IDLValue::Int(str.parse::<Int>()?),
                 Type::Nat => IDLValue::Nat(str.parse::<Nat>()?),
-                Type::Nat8 => IDLValue::Nat8(str.parse::<u8>().map_err(error)?),
-                Type::Nat16 => IDLValue::Nat16(str.parse::<u16>().map_err(error)?),
-                Type::Nat32 => IDLValue::Nat32(str.parse::<u32>().map_err(error)?),
-                Type::Nat64 => IDLValue::Nat64(str.parse::<u64>().map_err(error)?),
-                Type::Int8 => IDLValue::Int8(str.parse::<i8>().map_err(error)?),
-                Type::Int16 => IDLValue::Int16(str.parse::<i16>().map_err(error)?),
-                Type::Int32 => IDLValue::Int32(str.parse::<i32>().map_err(error)?),
-                Type::Int64 => IDLValue::Int64(str.parse::<i64>().map_err(error)?),
+                Type::Nat8 => IDLValue::Nat8(str.parse::<u8>().map_err(Error::msg)?),
+                Type::Nat16 => IDLValue::Nat16(str.parse::<u16>().map_err(Error::msg)?),
+                Type::Nat32 => IDLValue::Nat32(str.parse::<u32>().map_err(Error::msg)?),
+                Type::Nat64 => IDLValue::Nat64(str.parse::<u64>().map_err(Error::msg)?),
+                Type::Int8 => IDLValue::Int8(str.parse::<i8>().map_err(Error::msg)?),
+                Type::Int16 => IDLValue::Int16(str.parse::<i16>().map_err(Error::msg)?),
+                Type::Int32 => IDLValue::Int32(str.parse::<i32>().map_err(Error::msg)?),
+                Type::Int64 => IDLValue::Int64(str.parse::<i64>().map_err(Error::msg)?),
+                Type::Float32 => IDLValue::Float32(str.parse::<f32>().map_err(Error::msg)?),
+                Type::Float64 => IDLValue::Float64(str.parse::<f64>().map_err(Error::msg)?),
                 _ => {
                     return Err(Error::msg(format!(
                         "type mismatch: {} can not be of type {}",
@@ -258,10 +412,14 @@ impl IDLValue {
                     vec.iter().map(|IDLField { id, val }| (id, val)).collect();
                 let mut res = Vec::new();
                 for Field { id, ty } in fs.iter() {
-                    let val = fields
-                        .get(&id)
-                        .ok_or_else(|| Error::msg(format!("field {} not found", id)))?;
-                    let val = val.annotate_type(from_parser, env, ty)?;
+                    let val = match fields.get(&id) {
+                        Some(val) => val.annotate_type(from_parser, env, ty)?,
+                        // A missing optional field reads as null, as in the decoder
+                        None if from_parser && is_nullable(env, ty) => {
+                            IDLValue::Null.annotate_type(from_parser, env, ty)?
+                        }
+                        None => return Err(Error::msg(format!("field {} not found", id))),
+                    };
                     res.push(IDLField {
                         id: id.clone(),
                         val,
@@ -291,6 +449,117 @@ impl IDLValue {
             }
         })
     }
+    /// Resets the variant indices set by `annotate_type`, so that the value can be
+    /// serialized without a type, like any parsed or deserialized value.
+    pub fn untyped(self) -> Self {
+        match self {
+            IDLValue::Opt(v) => IDLValue::Opt(Box::new(v.untyped())),
+            IDLValue::Vec(vec) => IDLValue::Vec(vec.into_iter().map(IDLValue::untyped).collect()),
+            IDLValue::Record(fs) => {
+                IDLValue::Record(fs.into_iter().map(IDLField::untyped).collect())
+            }
+            IDLValue::Variant(f, _) => IDLValue::Variant(Box::new(f.untyped()), 0),
+            v => v,
+        }
+    }
+    /// Converts a value of type `from` to the supertype `to`, following the coercion rules
+    /// of the spec: extra record fields are dropped, missing `opt` fields read as `null`,
+    /// and a value that does not fit under `opt` becomes `null`. The value is expected to be
+    /// annotated with `from`, such as values decoded by `IDLArgs::from_bytes_with_types`.
+    pub fn coerce_to(&self, env: &TypeEnv, from: &Type, to: &Type) -> Result<Self> {
+        if let Type::Var(_) | Type::Knot(_) = from {
+            return self.coerce_to(env, &unroll(env, from)?, to);
+        }
+        if let Type::Var(_) | Type::Knot(_) = to {
+            return self.coerce_to(env, from, &unroll(env, to)?);
+        }
+        if from == to {
+            return Ok(self.clone());
+        }
+        Ok(match (self, from, to) {
+            (_, _, Type::Reserved) => IDLValue::Reserved,
+            (IDLValue::Nat(n), Type::Nat, Type::Int) => IDLValue::Int(Int(n.0.clone().into())),
+            (IDLValue::Null, _, Type::Opt(_))
+            | (IDLValue::None, _, Type::Opt(_))
+            | (IDLValue::Reserved, _, Type::Opt(_)) => IDLValue::None,
+            (IDLValue::Opt(v), Type::Opt(t1), Type::Opt(t2)) => match v.coerce_to(env, t1, t2) {
+                Ok(v) => IDLValue::Opt(Box::new(v)),
+                Err(_) => IDLValue::None,
+            },
+            (_, _, Type::Opt(t2)) => {
+                if is_nullable(env, from) {
+                    IDLValue::None
+                } else {
+                    match self.coerce_to(env, from, t2) {
+                        Ok(v) => IDLValue::Opt(Box::new(v)),
+                        Err(_) => IDLValue::None,
+                    }
+                }
+            }
+            (IDLValue::Vec(vec), Type::Vec(t1), Type::Vec(t2)) => {
+                let mut res = Vec::new();
+                for e in vec.iter() {
+                    res.push(e.coerce_to(env, t1, t2)?);
+                }
+                IDLValue::Vec(res)
+            }
+            (IDLValue::Record(vec), Type::Record(fs1), Type::Record(fs2)) => {
+                let fields: HashMap<_, _> =
+                    vec.iter().map(|IDLField { id, val }| (id, val)).collect();
+                let types: HashMap<_, _> = fs1.iter().map(|Field { id, ty }| (id, ty)).collect();
+                let mut res = Vec::new();
+                for Field { id, ty } in fs2.iter() {
+                    let val = match (fields.get(&id), types.get(&id)) {
+                        (Some(val), Some(t1)) => val
+                            .coerce_to(env, t1, ty)
+                            .map_err(|e| Error::msg(format!("record field {}: {}", id, e)))?,
+                        (None, _) if is_nullable(env, ty) => {
+                            IDLValue::Null.coerce_to(env, &Type::Null, ty)?
+                        }
+                        _ => return Err(Error::msg(format!("record field {} is missing", id))),
+                    };
+                    res.push(IDLField {
+                        id: id.clone(),
+                        val,
+                    });
+                }
+                IDLValue::Record(res)
+            }
+            (IDLValue::Variant(v, _), Type::Variant(fs1), Type::Variant(fs2)) => {
+                let t1 = fs1
+                    .iter()
+                    .find(|f| f.id == v.id)
+                    .ok_or_else(|| Error::msg(format!("variant case {} not found", v.id)))?;
+                for (i, f) in fs2.iter().enumerate() {
+                    if v.id == f.id {
+                        let val = v
+                            .val
+                            .coerce_to(env, &t1.ty, &f.ty)
+                            .map_err(|e| Error::msg(format!("variant case {}: {}", f.id, e)))?;
+                        let field = IDLField {
+                            id: f.id.clone(),
+                            val,
+                        };
+                        return Ok(IDLValue::Variant(Box::new(field), i as u64));
+                    }
+                }
+                return Err(Error::msg(format!("variant case {} is unexpected", v.id)));
+            }
+            // References are kept as they are, when their types are subtypes
+            (_, Type::Func(_), Type::Func(_))
+            | (_, Type::Service(_), Type::Service(_))
+            | (_, Type::Principal, Type::Principal) => {
+                subtype(&mut Gamma::new(), env, from, to)?;
+                self.clone()
+            }
+            _ => {
+                return Err(Error::msg(format!(
+                    "type mismatch: {} cannot be coerced from {} to {}",
+                    self, from, to
+                )))
+            }
+        })
+    }
     // This will only be called when the type is not provided
     pub fn value_ty(&self) -> Type {
         match *self {
@@ -347,6 +616,243 @@ impl IDLValue {
     }
 }
 
+/// A path into a value, such as `.items[2].owner`, in the style of jq.
+///
+/// * `.name` or `.42` selects a record field, by name or by hash;
+/// * `[n]` selects an element of a vector, and `[*]` selects all elements of a vector
+///   or all fields of a record;
+/// * `.?` selects the content of an opt, and nothing for `null`;
+/// * `#name` selects the content of a variant, and nothing if the variant is another case.
+///
+/// The path `.` selects the value itself.
+#[derive(Debug, PartialEq, Clone)]
+pub struct IDLPath(pub Vec<PathStep>);
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PathStep {
+    Field(Label),
+    Index(usize),
+    All,
+    Opt,
+    Case(Label),
+}
+
+impl std::str::FromStr for IDLPath {
+    type Err = Error;
+    fn from_str(str: &str) -> std::result::Result<Self, Self::Err> {
+        let str = str.trim();
+        if str == "." {
+            return Ok(IDLPath(Vec::new()));
+        }
+        let mut steps = Vec::new();
+        let mut rest = str;
+        while let Some(c) = rest.chars().next() {
+            let (step, len) = match c {
+                '.' if rest[1..].starts_with('?') => (PathStep::Opt, 2),
+                '.' => {
+                    let (id, len) = parse_label(&rest[1..])?;
+                    (PathStep::Field(id), len + 1)
+                }
+                '#' => {
+                    let (id, len) = parse_label(&rest[1..])?;
+                    (PathStep::Case(id), len + 1)
+                }
+                '[' => {
+                    let end = rest
+                        .find(']')
+                        .ok_or_else(|| Error::msg(format!("unclosed [ in path {}", str)))?;
+                    let step = match rest[1..end].trim() {
+                        "*" => PathStep::All,
+                        n => PathStep::Index(
+                            n.parse()
+                                .map_err(|_| Error::msg(format!("invalid index [{}]", n)))?,
+                        ),
+                    };
+                    (step, end + 1)
+                }
+                _ => {
+                    return Err(Error::msg(format!(
+                        "unexpected {} in path {}, expects ., # or [",
+                        c, str
+                    )))
+                }
+            };
+            steps.push(step);
+            rest = &rest[len..];
+        }
+        Ok(IDLPath(steps))
+    }
+}
+
+// Numbers are field ids, and other names are hashed when comparing labels.
+fn parse_label(str: &str) -> Result<(Label, usize)> {
+    let len = str
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(str.len());
+    let name = &str[..len];
+    if name.is_empty() {
+        return Err(Error::msg("expects a field name in path"));
+    }
+    let id = match name.parse::<u32>() {
+        Ok(n) => Label::Id(n),
+        Err(_) => Label::Named(name.to_string()),
+    };
+    Ok((id, len))
+}
+
+impl fmt::Display for PathStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathStep::Field(id) => write!(f, ".{}", id),
+            PathStep::Index(n) => write!(f, "[{}]", n),
+            PathStep::All => write!(f, "[*]"),
+            PathStep::Opt => write!(f, ".?"),
+            PathStep::Case(id) => write!(f, "#{}", id),
+        }
+    }
+}
+
+impl fmt::Display for IDLPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, ".");
+        }
+        for step in self.0.iter() {
+            write!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+impl PathStep {
+    fn select<'a>(&self, v: &'a IDLValue) -> Result<Vec<&'a IDLValue>> {
+        Ok(match (self, v) {
+            (PathStep::Field(id), IDLValue::Record(fs)) => match fs.iter().find(|f| f.id == *id) {
+                Some(f) => vec![&f.val],
+                None => return Err(Error::msg(format!("field {} not found", id))),
+            },
+            (PathStep::Index(n), IDLValue::Vec(vs)) => match vs.get(*n) {
+                Some(v) => vec![v],
+                None => return Err(out_of_range(*n, vs.len())),
+            },
+            (PathStep::All, IDLValue::Vec(vs)) => vs.iter().collect(),
+            (PathStep::All, IDLValue::Record(fs)) => fs.iter().map(|f| &f.val).collect(),
+            (PathStep::Opt, IDLValue::Opt(v)) => vec![v],
+            (PathStep::Opt, IDLValue::Null) | (PathStep::Opt, IDLValue::None) => Vec::new(),
+            (PathStep::Case(id), IDLValue::Variant(f, _)) => {
+                if f.id == *id {
+                    vec![&f.val]
+                } else {
+                    Vec::new()
+                }
+            }
+            _ => return Err(self.mismatch(v)),
+        })
+    }
+    fn select_mut<'a>(&self, v: &'a mut IDLValue) -> Result<Vec<&'a mut IDLValue>> {
+        Ok(match (self, v) {
+            (PathStep::Field(id), IDLValue::Record(fs)) => {
+                match fs.iter_mut().find(|f| f.id == *id) {
+                    Some(f) => vec![&mut f.val],
+                    None => return Err(Error::msg(format!("field {} not found", id))),
+                }
+            }
+            (PathStep::Index(n), IDLValue::Vec(vs)) => {
+                let len = vs.len();
+                match vs.get_mut(*n) {
+                    Some(v) => vec![v],
+                    None => return Err(out_of_range(*n, len)),
+                }
+            }
+            (PathStep::All, IDLValue::Vec(vs)) => vs.iter_mut().collect(),
+            (PathStep::All, IDLValue::Record(fs)) => fs.iter_mut().map(|f| &mut f.val).collect(),
+            (PathStep::Opt, IDLValue::Opt(v)) => vec![v],
+            (PathStep::Opt, IDLValue::Null) | (PathStep::Opt, IDLValue::None) => Vec::new(),
+            (PathStep::Case(id), IDLValue::Variant(f, _)) => {
+                if f.id == *id {
+                    vec![&mut f.val]
+                } else {
+                    Vec::new()
+                }
+            }
+            (_, v) => return Err(self.mismatch(v)),
+        })
+    }
+    fn mismatch(&self, v: &IDLValue) -> Error {
+        let kind = match v {
+            IDLValue::Record(_) => "a record",
+            IDLValue::Vec(_) => "a vector",
+            IDLValue::Opt(_) | IDLValue::None => "an opt",
+            IDLValue::Variant(_, _) => "a variant",
+            IDLValue::Null => "null",
+            _ => "a primitive value",
+        };
+        Error::msg(format!("cannot apply {} to {}", self, kind))
+    }
+}
+
+fn out_of_range(n: usize, len: usize) -> Error {
+    Error::msg(format!(
+        "index {} is out of range for a vector of length {}",
+        n, len
+    ))
+}
+
+impl IDLValue {
+    /// Returns the values at `path`, in order.
+    pub fn select(&self, path: &IDLPath) -> Result<Vec<&IDLValue>> {
+        let mut res = vec![self];
+        for step in path.0.iter() {
+            let mut next = Vec::new();
+            for v in res.into_iter() {
+                next.extend(step.select(v)?);
+            }
+            res = next;
+        }
+        Ok(res)
+    }
+    /// Applies `f` to the values at `path`, and returns the number of updated values.
+    pub fn update<F: FnMut(&mut IDLValue)>(&mut self, path: &IDLPath, mut f: F) -> Result<usize> {
+        update(self, &path.0, &mut f)
+    }
+}
+
+fn update(v: &mut IDLValue, path: &[PathStep], f: &mut dyn FnMut(&mut IDLValue)) -> Result<usize> {
+    match path.split_first() {
+        None => {
+            f(v);
+            Ok(1)
+        }
+        Some((step, path)) => {
+            let mut n = 0;
+            for v in step.select_mut(v)? {
+                n += update(v, path, f)?;
+            }
+            Ok(n)
+        }
+    }
+}
+
+impl IDLArgs {
+    /// Returns the values at `path` in each argument, in order.
+    pub fn select(&self, path: &IDLPath) -> Result<Vec<&IDLValue>> {
+        let mut res = Vec::new();
+        for v in self.args.iter() {
+            res.extend(v.select(path)?);
+        }
+        Ok(res)
+    }
+    /// Applies `f` to the values at `path` in each argument, and returns the number of
+    /// updated values.
+    pub fn update<F: FnMut(&mut IDLValue)>(&mut self, path: &IDLPath, mut f: F) -> Result<usize> {
+        let mut n = 0;
+        for v in self.args.iter_mut() {
+            n += update(v, &path.0, &mut f)?;
+        }
+        Ok(n)
+    }
+}
+
 pub mod pretty {
     use super::*;
     use crate::pretty::*;
@@ -370,6 +876,9 @@ pub mod pretty {
         match &*v {
             Opt(v) => kwd("opt").append(enclose_space("{", pp_value(v), "}")),
             Vec(vs) => {
+                if let Some(bytes) = as_blob(vs) {
+                    return kwd("blob").append(RcDoc::text(pp_blob(&bytes)));
+                }
                 let body = concat(vs.iter().map(|v| pp_value(v)), ";");
                 kwd("vec").append(enclose_space("{", body, "}"))
             }
@@ -379,6 +888,31 @@ pub mod pretty {
         }
     }
 
+    fn as_blob(vs: &[IDLValue]) -> Option<Vec<u8>> {
+        if vs.is_empty() {
+            return None;
+        }
+        vs.iter()
+            .map(|v| match v {
+                IDLValue::Nat8(n) => Some(*n),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn pp_blob(bytes: &[u8]) -> String {
+        let mut res = String::from("\"");
+        for b in bytes.iter() {
+            match b {
+                b'"' | b'\\' => res.push_str(&format!("\\{}", *b as char)),
+                0x20..=0x7e => res.push(*b as char),
+                _ => res.push_str(&format!("\\{:02x}", b)),
+            }
+        }
+        res.push('"');
+        res
+    }
+
     pub fn pp_args(args: &IDLArgs) -> RcDoc {
         let body = concat(args.args.iter().map(pp_value), ",");
         enclose("(", body, ")")
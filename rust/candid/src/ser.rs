@@ -224,7 +224,7 @@ impl TypeSerialize {
         // from the type table.
         // Someone should implement Pottier's O(nlogn) algorithm
         // http://gallium.inria.fr/~fpottier/publis/gauthier-fpottier-icfp04.pdf
-        let unrolled = types::internal::unroll(t);
+        let unrolled = types::internal::try_unroll(t)?;
         if let Some(idx) = self.type_map.get(&unrolled) {
             let idx = *idx;
             self.type_map.insert((*t).clone(), idx);
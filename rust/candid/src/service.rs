@@ -0,0 +1,217 @@
+//! Dispatch encoded calls to typed Rust handlers, checked against the service type of a did file.
+//!
+//! ```
+//! use candid::{check_prog, service::Dispatcher, Decode, Encode, IDLProg, TypeEnv};
+//! let did_file = "service : { greet : (text) -> (text) }";
+//! let mut env = TypeEnv::new();
+//! let actor = check_prog(&mut env, &did_file.parse::<IDLProg>()?)?.unwrap();
+//! let mut dispatcher = Dispatcher::new(env, actor)?;
+//! dispatcher.method("greet", |(name,): (String,)| (format!("Hello, {}!", name),))?;
+//!
+//! let reply = dispatcher.dispatch("greet", &Encode!(&"world")?).unwrap();
+//! assert_eq!(Decode!(&reply, String)?, "Hello, world!");
+//! # Ok::<(), candid::Error>(())
+//! ```
+
+use crate::de::IDLDeserialize;
+use crate::parser::typing::TypeEnv;
+use crate::ser::IDLBuilder;
+use crate::types::subtype::{subtype, tuple, Gamma};
+use crate::types::{CandidType, Type};
+use crate::{Error, Result};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A sequence of arguments, decoded from a Candid message as a tuple of Rust values.
+/// Missing trailing arguments are decoded as `null`.
+pub trait ArgumentDecoder: Sized {
+    fn types() -> Vec<Type>;
+    fn decode(de: &mut IDLDeserialize<'_>) -> Result<Self>;
+}
+
+/// A sequence of results, encoded from a tuple of Rust values as a Candid message.
+pub trait ArgumentEncoder {
+    fn types() -> Vec<Type>;
+    fn encode(&self, ser: &mut IDLBuilder) -> Result<()>;
+}
+
+macro_rules! argument_impls {
+    ($(($($n:tt $name:ident)*))+) => {
+        $(
+            impl<$($name),*> ArgumentDecoder for ($($name,)*)
+            where
+                $($name: CandidType + DeserializeOwned,)*
+            {
+                fn types() -> Vec<Type> {
+                    vec![$($name::ty()),*]
+                }
+                #[allow(unused_variables)]
+                fn decode(de: &mut IDLDeserialize<'_>) -> Result<Self> {
+                    Ok(($(de.get_value_or_null::<$name>()?,)*))
+                }
+            }
+            impl<$($name),*> ArgumentEncoder for ($($name,)*)
+            where
+                $($name: CandidType,)*
+            {
+                fn types() -> Vec<Type> {
+                    vec![$($name::ty()),*]
+                }
+                #[allow(unused_variables)]
+                fn encode(&self, ser: &mut IDLBuilder) -> Result<()> {
+                    $(ser.arg(&self.$n)?;)*
+                    Ok(())
+                }
+            }
+        )+
+    }
+}
+
+argument_impls! {
+    ()
+    (0 T0)
+    (0 T0 1 T1)
+    (0 T0 1 T1 2 T2)
+    (0 T0 1 T1 2 T2 3 T3)
+    (0 T0 1 T1 2 T2 3 T3 4 T4)
+    (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5)
+    (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6)
+    (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectCode {
+    /// The service has no such method, or the method has no handler.
+    MethodNotFound,
+    /// The arguments cannot be decoded at the argument types of the handler.
+    InvalidArguments,
+    /// The handler rejected the call.
+    HandlerReject,
+    /// The results of the handler cannot be encoded.
+    InvalidReply,
+}
+
+/// Why a call was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reject {
+    pub code: RejectCode,
+    pub message: String,
+}
+
+impl Reject {
+    fn new<T: fmt::Display>(code: RejectCode, message: T) -> Self {
+        Reject {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Reject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for Reject {}
+
+type Handler = Box<dyn FnMut(&[u8]) -> std::result::Result<Vec<u8>, Reject>>;
+
+pub struct Dispatcher {
+    env: TypeEnv,
+    actor: Type,
+    handlers: BTreeMap<String, Handler>,
+}
+
+impl Dispatcher {
+    /// Creates a dispatcher for the service `actor`, with type definitions in `env`.
+    pub fn new(env: TypeEnv, actor: Type) -> Result<Self> {
+        env.as_service(&actor)?;
+        Ok(Dispatcher {
+            env,
+            actor,
+            handlers: BTreeMap::new(),
+        })
+    }
+    /// Registers the handler of `method`. See `try_method`.
+    pub fn method<A, R, F>(&mut self, method: &str, mut handler: F) -> Result<&mut Self>
+    where
+        A: ArgumentDecoder + 'static,
+        R: ArgumentEncoder + 'static,
+        F: FnMut(A) -> R + 'static,
+    {
+        self.try_method(method, move |args| Ok(handler(args)))
+    }
+    /// Registers the handler of `method`, which can reject calls with an error message.
+    /// The handler has to accept the arguments of the method, and its results have to be
+    /// valid results of the method, according to the subtyping rules.
+    pub fn try_method<A, R, F>(&mut self, method: &str, mut handler: F) -> Result<&mut Self>
+    where
+        A: ArgumentDecoder + 'static,
+        R: ArgumentEncoder + 'static,
+        F: FnMut(A) -> std::result::Result<R, String> + 'static,
+    {
+        if self.handlers.contains_key(method) {
+            return Err(Error::msg(format!(
+                "method {} is already registered",
+                method
+            )));
+        }
+        let func = self.env.get_method(&self.actor, method)?;
+        subtype(
+            &mut Gamma::new(),
+            &self.env,
+            &tuple(&func.args),
+            &tuple(&A::types()),
+        )
+        .map_err(|e| Error::msg(format!("method {} arguments: {}", method, e)))?;
+        subtype(
+            &mut Gamma::new(),
+            &self.env,
+            &tuple(&R::types()),
+            &tuple(&func.rets),
+        )
+        .map_err(|e| Error::msg(format!("method {} results: {}", method, e)))?;
+        let handler = move |bytes: &[u8]| {
+            let args =
+                decode::<A>(bytes).map_err(|e| Reject::new(RejectCode::InvalidArguments, e))?;
+            let res = handler(args).map_err(|e| Reject::new(RejectCode::HandlerReject, e))?;
+            encode(&res).map_err(|e| Reject::new(RejectCode::InvalidReply, e))
+        };
+        self.handlers.insert(method.to_string(), Box::new(handler));
+        Ok(self)
+    }
+    /// Calls the handler of `method` with the encoded arguments `args`, and returns the encoded reply.
+    pub fn dispatch(&mut self, method: &str, args: &[u8]) -> std::result::Result<Vec<u8>, Reject> {
+        match self.handlers.get_mut(method) {
+            Some(handler) => handler(args),
+            None => Err(Reject::new(
+                RejectCode::MethodNotFound,
+                format!("method {} not found", method),
+            )),
+        }
+    }
+    /// Methods of the service without a handler.
+    pub fn missing(&self) -> Result<Vec<String>> {
+        let methods = self.env.as_service(&self.actor)?;
+        Ok(methods
+            .iter()
+            .filter(|(id, _)| !self.handlers.contains_key(id))
+            .map(|(id, _)| id.clone())
+            .collect())
+    }
+}
+
+fn decode<A: ArgumentDecoder>(bytes: &[u8]) -> Result<A> {
+    let mut de = IDLDeserialize::new(bytes)?;
+    let args = A::decode(&mut de)?;
+    de.done()?;
+    Ok(args)
+}
+
+fn encode<R: ArgumentEncoder>(res: &R) -> Result<Vec<u8>> {
+    let mut ser = IDLBuilder::new();
+    res.encode(&mut ser)?;
+    ser.serialize_to_vec()
+}
@@ -0,0 +1,272 @@
+//! Persist a value across changes of its type, such as the state of a canister across upgrades.
+//!
+//! The stored bytes start with a header, followed by a Candid message with the type table and
+//! the value:
+//!
+//! * the magic number `CSTB`;
+//! * the version of this format, as a little-endian `u32`;
+//! * the schema version of the value, as a little-endian `u32`;
+//! * the length of the Candid message, as a little-endian `u64`.
+//!
+//! A stored value is read into the current type through the subtyping rules, and the
+//! `Report` lists the fields that were dropped or defaulted on the way. Changes that are not
+//! subtype-compatible need a migration from the stored schema version.
+//!
+//! ```
+//! use candid::{stable::Schema, CandidType, Deserialize};
+//! #[derive(CandidType, Deserialize)]
+//! struct StateV1 { owner: String, count: u32 }
+//! #[derive(CandidType, Deserialize)]
+//! struct StateV2 { owners: Vec<String>, count: u32, memo: Option<String> }
+//!
+//! let bytes = Schema::<StateV1>::new(1).to_bytes(&StateV1 { owner: "a".to_string(), count: 1 })?;
+//!
+//! let schema = Schema::<StateV2>::new(2).migration(1, |old: StateV1| StateV2 {
+//!     owners: vec![old.owner],
+//!     count: old.count,
+//!     memo: None,
+//! });
+//! let (state, report) = schema.from_bytes(&bytes)?;
+//! assert_eq!(state.owners, ["a"]);
+//! assert!(report.migrated);
+//! # Ok::<(), candid::Error>(())
+//! ```
+
+use crate::de::IDLDeserialize;
+use crate::parser::typing::TypeEnv;
+use crate::parser::value::{IDLPath, PathStep};
+use crate::ser::IDLBuilder;
+use crate::types::subtype::{is_nullable, subtype, unroll, Gamma};
+use crate::types::{CandidType, Field, Type};
+use crate::{Error, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Read, Write};
+
+const MAGIC_NUMBER: &[u8; 4] = b"CSTB";
+const FORMAT_VERSION: u32 = 1;
+// The size of a stable memory page
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// How a stored value was read into the current type.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Report {
+    /// Schema version of the stored value.
+    pub version: u32,
+    /// Whether the value was converted by a migration.
+    pub migrated: bool,
+    /// Stored fields that are not in the type that was read, so they were dropped. They are
+    /// reported by their hash, as the names of fields are not stored.
+    pub dropped: Vec<IDLPath>,
+    /// Fields of the type that was read that are not stored, so they read as `null`.
+    pub defaulted: Vec<IDLPath>,
+}
+
+type Migration<T> = Box<dyn Fn(&[u8], &mut Report) -> Result<T>>;
+
+/// Reads and writes values of `T`, the current type, at schema `version`.
+pub struct Schema<T> {
+    version: u32,
+    chunk_size: usize,
+    migrations: BTreeMap<u32, Migration<T>>,
+}
+
+impl<T: CandidType + DeserializeOwned> Schema<T> {
+    pub fn new(version: u32) -> Self {
+        Schema {
+            version,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            migrations: BTreeMap::new(),
+        }
+    }
+    /// Reads and writes the Candid message in chunks of `size` bytes.
+    pub fn with_chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size.max(1);
+        self
+    }
+    /// Converts values stored at schema `version` with `migrate`. The stored value is read
+    /// into `Old` through subtyping, so `Old` only needs to be compatible with the stored type.
+    pub fn migration<Old, F>(mut self, version: u32, migrate: F) -> Self
+    where
+        Old: CandidType + DeserializeOwned,
+        F: Fn(Old) -> T + 'static,
+    {
+        let migration = move |message: &[u8], report: &mut Report| {
+            let old = read_message::<Old>(message, report)?;
+            Ok(migrate(old))
+        };
+        self.migrations.insert(version, Box::new(migration));
+        self
+    }
+    pub fn to_bytes(&self, value: &T) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes, value)?;
+        Ok(bytes)
+    }
+    pub fn from_bytes(&self, mut bytes: &[u8]) -> Result<(T, Report)> {
+        let res = self.read(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(Error::msg("trailing bytes after the stored value"));
+        }
+        Ok(res)
+    }
+    pub fn write<W: Write>(&self, mut w: W, value: &T) -> Result<()> {
+        let message = IDLBuilder::new().arg(value)?.serialize_to_vec()?;
+        w.write_all(MAGIC_NUMBER)?;
+        w.write_u32::<LittleEndian>(FORMAT_VERSION)?;
+        w.write_u32::<LittleEndian>(self.version)?;
+        w.write_u64::<LittleEndian>(message.len() as u64)?;
+        for chunk in message.chunks(self.chunk_size) {
+            w.write_all(chunk)?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+    pub fn read<R: Read>(&self, mut r: R) -> Result<(T, Report)> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if magic != *MAGIC_NUMBER {
+            return Err(Error::msg(format!("wrong magic number {:?}", magic)));
+        }
+        let format = r.read_u32::<LittleEndian>()?;
+        if format != FORMAT_VERSION {
+            return Err(Error::msg(format!("unknown format version {}", format)));
+        }
+        let version = r.read_u32::<LittleEndian>()?;
+        let len = r.read_u64::<LittleEndian>()? as usize;
+        // A corrupted length fails at the end of the input, instead of allocating it upfront
+        let mut message = Vec::new();
+        while message.len() < len {
+            let start = message.len();
+            message.resize(start + self.chunk_size.min(len - start), 0);
+            r.read_exact(&mut message[start..])?;
+        }
+        let mut report = Report {
+            version,
+            ..Default::default()
+        };
+        let value = match self.migrations.get(&version) {
+            Some(migrate) if version != self.version => {
+                report.migrated = true;
+                migrate(&message, &mut report)
+            }
+            _ => read_message::<T>(&message, &mut report),
+        }
+        .map_err(|e| {
+            Error::msg(format!(
+                "cannot read value of schema version {}: {}",
+                version, e
+            ))
+        })?;
+        Ok((value, report))
+    }
+}
+
+fn read_message<T: CandidType + DeserializeOwned>(
+    message: &[u8],
+    report: &mut Report,
+) -> Result<T> {
+    let mut de = IDLDeserialize::new(message)?;
+    let (env, types) = de.types();
+    if types.len() != 1 {
+        return Err(Error::msg(format!(
+            "expects one value, found {}",
+            types.len()
+        )));
+    }
+    let expected = T::ty();
+    subtype(&mut Gamma::new(), &env, &types[0], &expected)?;
+    let mut path = Vec::new();
+    diff(
+        &env,
+        &types[0],
+        &expected,
+        &mut path,
+        &mut Gamma::new(),
+        report,
+    )?;
+    let value = de.get_value::<T>()?;
+    de.done()?;
+    Ok(value)
+}
+
+// Collects the record fields that differ between the stored type `t1` and its supertype `t2`.
+fn diff(
+    env: &TypeEnv,
+    t1: &Type,
+    t2: &Type,
+    path: &mut Vec<PathStep>,
+    seen: &mut Gamma,
+    report: &mut Report,
+) -> Result<()> {
+    use Type::*;
+    if let (Var(_), _) | (_, Var(_)) | (Knot(_), _) | (_, Knot(_)) = (t1, t2) {
+        // Recursive types are compared once
+        if !seen.insert((t1.clone(), t2.clone())) {
+            return Ok(());
+        }
+        return diff(
+            env,
+            &unroll(env, t1)?,
+            &unroll(env, t2)?,
+            path,
+            seen,
+            report,
+        );
+    }
+    match (t1, t2) {
+        (Opt(t1), Opt(t2)) => nested(env, PathStep::Opt, t1, t2, path, seen, report),
+        (t1, Opt(t2)) if !is_nullable(env, t1) => {
+            nested(env, PathStep::Opt, t1, t2, path, seen, report)
+        }
+        (Vec(t1), Vec(t2)) => nested(env, PathStep::All, t1, t2, path, seen, report),
+        (Record(fs1), Record(fs2)) => {
+            let ids2: HashSet<_> = fs2.iter().map(|f| f.id.get_id()).collect();
+            for f in fs1.iter().filter(|f| !ids2.contains(&f.id.get_id())) {
+                report.dropped.push(field_path(path, f));
+            }
+            for f in fs2.iter() {
+                match fs1.iter().find(|f1| f1.id == f.id) {
+                    Some(f1) => {
+                        let step = PathStep::Field(f.id.clone());
+                        nested(env, step, &f1.ty, &f.ty, path, seen, report)?;
+                    }
+                    None => report.defaulted.push(field_path(path, f)),
+                }
+            }
+            Ok(())
+        }
+        (Variant(fs1), Variant(fs2)) => {
+            for f1 in fs1.iter() {
+                if let Some(f) = fs2.iter().find(|f| f.id == f1.id) {
+                    let step = PathStep::Case(f.id.clone());
+                    nested(env, step, &f1.ty, &f.ty, path, seen, report)?;
+                }
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn nested(
+    env: &TypeEnv,
+    step: PathStep,
+    t1: &Type,
+    t2: &Type,
+    path: &mut Vec<PathStep>,
+    seen: &mut Gamma,
+    report: &mut Report,
+) -> Result<()> {
+    path.push(step);
+    let res = diff(env, t1, t2, path, seen, report);
+    path.pop();
+    res
+}
+
+fn field_path(path: &[PathStep], f: &Field) -> IDLPath {
+    let mut path = path.to_vec();
+    path.push(PathStep::Field(f.id.clone()));
+    IDLPath(path)
+}
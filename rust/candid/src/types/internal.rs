@@ -3,18 +3,25 @@ use crate::idl_hash;
 use num_enum::TryFromPrimitive;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
 
-// This is a re-implementation of std::any::TypeId to get rid of 'static constraint.
-// The current TypeId doesn't consider lifetime while computing the hash, which is
-// totally fine for IDL type, as we don't care about lifetime at all.
+// The identity of a Rust type, given by its name. Unlike std::any::TypeId, it does not
+// require 'static, and lifetimes are erased from the name, which is fine for IDL types.
+// Different instantiations of a generic type have different names, and the same type
+// has the same name in all codegen units, which does not hold for function addresses.
+// The generation is bumped by `env_clear`, so that the knots of types derived before
+// are told apart from the types derived again.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct TypeId {
-    id: usize,
+    name: &'static str,
+    generation: u64,
 }
 impl TypeId {
     pub fn of<T: ?Sized>() -> Self {
         TypeId {
-            id: TypeId::of::<T> as usize,
+            name: std::any::type_name::<T>(),
+            generation: GENERATION.load(Ordering::SeqCst),
         }
     }
 }
@@ -89,8 +96,20 @@ impl PartialEq for Label {
 }
 
 impl std::hash::Hash for Label {
-    fn hash<H: std::hash::Hasher>(&self, _state: &mut H) {
-        self.get_id();
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.get_id().hash(state);
+    }
+}
+
+impl PartialOrd for Label {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Label {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.get_id().cmp(&other.get_id())
     }
 }
 
@@ -113,7 +132,7 @@ impl Function {
     }
 }
 
-#[derive(Debug, PartialEq, TryFromPrimitive)]
+#[derive(Debug, PartialEq, Clone, Copy, TryFromPrimitive)]
 #[repr(i64)]
 pub(crate) enum Opcode {
     Null = -1,
@@ -157,51 +176,104 @@ pub fn is_primitive(t: &Type) -> bool {
     }
 }
 
+/// Same as `try_unroll`, but panics on the `Knot` of a type that is not derived.
 pub fn unroll(t: &Type) -> Type {
+    try_unroll(t).unwrap()
+}
+
+/// Unrolls the `Knot`s in `t`, including the ones from before `env_clear`.
+pub fn try_unroll(t: &Type) -> crate::Result<Type> {
     use self::Type::*;
-    match t {
-        Knot(id) => find_type(*id).unwrap(),
-        Opt(ref t) => Opt(Box::new(unroll(t))),
-        Vec(ref t) => Vec(Box::new(unroll(t))),
-        Record(fs) => Record(
-            fs.iter()
-                .map(|Field { id, ty }| Field {
-                    id: id.clone(),
-                    ty: unroll(ty),
-                })
-                .collect(),
-        ),
-        Variant(fs) => Variant(
-            fs.iter()
-                .map(|Field { id, ty }| Field {
+    let fields = |fs: &[Field]| {
+        fs.iter()
+            .map(|Field { id, ty }| {
+                Ok(Field {
                     id: id.clone(),
-                    ty: unroll(ty),
+                    ty: try_unroll(ty)?,
                 })
-                .collect(),
-        ),
+            })
+            .collect::<crate::Result<_>>()
+    };
+    Ok(match t {
+        Knot(id) => {
+            find_type(*id).ok_or_else(|| crate::Error::msg(format!("unknown type {}", id.name)))?
+        }
+        Opt(ref t) => Opt(Box::new(try_unroll(t)?)),
+        Vec(ref t) => Vec(Box::new(try_unroll(t)?)),
+        Record(fs) => Record(fields(fs)?),
+        Variant(fs) => Variant(fields(fs)?),
         _ => (*t).clone(),
-    }
+    })
+}
+
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+type DeriveFn = fn() -> Type;
+
+lazy_static::lazy_static! {
+    // Derived types, shared by all threads.
+    static ref ENV: RwLock<HashMap<TypeId, Type>> = RwLock::new(HashMap::new());
+    // `CandidType::ty` of the derived types, by name, to derive them again after `env_clear`.
+    static ref DERIVE: RwLock<HashMap<&'static str, DeriveFn>> = RwLock::new(HashMap::new());
 }
 
 thread_local! {
-    static ENV: RefCell<HashMap<TypeId, Type>> = RefCell::new(HashMap::new());
+    // Types being derived by this thread, `Unknown` until their derivation finishes. They
+    // move to `ENV` when the outermost derivation finishes, so that the knots of the
+    // types in `ENV` are always in `ENV`.
+    static PENDING: RefCell<HashMap<TypeId, Type>> = RefCell::new(HashMap::new());
 }
 
 pub(crate) fn find_type(id: TypeId) -> Option<Type> {
-    ENV.with(|e| match e.borrow().get(&id) {
-        None => None,
-        Some(t) => Some((*t).clone()),
-    })
+    if let Some(t) = ENV.read().unwrap().get(&id) {
+        return Some(t.clone());
+    }
+    if let Some(t) = PENDING.with(|p| p.borrow().get(&id).cloned()) {
+        return Some(t);
+    }
+    // A knot from before `env_clear`, which resolves to the type derived again
+    if id.generation != GENERATION.load(Ordering::SeqCst) {
+        let ty = *DERIVE.read().unwrap().get(id.name)?;
+        return match ty() {
+            Type::Knot(id) => find_type(id),
+            t => Some(t),
+        };
+    }
+    None
 }
 
 // only for debugging
 #[allow(dead_code)]
 pub(crate) fn show_env() {
-    ENV.with(|e| println!("{:?}", e.borrow()));
+    println!("{:?}", ENV.read().unwrap());
+    PENDING.with(|p| println!("{:?}", p.borrow()));
+}
+
+pub(crate) fn derive_type(id: TypeId, ty: DeriveFn, derive: impl FnOnce() -> Type) -> Type {
+    DERIVE.write().unwrap().entry(id.name).or_insert(ty);
+    let outermost = PENDING.with(|p| {
+        let mut p = p.borrow_mut();
+        let outermost = p.is_empty();
+        p.insert(id, Type::Unknown);
+        outermost
+    });
+    let t = derive();
+    PENDING.with(|p| {
+        let mut p = p.borrow_mut();
+        p.insert(id, t.clone());
+        if outermost {
+            ENV.write().unwrap().extend(p.drain());
+        }
+    });
+    t
 }
 
-pub(crate) fn env_add(id: TypeId, t: Type) {
-    ENV.with(|e| drop(e.borrow_mut().insert(id, t)))
+/// Clears the types derived so far, in all threads, and starts a new generation of
+/// `TypeId`s. The `Knot`s in types derived before still resolve, to the types derived again.
+pub fn env_clear() {
+    let mut env = ENV.write().unwrap();
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    env.clear();
 }
 
 pub fn get_type<T>(_v: &T) -> Type
@@ -9,26 +9,21 @@ use serde::ser::Error;
 mod impls;
 pub mod internal;
 
-pub use self::internal::{get_type, Field, Function, Label, Type, TypeId};
+pub use self::internal::{env_clear, get_type, Field, Function, Label, Type, TypeId};
 
 pub mod number;
 pub mod principal;
 pub mod reserved;
+pub mod subtype;
 
 pub trait CandidType {
     // memoized type derivation
     fn ty() -> Type {
         let id = Self::id();
-        if let Some(t) = self::internal::find_type(id) {
-            match t {
-                Type::Unknown => Type::Knot(id),
-                _ => t,
-            }
-        } else {
-            self::internal::env_add(id, Type::Unknown);
-            let t = Self::_ty();
-            self::internal::env_add(id, t.clone());
-            t
+        match self::internal::find_type(id) {
+            Some(Type::Unknown) => Type::Knot(id),
+            Some(t) => t,
+            None => self::internal::derive_type(id, Self::ty, Self::_ty),
         }
     }
     fn id() -> TypeId;
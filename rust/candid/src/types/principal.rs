@@ -4,7 +4,7 @@ use serde::de::{Deserialize, Visitor};
 use std::fmt;
 use std::fmt::Write as FmtWrite;
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone)]
 pub struct Principal(pub Vec<u8>);
 
 // TODO this whole implementation should be replaced with the real ic-types crate
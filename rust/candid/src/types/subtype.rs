@@ -0,0 +1,126 @@
+//! Subtyping of Candid types, as defined in the upgrade rules of the spec.
+
+use super::internal::{find_type, Field, Label, Type};
+use crate::parser::typing::TypeEnv;
+use crate::{Error, Result};
+use std::collections::{BTreeMap, HashSet};
+
+/// Pairs of types assumed to be in the subtype relation, to check recursive types.
+pub type Gamma = HashSet<(Type, Type)>;
+
+/// Check if `t1 <: t2`, that is, if a value of `t1` can be used where `t2` is expected.
+/// Both types are bound in `env`.
+///
+/// Besides the rules of the spec, a record field of type `null`, `opt` or `reserved`
+/// can be missing from the subtype, as the decoder reads it as `null`.
+pub fn subtype(gamma: &mut Gamma, env: &TypeEnv, t1: &Type, t2: &Type) -> Result<()> {
+    use Type::*;
+    if t1 == t2 {
+        return Ok(());
+    }
+    if let (Var(_), _) | (_, Var(_)) | (Knot(_), _) | (_, Knot(_)) = (t1, t2) {
+        let pair = (t1.clone(), t2.clone());
+        if !gamma.insert(pair.clone()) {
+            return Ok(());
+        }
+        let res = match (t1, t2) {
+            (Var(_), _) | (Knot(_), _) => subtype(gamma, env, &unroll(env, t1)?, t2),
+            _ => subtype(gamma, env, t1, &unroll(env, t2)?),
+        };
+        if res.is_err() {
+            gamma.remove(&pair);
+        }
+        return res;
+    }
+    match (t1, t2) {
+        (_, Reserved) | (Empty, _) | (Nat, Int) | (Null, Opt(_)) => Ok(()),
+        (Opt(t1), Opt(t2)) | (Vec(t1), Vec(t2)) => subtype(gamma, env, t1, t2),
+        (t1, Opt(t2)) if !is_nullable(env, t1) => subtype(gamma, env, t1, t2),
+        (Record(fs1), Record(fs2)) => {
+            let fs1: BTreeMap<_, _> = fs1.iter().map(|f| (f.id.get_id(), &f.ty)).collect();
+            for Field { id, ty } in fs2.iter() {
+                match fs1.get(&id.get_id()) {
+                    Some(ty1) => subtype(gamma, env, ty1, ty)
+                        .map_err(|e| Error::msg(format!("record field {}: {}", id, e)))?,
+                    None if is_nullable(env, ty) => (),
+                    None => {
+                        return Err(Error::msg(format!("record field {} is missing", id)));
+                    }
+                }
+            }
+            Ok(())
+        }
+        (Variant(fs1), Variant(fs2)) => {
+            let fs2: BTreeMap<_, _> = fs2.iter().map(|f| (f.id.get_id(), &f.ty)).collect();
+            for Field { id, ty } in fs1.iter() {
+                match fs2.get(&id.get_id()) {
+                    Some(ty2) => subtype(gamma, env, ty, ty2)
+                        .map_err(|e| Error::msg(format!("variant case {}: {}", id, e)))?,
+                    None => {
+                        return Err(Error::msg(format!("variant case {} is unexpected", id)));
+                    }
+                }
+            }
+            Ok(())
+        }
+        (Service(ms1), Service(ms2)) => {
+            let ms1: BTreeMap<_, _> = ms1.iter().map(|(id, ty)| (id, ty)).collect();
+            for (id, ty) in ms2.iter() {
+                match ms1.get(id) {
+                    Some(ty1) => subtype(gamma, env, ty1, ty)
+                        .map_err(|e| Error::msg(format!("method {}: {}", id, e)))?,
+                    None => return Err(Error::msg(format!("method {} is missing", id))),
+                }
+            }
+            Ok(())
+        }
+        (Func(f1), Func(f2)) => {
+            let modes1: HashSet<_> = f1.modes.iter().collect();
+            let modes2: HashSet<_> = f2.modes.iter().collect();
+            if modes1 != modes2 {
+                return Err(Error::msg("function modes differ"));
+            }
+            // Arguments and results are compared as tuples
+            subtype(gamma, env, &tuple(&f2.args), &tuple(&f1.args))
+                .map_err(|e| Error::msg(format!("function arguments: {}", e)))?;
+            subtype(gamma, env, &tuple(&f1.rets), &tuple(&f2.rets))
+                .map_err(|e| Error::msg(format!("function results: {}", e)))
+        }
+        _ => Err(Error::msg(format!("{} is not a subtype of {}", t1, t2))),
+    }
+}
+
+/// Check if `null <: t`, which holds for `null`, `opt` and `reserved` types.
+pub fn is_nullable(env: &TypeEnv, t: &Type) -> bool {
+    match t {
+        Type::Null | Type::Opt(_) | Type::Reserved => true,
+        Type::Var(_) | Type::Knot(_) => unroll(env, t)
+            .map(|t| is_nullable(env, &t))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Definition of a type variable from a did file, or of a recursive Rust type.
+pub(crate) fn unroll(env: &TypeEnv, t: &Type) -> Result<Type> {
+    match t {
+        Type::Var(id) => env.rec_find_type(id).cloned(),
+        Type::Knot(id) => {
+            find_type(*id).ok_or_else(|| Error::msg(format!("unknown type {:?}", id)))
+        }
+        _ => Ok(t.clone()),
+    }
+}
+
+/// Argument and result sequences are compared as tuples.
+pub(crate) fn tuple(args: &[Type]) -> Type {
+    let fs = args
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| Field {
+            id: Label::Id(i as u32),
+            ty: ty.clone(),
+        })
+        .collect();
+    Type::Record(fs)
+}
@@ -0,0 +1,24 @@
+// Code generated by didc. DO NOT EDIT.
+
+package service
+
+import "math/big"
+
+type F struct{ Principal []byte; Method string }
+
+type G F
+
+type H struct{ Principal []byte; Method string }
+
+type O *O
+
+type Service interface {
+	// F calls the method "f".
+	F(arg0 *big.Int) (H, error)
+	// G calls the method "g".
+	G(arg0 int8) (int8, error)
+	// H calls the method "h".
+	H(arg0 int8) (int8, error)
+	// O calls the method "o".
+	O(arg0 O) (O, error)
+}
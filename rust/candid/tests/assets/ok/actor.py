@@ -0,0 +1,69 @@
+# This is a generated Python binding. It requires Python 3.9 or later.
+import dataclasses
+import typing
+
+
+@dataclasses.dataclass(frozen=True)
+class Range:
+    """Inclusive bounds of an integer type, `None` for unbounded."""
+
+    min: typing.Optional[int]
+    max: typing.Optional[int]
+
+
+nat = typing.Annotated[int, Range(0, None)]
+nat8 = typing.Annotated[int, Range(0, 2**8 - 1)]
+nat16 = typing.Annotated[int, Range(0, 2**16 - 1)]
+nat32 = typing.Annotated[int, Range(0, 2**32 - 1)]
+nat64 = typing.Annotated[int, Range(0, 2**64 - 1)]
+int8 = typing.Annotated[int, Range(-(2**7), 2**7 - 1)]
+int16 = typing.Annotated[int, Range(-(2**15), 2**15 - 1)]
+int32 = typing.Annotated[int, Range(-(2**31), 2**31 - 1)]
+int64 = typing.Annotated[int, Range(-(2**63), 2**63 - 1)]
+# Textual representation of a principal
+principal = str
+
+
+f = typing.Tuple[principal, str]
+
+
+h = typing.Tuple[principal, str]
+
+
+g = f
+
+
+o = typing.Optional["o"]
+
+
+class Agent(typing.Protocol):
+    """Sends a call to a canister. `mode` is "query", "update" or "oneway"."""
+
+    def call(
+        self,
+        canister_id: principal,
+        method: str,
+        args: typing.List[typing.Any],
+        mode: str,
+    ) -> typing.List[typing.Any]:
+        ...
+
+
+class ServiceClient:
+    """Client of the service, which sends the calls through `agent`."""
+
+    def __init__(self, agent: Agent, canister_id: principal) -> None:
+        self._agent = agent
+        self._canister_id = canister_id
+
+    def f(self, arg0: nat) -> h:
+        return self._agent.call(self._canister_id, "f", [arg0], "update")[0]
+
+    def g(self, arg0: int8) -> int8:
+        return self._agent.call(self._canister_id, "g", [arg0], "update")[0]
+
+    def h(self, arg0: int8) -> int8:
+        return self._agent.call(self._canister_id, "h", [arg0], "update")[0]
+
+    def o(self, arg0: "o") -> "o":
+        return self._agent.call(self._canister_id, "o", [arg0], "update")[0]
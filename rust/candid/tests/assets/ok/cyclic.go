@@ -0,0 +1,20 @@
+// Code generated by didc. DO NOT EDIT.
+
+package service
+
+type A *B
+
+type B *C
+
+type C A
+
+type X Y
+
+type Y Z
+
+type Z A
+
+type Service interface {
+	// F calls the method "f".
+	F(arg0 A, arg1 B, arg2 C, arg3 X, arg4 Y, arg5 Z) error
+}
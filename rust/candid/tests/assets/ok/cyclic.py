@@ -0,0 +1,66 @@
+# This is a generated Python binding. It requires Python 3.9 or later.
+import dataclasses
+import typing
+
+
+@dataclasses.dataclass(frozen=True)
+class Range:
+    """Inclusive bounds of an integer type, `None` for unbounded."""
+
+    min: typing.Optional[int]
+    max: typing.Optional[int]
+
+
+nat = typing.Annotated[int, Range(0, None)]
+nat8 = typing.Annotated[int, Range(0, 2**8 - 1)]
+nat16 = typing.Annotated[int, Range(0, 2**16 - 1)]
+nat32 = typing.Annotated[int, Range(0, 2**32 - 1)]
+nat64 = typing.Annotated[int, Range(0, 2**64 - 1)]
+int8 = typing.Annotated[int, Range(-(2**7), 2**7 - 1)]
+int16 = typing.Annotated[int, Range(-(2**15), 2**15 - 1)]
+int32 = typing.Annotated[int, Range(-(2**31), 2**31 - 1)]
+int64 = typing.Annotated[int, Range(-(2**63), 2**63 - 1)]
+# Textual representation of a principal
+principal = str
+
+
+C = "A"
+
+
+B = typing.Optional[C]
+
+
+A = typing.Optional[B]
+
+
+Z = "A"
+
+
+Y = Z
+
+
+X = Y
+
+
+class Agent(typing.Protocol):
+    """Sends a call to a canister. `mode` is "query", "update" or "oneway"."""
+
+    def call(
+        self,
+        canister_id: principal,
+        method: str,
+        args: typing.List[typing.Any],
+        mode: str,
+    ) -> typing.List[typing.Any]:
+        ...
+
+
+class ServiceClient:
+    """Client of the service, which sends the calls through `agent`."""
+
+    def __init__(self, agent: Agent, canister_id: principal) -> None:
+        self._agent = agent
+        self._canister_id = canister_id
+
+    def f(self, arg0: "A", arg1: B, arg2: C, arg3: X, arg4: Y, arg5: Z) -> None:
+        self._agent.call(self._canister_id, "f", [arg0, arg1, arg2, arg3, arg4, arg5], "update")
@@ -0,0 +1,35 @@
+// Code generated by didc. DO NOT EDIT.
+
+package service
+
+import "math/big"
+
+// Color is a variant, exactly one of its fields is set.
+type Color struct {
+	Red   *struct{} `candid:"red"`
+	Green *struct{} `candid:"green"`
+}
+
+type List *struct{ Head *big.Int `candid:"head"`; Tail List `candid:"tail"` }
+
+type Pair struct {
+	Field0 *big.Int `candid:"0"`
+	Field1 string   `candid:"1"`
+}
+
+type Shape struct {
+	OnChange struct{ Principal []byte; Method string }                                    `candid:"on_change"`
+	Kind     *struct{ Free *struct{} `candid:"free"`; Regular *uint8 `candid:"regular"` } `candid:"kind"`
+	Points   []struct{ X float64 `candid:"x"`; Y float64 `candid:"y"` }                   `candid:"points"`
+}
+
+type Store []byte
+
+type Service interface {
+	// Get calls the query method "get".
+	Get(arg0 *big.Int) (List, error)
+	// Set calls the method "set".
+	Set(arg0 List, arg1 Color) error
+	// Draw calls the method "draw".
+	Draw(arg0 struct{ Top bool `candid:"top"`; Shape Shape `candid:"shape"` }) error
+}
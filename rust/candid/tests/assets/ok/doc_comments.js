@@ -0,0 +1,70 @@
+export default ({ IDL }) => {
+  const List = IDL.Rec();
+  const Shape = IDL.Rec();
+  /**
+   * A linked list
+   * of integers.
+   */
+  List.fill(
+    IDL.Opt(
+      IDL.Record({
+        /** The first element. */
+        'head' : IDL.Int,
+        'tail' : List,
+      })
+    )
+  );
+  const Color = IDL.Variant({
+    /** Also known as rouge. *\/ */
+    'red' : IDL.Null,
+    'green' : IDL.Null,
+  });
+  Shape.fill(
+    IDL.Record({
+      'on_change' : IDL.Func(
+          [
+            IDL.Record({
+              /** The new shape. */
+              'shape' : Shape,
+            }),
+          ],
+          [],
+          ['oneway'],
+        ),
+      'kind' : IDL.Opt(
+        IDL.Variant({
+          'free' : IDL.Null,
+          /** A regular polygon. */
+          'regular' : IDL.Nat8,
+        })
+      ),
+      'points' : IDL.Vec(
+        IDL.Record({
+          /** Horizontal, from the left. */
+          'x' : IDL.Float64,
+          'y' : IDL.Float64,
+        })
+      ),
+    })
+  );
+  return IDL.Service({
+    /**
+     * Returns the list.
+     *
+     * Query only.
+     */
+    'get' : IDL.Func([IDL.Nat], [List], ['query']),
+    'set' : IDL.Func([List, Color], [], []),
+    'draw' : IDL.Func(
+        [
+          IDL.Record({
+            /** Drawn on top of everything else. */
+            'top' : IDL.Bool,
+            'shape' : Shape,
+          }),
+        ],
+        [],
+        [],
+      ),
+  });
+};
@@ -0,0 +1,157 @@
+# This is a generated Python binding. It requires Python 3.9 or later.
+import dataclasses
+import typing
+
+
+@dataclasses.dataclass(frozen=True)
+class Range:
+    """Inclusive bounds of an integer type, `None` for unbounded."""
+
+    min: typing.Optional[int]
+    max: typing.Optional[int]
+
+
+nat = typing.Annotated[int, Range(0, None)]
+nat8 = typing.Annotated[int, Range(0, 2**8 - 1)]
+nat16 = typing.Annotated[int, Range(0, 2**16 - 1)]
+nat32 = typing.Annotated[int, Range(0, 2**32 - 1)]
+nat64 = typing.Annotated[int, Range(0, 2**64 - 1)]
+int8 = typing.Annotated[int, Range(-(2**7), 2**7 - 1)]
+int16 = typing.Annotated[int, Range(-(2**15), 2**15 - 1)]
+int32 = typing.Annotated[int, Range(-(2**31), 2**31 - 1)]
+int64 = typing.Annotated[int, Range(-(2**63), 2**63 - 1)]
+# Textual representation of a principal
+principal = str
+
+
+@dataclasses.dataclass
+class List_inner:
+    head: int
+    tail: "List"
+
+
+class List_innerDict(typing.TypedDict):
+    """`List_inner` as a plain dict."""
+
+    head: int
+    tail: "ListDict"
+
+
+List = typing.Optional[List_inner]
+
+
+ListDict = typing.Optional[List_innerDict]
+
+
+class Color:
+    """Base class of the `Color_*` cases."""
+
+
+@dataclasses.dataclass
+class Color_red(Color):
+    tag: typing.ClassVar[str] = "red"
+
+
+@dataclasses.dataclass
+class Color_green(Color):
+    tag: typing.ClassVar[str] = "green"
+
+
+class ColorDict(typing.TypedDict, total=False):
+    """`Color` as a plain dict, with the case as its only key."""
+
+    red: None
+    green: None
+
+
+class Shape_kind:
+    """Base class of the `Shape_kind_*` cases."""
+
+
+@dataclasses.dataclass
+class Shape_kind_free(Shape_kind):
+    tag: typing.ClassVar[str] = "free"
+
+
+@dataclasses.dataclass
+class Shape_kind_regular(Shape_kind):
+    value: nat8
+    tag: typing.ClassVar[str] = "regular"
+
+
+class Shape_kindDict(typing.TypedDict, total=False):
+    """`Shape_kind` as a plain dict, with the case as its only key."""
+
+    free: None
+    regular: nat8
+
+
+@dataclasses.dataclass
+class Shape_points:
+    x: float
+    y: float
+
+
+class Shape_pointsDict(typing.TypedDict):
+    """`Shape_points` as a plain dict."""
+
+    x: float
+    y: float
+
+
+@dataclasses.dataclass
+class Shape:
+    on_change: typing.Tuple[principal, str]
+    kind: typing.Optional[Shape_kind]
+    points: typing.List[Shape_points]
+
+
+class ShapeDict(typing.TypedDict):
+    """`Shape` as a plain dict."""
+
+    on_change: typing.Tuple[principal, str]
+    kind: typing.Optional[Shape_kindDict]
+    points: typing.List[Shape_pointsDict]
+
+
+@dataclasses.dataclass
+class draw_arg0:
+    top: bool
+    shape: "Shape"
+
+
+class draw_arg0Dict(typing.TypedDict):
+    """`draw_arg0` as a plain dict."""
+
+    top: bool
+    shape: "ShapeDict"
+
+
+class Agent(typing.Protocol):
+    """Sends a call to a canister. `mode` is "query", "update" or "oneway"."""
+
+    def call(
+        self,
+        canister_id: principal,
+        method: str,
+        args: typing.List[typing.Any],
+        mode: str,
+    ) -> typing.List[typing.Any]:
+        ...
+
+
+class ServiceClient:
+    """Client of the service, which sends the calls through `agent`."""
+
+    def __init__(self, agent: Agent, canister_id: principal) -> None:
+        self._agent = agent
+        self._canister_id = canister_id
+
+    def get(self, arg0: nat) -> "List":
+        return self._agent.call(self._canister_id, "get", [arg0], "query")[0]
+
+    def set(self, arg0: "List", arg1: Color) -> None:
+        self._agent.call(self._canister_id, "set", [arg0, arg1], "update")
+
+    def draw(self, arg0: draw_arg0) -> None:
+        self._agent.call(self._canister_id, "draw", [arg0], "update")
@@ -0,0 +1,17 @@
+// Code generated by didc. DO NOT EDIT.
+
+package service
+
+import "math/big"
+
+type T struct {
+	Hash34         *big.Int `candid:"\""`
+	Hash39         *big.Int `candid:"'"`
+	Hash7621       *big.Int `candid:"\"'"`
+	Hash1020746185 *big.Int `candid:"_1020746185_"`
+}
+
+type Service interface {
+	// Hash2635468193 calls the method "\u000a'\"''\"\"\u000d\u0009".
+	Hash2635468193(arg0 T) error
+}
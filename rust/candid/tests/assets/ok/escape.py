@@ -0,0 +1,65 @@
+# This is a generated Python binding. It requires Python 3.9 or later.
+import dataclasses
+import typing
+
+
+@dataclasses.dataclass(frozen=True)
+class Range:
+    """Inclusive bounds of an integer type, `None` for unbounded."""
+
+    min: typing.Optional[int]
+    max: typing.Optional[int]
+
+
+nat = typing.Annotated[int, Range(0, None)]
+nat8 = typing.Annotated[int, Range(0, 2**8 - 1)]
+nat16 = typing.Annotated[int, Range(0, 2**16 - 1)]
+nat32 = typing.Annotated[int, Range(0, 2**32 - 1)]
+nat64 = typing.Annotated[int, Range(0, 2**64 - 1)]
+int8 = typing.Annotated[int, Range(-(2**7), 2**7 - 1)]
+int16 = typing.Annotated[int, Range(-(2**15), 2**15 - 1)]
+int32 = typing.Annotated[int, Range(-(2**31), 2**31 - 1)]
+int64 = typing.Annotated[int, Range(-(2**63), 2**63 - 1)]
+# Textual representation of a principal
+principal = str
+
+
+@dataclasses.dataclass
+class t:
+    _34_: nat
+    _39_: nat
+    _7621_: nat
+    _1020746185_: nat
+
+
+class tDict(typing.TypedDict):
+    """`t` as a plain dict."""
+
+    _34_: nat
+    _39_: nat
+    _7621_: nat
+    _1020746185_: nat
+
+
+class Agent(typing.Protocol):
+    """Sends a call to a canister. `mode` is "query", "update" or "oneway"."""
+
+    def call(
+        self,
+        canister_id: principal,
+        method: str,
+        args: typing.List[typing.Any],
+        mode: str,
+    ) -> typing.List[typing.Any]:
+        ...
+
+
+class ServiceClient:
+    """Client of the service, which sends the calls through `agent`."""
+
+    def __init__(self, agent: Agent, canister_id: principal) -> None:
+        self._agent = agent
+        self._canister_id = canister_id
+
+    def _2635468193_(self, arg0: t) -> None:
+        self._agent.call(self._canister_id, "\n'\"''\"\"\r\t", [arg0], "update")
@@ -0,0 +1,34 @@
+// Code generated by didc. DO NOT EDIT.
+
+package service
+
+import "math/big"
+
+type List *struct{ Head *big.Int `candid:"head"`; Tail List `candid:"tail"` }
+
+type Broker []byte
+
+type F struct{ Principal []byte; Method string }
+
+type MyType []byte
+
+type Nested struct {
+	Field0  *big.Int                                                                                                                `candid:"0"`
+	Field1  *big.Int                                                                                                                `candid:"1"`
+	Field2  struct{ Field0 *big.Int `candid:"0"`; Field1 *big.Int `candid:"1"` }                                                    `candid:"2"`
+	Field3  struct{ Field0 *big.Int `candid:"0"`; Field42 *big.Int `candid:"42"`; Field43 uint8 `candid:"43"` }                     `candid:"3"`
+	Field40 *big.Int                                                                                                                `candid:"40"`
+	Field41 struct{ Field42 *struct{} `candid:"42"`; A *struct{} `candid:"A"`; B *struct{} `candid:"B"`; C *struct{} `candid:"C"` } `candid:"41"`
+	Field42 *big.Int                                                                                                                `candid:"42"`
+}
+
+type Service interface {
+	// F calls the oneway method "f".
+	F(arg0 []byte, arg1 *bool) error
+	// G calls the query method "g".
+	G(arg0 MyType, arg1 List, arg2 *List, arg3 Nested) (*big.Int, Broker, error)
+	// H calls the method "h".
+	H(arg0 []*string, arg1 struct{ A *big.Int `candid:"A"`; B **string `candid:"B"` }, arg2 *List) (struct{ Field42 struct{} `candid:"42"`; Id *big.Int `candid:"id"` }, error)
+	// I calls the method "i".
+	I(arg0 List, arg1 struct{ Principal []byte; Method string }) (*List, error)
+}
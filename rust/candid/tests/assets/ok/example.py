@@ -0,0 +1,192 @@
+# This is a generated Python binding. It requires Python 3.9 or later.
+import dataclasses
+import typing
+
+
+@dataclasses.dataclass(frozen=True)
+class Range:
+    """Inclusive bounds of an integer type, `None` for unbounded."""
+
+    min: typing.Optional[int]
+    max: typing.Optional[int]
+
+
+nat = typing.Annotated[int, Range(0, None)]
+nat8 = typing.Annotated[int, Range(0, 2**8 - 1)]
+nat16 = typing.Annotated[int, Range(0, 2**16 - 1)]
+nat32 = typing.Annotated[int, Range(0, 2**32 - 1)]
+nat64 = typing.Annotated[int, Range(0, 2**64 - 1)]
+int8 = typing.Annotated[int, Range(-(2**7), 2**7 - 1)]
+int16 = typing.Annotated[int, Range(-(2**15), 2**15 - 1)]
+int32 = typing.Annotated[int, Range(-(2**31), 2**31 - 1)]
+int64 = typing.Annotated[int, Range(-(2**63), 2**63 - 1)]
+# Textual representation of a principal
+principal = str
+
+
+my_type = principal
+
+
+@dataclasses.dataclass
+class List_inner:
+    head: int
+    tail: "List"
+
+
+class List_innerDict(typing.TypedDict):
+    """`List_inner` as a plain dict."""
+
+    head: int
+    tail: "ListDict"
+
+
+List = typing.Optional[List_inner]
+
+
+ListDict = typing.Optional[List_innerDict]
+
+
+@dataclasses.dataclass
+class nested__3_:
+    _0_: nat
+    _42_: nat
+    _43_: nat8
+
+
+class nested__3_Dict(typing.TypedDict):
+    """`nested__3_` as a plain dict."""
+
+    _0_: nat
+    _42_: nat
+    _43_: nat8
+
+
+class nested__41_:
+    """Base class of the `nested__41__*` cases."""
+
+
+@dataclasses.dataclass
+class nested__41___42_(nested__41_):
+    tag: typing.ClassVar[int] = 42
+
+
+@dataclasses.dataclass
+class nested__41__A(nested__41_):
+    tag: typing.ClassVar[str] = "A"
+
+
+@dataclasses.dataclass
+class nested__41__B(nested__41_):
+    tag: typing.ClassVar[str] = "B"
+
+
+@dataclasses.dataclass
+class nested__41__C(nested__41_):
+    tag: typing.ClassVar[str] = "C"
+
+
+class nested__41_Dict(typing.TypedDict, total=False):
+    """`nested__41_` as a plain dict, with the case as its only key."""
+
+    _42_: None
+    A: None
+    B: None
+    C: None
+
+
+@dataclasses.dataclass
+class nested:
+    _0_: nat
+    _1_: nat
+    _2_: typing.Tuple[nat, int]
+    _3_: nested__3_
+    _40_: nat
+    _41_: nested__41_
+    _42_: nat
+
+
+class nestedDict(typing.TypedDict):
+    """`nested` as a plain dict."""
+
+    _0_: nat
+    _1_: nat
+    _2_: typing.Tuple[nat, int]
+    _3_: nested__3_Dict
+    _40_: nat
+    _41_: nested__41_Dict
+    _42_: nat
+
+
+broker = principal
+
+
+f = typing.Tuple[principal, str]
+
+
+class h_arg1:
+    """Base class of the `h_arg1_*` cases."""
+
+
+@dataclasses.dataclass
+class h_arg1_A(h_arg1):
+    value: nat
+    tag: typing.ClassVar[str] = "A"
+
+
+@dataclasses.dataclass
+class h_arg1_B(h_arg1):
+    value: typing.Optional[str]
+    tag: typing.ClassVar[str] = "B"
+
+
+class h_arg1Dict(typing.TypedDict, total=False):
+    """`h_arg1` as a plain dict, with the case as its only key."""
+
+    A: nat
+    B: typing.Optional[str]
+
+
+@dataclasses.dataclass
+class h_ret0:
+    _42_: typing.Tuple[()]
+    id: nat
+
+
+class h_ret0Dict(typing.TypedDict):
+    """`h_ret0` as a plain dict."""
+
+    _42_: typing.Tuple[()]
+    id: nat
+
+
+class Agent(typing.Protocol):
+    """Sends a call to a canister. `mode` is "query", "update" or "oneway"."""
+
+    def call(
+        self,
+        canister_id: principal,
+        method: str,
+        args: typing.List[typing.Any],
+        mode: str,
+    ) -> typing.List[typing.Any]:
+        ...
+
+
+class ServiceClient:
+    """Client of the service, which sends the calls through `agent`."""
+
+    def __init__(self, agent: Agent, canister_id: principal) -> None:
+        self._agent = agent
+        self._canister_id = canister_id
+
+    def f(self, arg0: bytes, arg1: typing.Optional[bool]) -> None:
+        self._agent.call(self._canister_id, "f", [arg0, arg1], "oneway")
+
+    def g(self, arg0: my_type, arg1: "List", arg2: typing.Optional["List"], arg3: nested) -> typing.Tuple[int, broker]:
+        return tuple(self._agent.call(self._canister_id, "g", [arg0, arg1, arg2, arg3], "query"))
+
+    def h(self, arg0: typing.List[typing.Optional[str]], arg1: h_arg1, arg2: typing.Optional["List"]) -> h_ret0:
+        return self._agent.call(self._canister_id, "h", [arg0, arg1, arg2], "update")[0]
+
+    def i(self, arg0: "List", arg1: typing.Tuple[principal, str]) -> typing.Optional["List"]:
+        return self._agent.call(self._canister_id, "i", [arg0, arg1], "update")[0]
@@ -0,0 +1,22 @@
+// Code generated by didc. DO NOT EDIT.
+
+package service
+
+import "math/big"
+
+type Service interface {
+	// Bab calls the method "bab".
+	Bab(arg0 *big.Int, arg1 *big.Int) error
+	// Bar calls the method "bar".
+	Bar(arg0 struct{ Hash50 *big.Int `candid:"_50_"` }) error
+	// Bas calls the method "bas".
+	Bas(arg0 struct{ Field0 *big.Int `candid:"0"`; Field1 *big.Int `candid:"1"` }) (struct{ Field0 string `candid:"0"`; Field1 *big.Int `candid:"1"` }, error)
+	// Baz calls the method "baz".
+	Baz(arg0 struct{ Field2 *big.Int `candid:"2"`; Hash50 *big.Int `candid:"_50_"` }) (struct{}, error)
+	// Bib calls the method "bib".
+	Bib(arg0 struct{ Field0 *big.Int `candid:"0"` }) (struct{ Field0 *big.Int `candid:"0"` }, error)
+	// Bob calls the method "bob".
+	Bob(arg0 struct{ Field50 *big.Int `candid:"50"`; Hash11867 *big.Int `candid:"_11867_"`; Hash1056155301 *big.Int `candid:"_1056155301_"` }) error
+	// Foo calls the method "foo".
+	Foo(arg0 struct{ Field2 *big.Int `candid:"2"` }) (struct{ Field2 *big.Int `candid:"2"`; Hash21235 *big.Int `candid:"_2"` }, error)
+}
@@ -17,6 +17,11 @@ export default ({ IDL }) => {
         [IDL.Variant({ _0_ : IDL.Int })],
         [],
       ),
+    'bob' : IDL.Func(
+        [IDL.Record({ _50_ : IDL.Int, '50' : IDL.Int, '_50_' : IDL.Int })],
+        [],
+        [],
+      ),
     'foo' : IDL.Func(
         [IDL.Record({ _2_ : IDL.Int })],
         [IDL.Record({ _2_ : IDL.Int, '_2' : IDL.Int })],
@@ -0,0 +1,145 @@
+# This is a generated Python binding. It requires Python 3.9 or later.
+import dataclasses
+import typing
+
+
+@dataclasses.dataclass(frozen=True)
+class Range:
+    """Inclusive bounds of an integer type, `None` for unbounded."""
+
+    min: typing.Optional[int]
+    max: typing.Optional[int]
+
+
+nat = typing.Annotated[int, Range(0, None)]
+nat8 = typing.Annotated[int, Range(0, 2**8 - 1)]
+nat16 = typing.Annotated[int, Range(0, 2**16 - 1)]
+nat32 = typing.Annotated[int, Range(0, 2**32 - 1)]
+nat64 = typing.Annotated[int, Range(0, 2**64 - 1)]
+int8 = typing.Annotated[int, Range(-(2**7), 2**7 - 1)]
+int16 = typing.Annotated[int, Range(-(2**15), 2**15 - 1)]
+int32 = typing.Annotated[int, Range(-(2**31), 2**31 - 1)]
+int64 = typing.Annotated[int, Range(-(2**63), 2**63 - 1)]
+# Textual representation of a principal
+principal = str
+
+
+@dataclasses.dataclass
+class bar_arg0:
+    _50_: int
+
+
+class bar_arg0Dict(typing.TypedDict):
+    """`bar_arg0` as a plain dict."""
+
+    _50_: int
+
+
+@dataclasses.dataclass
+class baz_arg0:
+    _2_: int
+    _50_: nat
+
+
+class baz_arg0Dict(typing.TypedDict):
+    """`baz_arg0` as a plain dict."""
+
+    _2_: int
+    _50_: nat
+
+
+class bib_ret0:
+    """Base class of the `bib_ret0_*` cases."""
+
+
+@dataclasses.dataclass
+class bib_ret0__0_(bib_ret0):
+    value: int
+    tag: typing.ClassVar[int] = 0
+
+
+class bib_ret0Dict(typing.TypedDict, total=False):
+    """`bib_ret0` as a plain dict, with the case as its only key."""
+
+    _0_: int
+
+
+@dataclasses.dataclass
+class bob_arg0:
+    _50_: int
+    _11867_: int
+    _50_: int
+
+
+class bob_arg0Dict(typing.TypedDict):
+    """`bob_arg0` as a plain dict."""
+
+    _50_: int
+    _11867_: int
+    _50_: int
+
+
+@dataclasses.dataclass
+class foo_arg0:
+    _2_: int
+
+
+class foo_arg0Dict(typing.TypedDict):
+    """`foo_arg0` as a plain dict."""
+
+    _2_: int
+
+
+@dataclasses.dataclass
+class foo_ret0:
+    _2_: int
+    _2: int
+
+
+class foo_ret0Dict(typing.TypedDict):
+    """`foo_ret0` as a plain dict."""
+
+    _2_: int
+    _2: int
+
+
+class Agent(typing.Protocol):
+    """Sends a call to a canister. `mode` is "query", "update" or "oneway"."""
+
+    def call(
+        self,
+        canister_id: principal,
+        method: str,
+        args: typing.List[typing.Any],
+        mode: str,
+    ) -> typing.List[typing.Any]:
+        ...
+
+
+class ServiceClient:
+    """Client of the service, which sends the calls through `agent`."""
+
+    def __init__(self, agent: Agent, canister_id: principal) -> None:
+        self._agent = agent
+        self._canister_id = canister_id
+
+    def bab(self, arg0: int, arg1: nat) -> None:
+        self._agent.call(self._canister_id, "bab", [arg0, arg1], "update")
+
+    def bar(self, arg0: bar_arg0) -> None:
+        self._agent.call(self._canister_id, "bar", [arg0], "update")
+
+    def bas(self, arg0: typing.Tuple[int, int]) -> typing.Tuple[str, nat]:
+        return self._agent.call(self._canister_id, "bas", [arg0], "update")[0]
+
+    def baz(self, arg0: baz_arg0) -> typing.Tuple[()]:
+        return self._agent.call(self._canister_id, "baz", [arg0], "update")[0]
+
+    def bib(self, arg0: typing.Tuple[int]) -> bib_ret0:
+        return self._agent.call(self._canister_id, "bib", [arg0], "update")[0]
+
+    def bob(self, arg0: bob_arg0) -> None:
+        self._agent.call(self._canister_id, "bob", [arg0], "update")
+
+    def foo(self, arg0: foo_arg0) -> foo_ret0:
+        return self._agent.call(self._canister_id, "foo", [arg0], "update")[0]
@@ -0,0 +1,35 @@
+// Code generated by didc. DO NOT EDIT.
+
+package service
+
+import "math/big"
+
+type A B
+
+type B *A
+
+type List *Node
+
+type Node struct {
+	Head *big.Int `candid:"head"`
+	Tail List     `candid:"tail"`
+}
+
+type S []byte
+
+type Stream *struct{ Head *big.Int `candid:"head"`; Next struct{ Principal []byte; Method string } `candid:"next"` }
+
+type T struct{ Principal []byte; Method string }
+
+// Tree is a variant, exactly one of its fields is set.
+type Tree struct {
+	Branch *struct{ Val *big.Int `candid:"val"`; Left Tree `candid:"left"`; Right Tree `candid:"right"` } `candid:"branch"`
+	Leaf   *big.Int                                                                                       `candid:"leaf"`
+}
+
+type Service interface {
+	// F calls the method "f".
+	F(arg0 S) error
+	// G calls the method "g".
+	G(arg0 List) (B, Tree, Stream, error)
+}
@@ -0,0 +1,139 @@
+# This is a generated Python binding. It requires Python 3.9 or later.
+import dataclasses
+import typing
+
+
+@dataclasses.dataclass(frozen=True)
+class Range:
+    """Inclusive bounds of an integer type, `None` for unbounded."""
+
+    min: typing.Optional[int]
+    max: typing.Optional[int]
+
+
+nat = typing.Annotated[int, Range(0, None)]
+nat8 = typing.Annotated[int, Range(0, 2**8 - 1)]
+nat16 = typing.Annotated[int, Range(0, 2**16 - 1)]
+nat32 = typing.Annotated[int, Range(0, 2**32 - 1)]
+nat64 = typing.Annotated[int, Range(0, 2**64 - 1)]
+int8 = typing.Annotated[int, Range(-(2**7), 2**7 - 1)]
+int16 = typing.Annotated[int, Range(-(2**15), 2**15 - 1)]
+int32 = typing.Annotated[int, Range(-(2**31), 2**31 - 1)]
+int64 = typing.Annotated[int, Range(-(2**63), 2**63 - 1)]
+# Textual representation of a principal
+principal = str
+
+
+t = typing.Tuple[principal, str]
+
+
+@dataclasses.dataclass
+class node:
+    head: nat
+    tail: "list"
+
+
+class nodeDict(typing.TypedDict):
+    """`node` as a plain dict."""
+
+    head: nat
+    tail: "listDict"
+
+
+list = typing.Optional[node]
+
+
+listDict = typing.Optional[nodeDict]
+
+
+A = "B"
+
+
+B = typing.Optional[A]
+
+
+class tree:
+    """Base class of the `tree_*` cases."""
+
+
+@dataclasses.dataclass
+class tree_branch_value:
+    val: int
+    left: "tree"
+    right: "tree"
+
+
+class tree_branch_valueDict(typing.TypedDict):
+    """`tree_branch_value` as a plain dict."""
+
+    val: int
+    left: "treeDict"
+    right: "treeDict"
+
+
+@dataclasses.dataclass
+class tree_branch(tree):
+    value: tree_branch_value
+    tag: typing.ClassVar[str] = "branch"
+
+
+@dataclasses.dataclass
+class tree_leaf(tree):
+    value: int
+    tag: typing.ClassVar[str] = "leaf"
+
+
+class treeDict(typing.TypedDict, total=False):
+    """`tree` as a plain dict, with the case as its only key."""
+
+    branch: tree_branch_valueDict
+    leaf: int
+
+
+@dataclasses.dataclass
+class stream_inner:
+    head: nat
+    next: typing.Tuple[principal, str]
+
+
+class stream_innerDict(typing.TypedDict):
+    """`stream_inner` as a plain dict."""
+
+    head: nat
+    next: typing.Tuple[principal, str]
+
+
+stream = typing.Optional[stream_inner]
+
+
+streamDict = typing.Optional[stream_innerDict]
+
+
+s = principal
+
+
+class Agent(typing.Protocol):
+    """Sends a call to a canister. `mode` is "query", "update" or "oneway"."""
+
+    def call(
+        self,
+        canister_id: principal,
+        method: str,
+        args: typing.List[typing.Any],
+        mode: str,
+    ) -> typing.List[typing.Any]:
+        ...
+
+
+class ServiceClient:
+    """Client of the service, which sends the calls through `agent`."""
+
+    def __init__(self, agent: Agent, canister_id: principal) -> None:
+        self._agent = agent
+        self._canister_id = canister_id
+
+    def f(self, arg0: "s") -> None:
+        self._agent.call(self._canister_id, "f", [arg0], "update")
+
+    def g(self, arg0: "list") -> typing.Tuple["B", "tree", "stream"]:
+        return tuple(self._agent.call(self._canister_id, "g", [arg0], "update"))
@@ -0,0 +1,31 @@
+// Code generated by didc. DO NOT EDIT.
+
+package service
+
+import "math/big"
+
+type A struct {
+	Hash11864174   *big.Int `candid:""`
+	Hash1832283146 *big.Int `candid:"📦🍦"`
+	Hash2119362116 *big.Int `candid:"字段名"`
+	Hash3133479156 *big.Int `candid:"字 段 名2"`
+}
+
+// B is a variant, exactly one of its fields is set.
+type B struct {
+	Hash0          *struct{} `candid:""`
+	Hash650764729  *struct{} `candid:"空的"`
+	Hash1036827129 *struct{} `candid:"  空的  "`
+	Hash3099250646 *struct{} `candid:"1⃣️2⃣️3⃣️"`
+}
+
+type Service interface {
+	// Hash0 calls the method "".
+	Hash0(arg0 *big.Int) (*big.Int, error)
+	// Hash356566390 calls the oneway method "✈️  🚗 ⛱️ ".
+	Hash356566390() error
+	// Hash2669435454 calls the query method "👀".
+	Hash2669435454(arg0 *big.Int) (*big.Int, error)
+	// Hash3300066460 calls the method "函数名".
+	Hash3300066460(arg0 A) (B, error)
+}
@@ -0,0 +1,107 @@
+# This is a generated Python binding. It requires Python 3.9 or later.
+import dataclasses
+import typing
+
+
+@dataclasses.dataclass(frozen=True)
+class Range:
+    """Inclusive bounds of an integer type, `None` for unbounded."""
+
+    min: typing.Optional[int]
+    max: typing.Optional[int]
+
+
+nat = typing.Annotated[int, Range(0, None)]
+nat8 = typing.Annotated[int, Range(0, 2**8 - 1)]
+nat16 = typing.Annotated[int, Range(0, 2**16 - 1)]
+nat32 = typing.Annotated[int, Range(0, 2**32 - 1)]
+nat64 = typing.Annotated[int, Range(0, 2**64 - 1)]
+int8 = typing.Annotated[int, Range(-(2**7), 2**7 - 1)]
+int16 = typing.Annotated[int, Range(-(2**15), 2**15 - 1)]
+int32 = typing.Annotated[int, Range(-(2**31), 2**31 - 1)]
+int64 = typing.Annotated[int, Range(-(2**63), 2**63 - 1)]
+# Textual representation of a principal
+principal = str
+
+
+@dataclasses.dataclass
+class A:
+    _11864174_: nat
+    _1832283146_: nat
+    _2119362116_: nat
+    _3133479156_: nat
+
+
+class ADict(typing.TypedDict):
+    """`A` as a plain dict."""
+
+    _11864174_: nat
+    _1832283146_: nat
+    _2119362116_: nat
+    _3133479156_: nat
+
+
+class B:
+    """Base class of the `B_*` cases."""
+
+
+@dataclasses.dataclass
+class B__0_(B):
+    tag: typing.ClassVar[str] = ""
+
+
+@dataclasses.dataclass
+class B__650764729_(B):
+    tag: typing.ClassVar[str] = "空的"
+
+
+@dataclasses.dataclass
+class B__1036827129_(B):
+    tag: typing.ClassVar[str] = "  空的  "
+
+
+@dataclasses.dataclass
+class B__3099250646_(B):
+    tag: typing.ClassVar[str] = "1⃣️2⃣️3⃣️"
+
+
+class BDict(typing.TypedDict, total=False):
+    """`B` as a plain dict, with the case as its only key."""
+
+    _0_: None
+    _650764729_: None
+    _1036827129_: None
+    _3099250646_: None
+
+
+class Agent(typing.Protocol):
+    """Sends a call to a canister. `mode` is "query", "update" or "oneway"."""
+
+    def call(
+        self,
+        canister_id: principal,
+        method: str,
+        args: typing.List[typing.Any],
+        mode: str,
+    ) -> typing.List[typing.Any]:
+        ...
+
+
+class ServiceClient:
+    """Client of the service, which sends the calls through `agent`."""
+
+    def __init__(self, agent: Agent, canister_id: principal) -> None:
+        self._agent = agent
+        self._canister_id = canister_id
+
+    def _0_(self, arg0: nat) -> nat:
+        return self._agent.call(self._canister_id, "", [arg0], "update")[0]
+
+    def _356566390_(self) -> None:
+        self._agent.call(self._canister_id, "✈️  🚗 ⛱️ ", [], "oneway")
+
+    def _2669435454_(self, arg0: nat) -> nat:
+        return self._agent.call(self._canister_id, "👀", [arg0], "query")[0]
+
+    def _3300066460_(self, arg0: A) -> B:
+        return self._agent.call(self._canister_id, "函数名", [arg0], "update")[0]
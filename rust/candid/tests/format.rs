@@ -0,0 +1,86 @@
+use candid::parser::format::format;
+use candid::parser::types::IDLProg;
+use std::path::Path;
+
+#[test]
+fn format_comments() {
+    let prog = r#"// Header
+
+import "a.did";
+type List = opt record { head: int; tail: List };   // trailing
+/* block */
+type B = variant {
+  // the first
+  a;
+  "b c": nat;   // b
+
+  0x2a: text
+};
+type S = service { m: (nat) -> (opt /* inline */ nat); n: F };
+service : {
+  // get
+  get: (nat) -> (List) query;
+  set: (List, B) -> (); // set
+  // end
+}
+// eof
+"#;
+    let expected = r#"// Header
+
+import "a.did";
+type List = opt record { head : int; tail : List }; // trailing
+/* block */
+type B = variant {
+  // the first
+  a;
+  "b c" : nat; // b
+
+  0x2a : text;
+};
+type S = service {
+  /* inline */
+  m : (nat) -> (opt nat);
+  n : F;
+};
+service : {
+  // get
+  get : (nat) -> (List) query;
+  set : (List, B) -> (); // set
+  // end
+}
+// eof
+"#;
+    assert_eq!(format(prog, 80).unwrap(), expected);
+    assert_eq!(format(expected, 80).unwrap(), expected);
+}
+
+#[test]
+fn format_width() {
+    let prog = "type A = record { name: text; id: nat; tags: vec text };\n";
+    assert_eq!(
+        format(prog, 80).unwrap(),
+        "type A = record { name : text; id : nat; tags : vec text };\n"
+    );
+    assert_eq!(
+        format(prog, 40).unwrap(),
+        "type A = record {\n  name : text;\n  id : nat;\n  tags : vec text;\n};\n"
+    );
+    assert!(format("type A = record { a: nat", 80).is_err());
+}
+
+#[test_generator::test_resources("rust/candid/tests/assets/*.did")]
+fn format_test(resource: &str) {
+    let path = std::env::current_dir()
+        .unwrap()
+        .join("tests/assets")
+        .join(Path::new(resource).file_name().unwrap());
+    let prog = std::fs::read_to_string(&path).unwrap();
+    let ast = match prog.parse::<IDLProg>() {
+        Ok(ast) => ast,
+        Err(_) => return,
+    };
+    let formatted = format(&prog, 80).unwrap();
+    let ast2 = formatted.parse::<IDLProg>().unwrap();
+    assert_eq!(format!("{:?}", ast2), format!("{:?}", ast));
+    assert_eq!(format(&formatted, 80).unwrap(), formatted);
+}
@@ -0,0 +1,160 @@
+use candid::parser::hash::{hash_args, hash_value};
+use candid::parser::types::IDLType;
+use candid::parser::value::IDLValue;
+use candid::types::Type;
+use candid::{check_prog, CandidType, Deserialize, Encode, IDLArgs, IDLProg, TypeEnv};
+use std::collections::{BTreeSet, HashSet};
+
+fn env() -> TypeEnv {
+    let did_file = r#"
+type List = opt record { head: int; tail: List };
+type Shape = variant { circle: float64; square: nat; none };
+type Entry = record { id: nat64; name: text; shape: Shape; tags: vec text };
+"#;
+    let mut env = TypeEnv::new();
+    check_prog(&mut env, &did_file.parse::<IDLProg>().unwrap()).unwrap();
+    env
+}
+
+fn ty(env: &TypeEnv, str: &str) -> Type {
+    let ast = str.parse::<IDLType>().unwrap();
+    env.ast_to_type(&ast).unwrap()
+}
+
+fn hash(env: &TypeEnv, t: &str, v: &str) -> [u8; 32] {
+    hash_value(env, &ty(env, t), &v.parse::<IDLValue>().unwrap()).unwrap()
+}
+
+#[test]
+fn same_logical_value() {
+    let env = env();
+    let entry = hash(
+        &env,
+        "Entry",
+        r#"record { id = 1; name = "a"; shape = variant { square = 2 }; tags = vec { "x" } }"#,
+    );
+    assert_eq!(
+        entry,
+        hash(
+            &env,
+            "Entry",
+            r#"record { tags = vec { "x" }; shape = variant { square = 2 }; name = "a"; id = 1; extra = 3 }"#
+        )
+    );
+    // Decoded from a message, where fields are typed and ordered by id
+    #[derive(CandidType, Deserialize)]
+    enum Shape {
+        #[serde(rename = "square")]
+        Square(candid::Nat),
+    }
+    #[derive(CandidType)]
+    struct Entry {
+        id: u64,
+        name: String,
+        shape: Shape,
+        tags: Vec<String>,
+    }
+    let bytes = Encode!(&Entry {
+        id: 1,
+        name: "a".to_string(),
+        shape: Shape::Square(2u8.into()),
+        tags: vec!["x".to_string()],
+    })
+    .unwrap();
+    let args = IDLArgs::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        entry,
+        hash_value(&env, &ty(&env, "Entry"), &args.args[0]).unwrap()
+    );
+
+    // Numbers hash by value
+    let five = hash(&env, "nat8", "5");
+    for t in &["nat", "int", "nat64", "int16"] {
+        assert_eq!(five, hash(&env, t, "5"));
+    }
+    assert_eq!(hash(&env, "int", "-5"), hash(&env, "int8", "-5"));
+    assert_eq!(hash(&env, "float32", "1.5"), hash(&env, "float64", "1.5"));
+    assert_eq!(hash(&env, "opt nat", "null"), hash(&env, "null", "null"));
+    let list = hash(&env, "List", "opt record { head = 1; tail = null }");
+    assert_eq!(
+        list,
+        hash(&env, "List", "opt record { tail = null; head = 1 }")
+    );
+}
+
+#[test]
+fn different_values() {
+    let env = env();
+    let hashes = [
+        hash(&env, "nat", "5"),
+        hash(&env, "nat", "6"),
+        hash(&env, "int", "-5"),
+        hash(&env, "text", "\"a\""),
+        hash(&env, "vec nat", "vec {}"),
+        hash(&env, "vec nat", "vec { 5 }"),
+        hash(&env, "opt nat", "opt 6"),
+        hash(&env, "opt opt nat", "opt null"),
+        hash(&env, "bool", "true"),
+        hash(&env, "bool", "false"),
+        hash(&env, "float64", "0.0"),
+        hash(&env, "float64", "-0.0"),
+        hash(&env, "Shape", "variant { square = 0 }"),
+        hash(&env, "Shape", "variant { none }"),
+        hash(&env, "record { a: nat; b: nat }", "record { a = 1; b = 2 }"),
+        hash(&env, "record { a: nat; b: nat }", "record { a = 2; b = 1 }"),
+        hash(&env, "List", "opt record { head = 1; tail = null }"),
+        hash(
+            &env,
+            "List",
+            "opt record { head = 1; tail = opt record { head = 1; tail = null } }",
+        ),
+    ];
+    let set: HashSet<_> = hashes.iter().collect();
+    assert_eq!(set.len(), hashes.len());
+
+    // The value does not have the type
+    let v = "record { id = 1 }".parse::<IDLValue>().unwrap();
+    assert!(hash_value(&env, &ty(&env, "Entry"), &v).is_err());
+    let v = "-1".parse::<IDLValue>().unwrap();
+    assert!(hash_value(&env, &ty(&env, "nat"), &v).is_err());
+}
+
+#[test]
+fn args() {
+    let env = env();
+    let types = [ty(&env, "nat"), ty(&env, "text")];
+    let args = "(1, \"a\")".parse::<IDLArgs>().unwrap();
+    let h = hash_args(&env, &types, &args).unwrap();
+    let args = "(1 : nat, \"a\")".parse::<IDLArgs>().unwrap();
+    assert_eq!(h, hash_args(&env, &types, &args).unwrap());
+    let args = "(\"a\", 1)".parse::<IDLArgs>().unwrap();
+    assert!(hash_args(&env, &types, &args).is_err());
+    let args = "(1)".parse::<IDLArgs>().unwrap();
+    assert!(hash_args(&env, &types, &args).is_err());
+}
+
+#[test]
+fn eq_ord_hash() {
+    let values: Vec<IDLValue> = [
+        "record { a = 1; b = vec { 1; 2 } }",
+        "record { a = 1; b = vec { 1; 2 } }",
+        "variant { a = \"x\" }",
+        "opt (1 : nat8)",
+        "opt (1 : nat16)",
+        "(0.0 : float64)",
+        "(-0.0 : float64)",
+    ]
+    .iter()
+    .map(|v| v.parse().unwrap())
+    .collect();
+    let hash_set: HashSet<_> = values.iter().cloned().collect();
+    let btree_set: BTreeSet<_> = values.iter().cloned().collect();
+    assert_eq!(hash_set.len(), 6);
+    assert_eq!(btree_set.len(), 6);
+    assert!(btree_set.iter().all(|v| hash_set.contains(v)));
+
+    let nan = IDLValue::Float64(f64::NAN);
+    assert_eq!(nan, nan.clone());
+    assert!(IDLValue::Nat8(1) < IDLValue::Nat8(2));
+    assert!(IDLValue::Text("a".to_string()) < IDLValue::Text("b".to_string()));
+}
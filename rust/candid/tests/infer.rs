@@ -0,0 +1,100 @@
+use candid::parser::infer::infer_type;
+use candid::parser::types::IDLType;
+use candid::types::Type;
+use candid::{IDLArgs, TypeEnv};
+
+fn infer(value: &str, narrow: bool) -> candid::Result<Type> {
+    let v = value.parse().unwrap();
+    infer_type(&v, narrow)
+}
+
+fn check(value: &str, narrow: bool, expected: &str) {
+    let env = TypeEnv::new();
+    let expected = env
+        .ast_to_type(&expected.parse::<IDLType>().unwrap())
+        .unwrap();
+    let ty = infer(value, narrow).unwrap();
+    assert_eq!(ty, expected, "{}", value);
+    // The value can be encoded with the inferred type
+    let args = format!("({})", value).parse::<IDLArgs>().unwrap();
+    args.to_bytes_with_types(&env, &[ty]).unwrap();
+}
+
+fn check_error(value: &str, message: &str) {
+    let err = infer(value, false).unwrap_err();
+    assert_eq!(err.to_string(), message);
+}
+
+#[test]
+fn vectors() {
+    check("vec {}", false, "vec null");
+    check("vec { null; opt 5 }", false, "vec opt int");
+    check(
+        "vec { opt opt 5; null; opt null }",
+        false,
+        "vec opt opt int",
+    );
+    check("vec { vec {}; vec { 1 } }", false, "vec vec int");
+    check("vec { 1; 2.5 }", false, "vec float64");
+    check("vec { 1; (2 : nat16) }", false, "vec nat16");
+    check("vec { blob \"a\"; vec { 1 } }", false, "vec blob");
+    check_error(
+        "vec { 1; \"a\" }",
+        "vector element 1: no common type for number and text",
+    );
+    check_error(
+        "vec { null; 1 }",
+        "vector element 1: no common type for null and number",
+    );
+    check_error(
+        "vec { (1 : nat8); (1 : nat16) }",
+        "vector element 1: no common type for nat8 and nat16",
+    );
+    check_error(
+        "vec { 256; (1 : nat8) }",
+        "vector element 1: no common type for number and nat8",
+    );
+}
+
+#[test]
+fn records() {
+    check(
+        "vec { record { a = 1 }; record { a = 2; b = opt \"x\" } }",
+        false,
+        "vec record { a : int; b : opt text }",
+    );
+    check(
+        "vec { record { 1; null }; record { 2 } }",
+        false,
+        "vec record { int; null }",
+    );
+    check(
+        "vec { variant { a }; variant { b = vec { 1 } }; variant { b = vec {} } }",
+        false,
+        "vec variant { a; b : vec int }",
+    );
+    check_error(
+        "vec { record { a = 1 }; record { a = opt 1 } }",
+        "vector element 1: record field a: no common type for number and opt int",
+    );
+    check_error(
+        "vec { record { a = 1 }; record { b = null } }",
+        "vector element 1: record field a is missing from some records, but is not optional",
+    );
+}
+
+#[test]
+fn narrow() {
+    check("vec { 1; 255 }", true, "vec nat8");
+    check("vec { 1; 256 }", true, "vec nat16");
+    check("vec { -1; 255 }", true, "vec int16");
+    check("vec { -129; 127 }", true, "vec int16");
+    check("18446744073709551616", true, "nat");
+    check("-9223372036854775809", true, "int");
+    check(
+        "record { 4294967295; vec { opt -2147483648 } }",
+        true,
+        "record { nat32; vec opt int32 }",
+    );
+    check("vec { 1; 255 }", false, "vec int");
+}
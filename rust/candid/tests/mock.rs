@@ -0,0 +1,85 @@
+#![cfg(feature = "random")]
+use candid::mock::{Call, MockService};
+use candid::parser::value::IDLValue;
+use candid::{check_prog, IDLArgs, IDLProg, TypeEnv};
+
+fn mock() -> MockService {
+    let did_file = r#"
+type List = opt record { head: int; tail: List };
+service : {
+  get : (nat8) -> (List) query;
+  put : (text, List) -> ();
+}
+"#;
+    let mut env = TypeEnv::new();
+    let actor = check_prog(&mut env, &did_file.parse::<IDLProg>().unwrap())
+        .unwrap()
+        .unwrap();
+    MockService::new(env, actor).unwrap().with_seed(42)
+}
+
+fn encode(mock: &MockService, method: &str, args: &str) -> Vec<u8> {
+    let func = mock.env().get_method(mock.actor(), method).unwrap();
+    let args = args.parse::<IDLArgs>().unwrap();
+    args.to_bytes_with_types(mock.env(), &func.args).unwrap()
+}
+
+fn decode(mock: &MockService, method: &str, reply: &[u8]) -> String {
+    let func = mock.env().get_method(mock.actor(), method).unwrap();
+    IDLArgs::from_bytes_with_types(reply, mock.env(), &func.rets)
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn scripted_responses() {
+    let mut mock = mock();
+    mock.respond(
+        "get",
+        "(opt record { head = 1; tail = null })".parse().unwrap(),
+    )
+    .unwrap()
+    .respond("get", "(null)".parse().unwrap())
+    .unwrap();
+    let args = encode(&mock, "get", "(1)");
+    let reply = mock.call("get", &args).unwrap();
+    assert_eq!(
+        decode(&mock, "get", &reply),
+        "(opt record { head = 1; tail = null; })"
+    );
+    let reply = mock.call("get", &args).unwrap();
+    assert_eq!(decode(&mock, "get", &reply), "(null)");
+    // Random replies afterwards
+    let reply = mock.call("get", &args).unwrap();
+    decode(&mock, "get", &reply);
+
+    let result = mock.respond("get", "(42)".parse().unwrap());
+    assert!(result.is_err());
+    let result = mock.respond("unknown", "()".parse().unwrap());
+    assert!(result.is_err());
+}
+
+#[test]
+fn validate_arguments() {
+    let mut mock = mock();
+    let args = encode(
+        &mock,
+        "put",
+        "(\"a\", opt record { head = 1; tail = null })",
+    );
+    let reply = mock.call("put", &args).unwrap();
+    assert_eq!(decode(&mock, "put", &reply), "()");
+    // Wrong argument types
+    let args = encode(&mock, "get", "(1)");
+    assert!(mock.call("put", &args).is_err());
+    let args = "(256)".parse::<IDLArgs>().unwrap().to_bytes().unwrap();
+    assert!(mock.call("get", &args).is_err());
+    assert!(mock.call("unknown", &args).is_err());
+    // Only valid calls are recorded
+    assert_eq!(mock.calls().len(), 1);
+    let Call { method, args } = &mock.calls()[0];
+    assert_eq!(method, "put");
+    assert_eq!(args.args[0], IDLValue::Text("a".to_string()));
+    mock.clear_calls();
+    assert!(mock.calls().is_empty());
+}
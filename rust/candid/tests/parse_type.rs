@@ -1,5 +1,7 @@
-use candid::bindings::{candid as candid_export, javascript};
-use candid::parser::types::{to_pretty, IDLProg};
+use candid::bindings::{
+    candid as candid_export, doc, go, javascript, jsonschema, motoko, protobuf, python,
+};
+use candid::parser::types::{to_pretty, DocComments, DocStep, IDLProg};
 use candid::parser::typing::{check_prog, TypeEnv};
 use candid::types::Type;
 use goldenfile::Mint;
@@ -32,10 +34,80 @@ service server : {
     assert_eq!(format!("{:?}", ast2), format!("{:?}", ast));
 }
 
-fn compile(env: &mut TypeEnv, file: &Path) -> candid::Result<Option<Type>> {
+#[test]
+fn lexer_spans() {
+    use candid::parser::lexer::{Lexer, Token};
+    let text = "0x2a; 0x_ff_ff : 42";
+    let spans: Vec<_> = Lexer::new(text)
+        .map(|t| t.unwrap())
+        .filter(|(_, t, _)| matches!(t, Token::Number(_)))
+        .map(|(l, _, r)| &text[l..r])
+        .collect();
+    assert_eq!(spans, ["0x2a", "0x_ff_ff", "42"]);
+}
+
+#[test]
+fn parse_doc_comments() {
+    let prog = r#"
+/// A list
+type List = opt record {
+  /// first
+  head: int; tail: List };
+/// not attached to anything
+service : {
+  //// plain
+  /// get
+  get: () -> (List) query;
+  set: (List) -> ();
+}
+"#;
+    let ast = prog.parse::<IDLProg>().unwrap();
+    let docs = DocComments::from_prog(&ast);
+    assert_eq!(docs.type_docs("List"), ["A list"]);
+    let field = |id: &str| [DocStep::Field(candid::idl_hash(id))];
+    assert_eq!(docs.field_docs(Some("List"), &field("head")), ["first"]);
+    assert!(docs.field_docs(Some("List"), &field("tail")).is_empty());
+    assert_eq!(docs.method_docs("get"), ["get"]);
+    assert!(docs.method_docs("set").is_empty());
+
+    let prog =
+        "/// A\ntype A = record { /// a\n a: nat; b: text };\nservice : { /// m\n m: (A) -> () }";
+    let rust =
+        candid::codegen::rust::idl_to_rust(&prog.parse::<IDLProg>().unwrap(), &Default::default())
+            .unwrap();
+    assert!(rust.starts_with(
+        "/// A\n#[derive(Clone)] pub struct A { /// a\npub a : u128 , pub b : String }"
+    ));
+    assert!(rust.contains("pub trait Actor { /// m\nfn m( arg0 : Box<A> ) "));
+
+    // Doc comments of nested fields, and of fields in function arguments
+    let prog = r#"
+type A = vec record { b: opt record { /// c
+  c: nat } };
+service : { m: (record { /// d
+  d: func (record { /// e
+    e: nat }) -> () }) -> () }
+"#;
+    let docs = DocComments::from_prog(&prog.parse::<IDLProg>().unwrap());
+    let path = [field("b")[0], field("c")[0]];
+    assert_eq!(docs.field_docs(Some("A"), &path), ["c"]);
+    let path = [field("m")[0], DocStep::Arg(0), field("d")[0]];
+    assert_eq!(docs.field_docs(None, &path), ["d"]);
+    let path = [
+        field("m")[0],
+        DocStep::Arg(0),
+        field("d")[0],
+        DocStep::Arg(0),
+        field("e")[0],
+    ];
+    assert_eq!(docs.field_docs(None, &path), ["e"]);
+}
+
+fn compile(env: &mut TypeEnv, file: &Path) -> candid::Result<(Option<Type>, DocComments)> {
     let prog = std::fs::read_to_string(&file)?;
     let ast = prog.parse::<IDLProg>()?;
-    check_prog(env, &ast)
+    let actor = check_prog(env, &ast)?;
+    Ok((actor, DocComments::from_prog(&ast)))
 }
 
 #[test_generator::test_resources("rust/candid/tests/assets/*.did")]
@@ -48,10 +120,10 @@ fn compiler_test(resource: &str) {
 
     let mut env = TypeEnv::new();
     match compile(&mut env, &candid_path) {
-        Ok(actor) => {
+        Ok((actor, docs)) => {
             {
                 let mut output = mint.new_goldenfile(filename.with_extension("did")).unwrap();
-                let content = candid_export::compile(&env, &actor);
+                let content = candid_export::compile_with_docs(&env, &actor, &docs);
                 // Type check output
                 let ast = content.parse::<IDLProg>().unwrap();
                 check_prog(&mut TypeEnv::new(), &ast).unwrap();
@@ -59,9 +131,55 @@ fn compiler_test(resource: &str) {
             }
             {
                 let mut output = mint.new_goldenfile(filename.with_extension("js")).unwrap();
-                let content = javascript::compile(&env, &actor);
+                let content = javascript::compile_with_docs(&env, &actor, &docs);
+                writeln!(output, "{}", content).unwrap();
+            }
+            {
+                let mut output = mint.new_goldenfile(filename.with_extension("mo")).unwrap();
+                let content = motoko::compile(&env, &actor);
+                writeln!(output, "{}", content).unwrap();
+            }
+            {
+                let mut output = mint.new_goldenfile(filename.with_extension("py")).unwrap();
+                let content = python::compile(&env, &actor);
+                write!(output, "{}", content).unwrap();
+            }
+            {
+                let mut output = mint.new_goldenfile(filename.with_extension("go")).unwrap();
+                let content = go::compile(&env, &actor);
+                write!(output, "{}", content).unwrap();
+            }
+            {
+                let mut output = mint
+                    .new_goldenfile(filename.with_extension("proto"))
+                    .unwrap();
+                let content = protobuf::compile(&env, &actor).unwrap();
+                // The output imports back as a Candid program
+                let ast = protobuf::parse(&content).unwrap();
+                check_prog(&mut TypeEnv::new(), &ast).unwrap();
+                write!(output, "{}", content).unwrap();
+            }
+            {
+                let mut output = mint
+                    .new_goldenfile(filename.with_extension("schema.json"))
+                    .unwrap();
+                let content = jsonschema::compile(&env, &actor);
+                writeln!(output, "{}", content).unwrap();
+            }
+            {
+                let mut output = mint
+                    .new_goldenfile(filename.with_extension("openapi.json"))
+                    .unwrap();
+                let content = jsonschema::compile_openapi(&env, &actor);
                 writeln!(output, "{}", content).unwrap();
             }
+            {
+                let mut output = mint.new_goldenfile(filename.with_extension("md")).unwrap();
+                let title = filename.file_stem().unwrap().to_str().unwrap();
+                let content =
+                    doc::compile(&env, &actor, &docs, title, doc::Format::Markdown).unwrap();
+                write!(output, "{}", content).unwrap();
+            }
         }
         Err(e) => {
             let mut fail_output = mint
@@ -71,3 +189,92 @@ fn compiler_test(resource: &str) {
         }
     }
 }
+
+#[test]
+fn html_doc() {
+    let prog = r#"
+/// A <list>
+type List = opt record { head: int; tail: List };
+service : { f: (List) -> () oneway; "g&h": () -> () }
+"#;
+    let ast = prog.parse::<IDLProg>().unwrap();
+    let mut env = TypeEnv::new();
+    let actor = check_prog(&mut env, &ast).unwrap();
+    let docs = DocComments::from_prog(&ast);
+    let html = doc::compile(&env, &actor, &docs, "list", doc::Format::Html).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains(
+        "<h3 id=\"method-f\"><code>f</code> <span class=\"badge oneway\">oneway</span></h3>"
+    ));
+    assert!(html.contains("<code>&quot;g&amp;h&quot;</code>"));
+    assert!(html.contains("<p>Arguments: (<a href=\"#type-List\">List</a>)</p>"));
+    assert!(html.contains("<span class=\"badge recursive\">recursive</span>"));
+    assert!(html.contains("<p>A &lt;list&gt;</p>"));
+    assert!(html.contains("<td>head</td><td>1158359328</td><td>int</td>"));
+}
+
+#[test]
+fn protobuf_import() {
+    let proto = r#"
+syntax = "proto3";
+package shop.v1;
+
+import "google/protobuf/empty.proto";
+import "google/protobuf/wrappers.proto";
+option go_package = "example.com/shop";
+
+/* An item, with a recursive bundle */
+message Item {
+  string name = 1;
+  repeated Item bundle = 2 [packed = false];
+  google.protobuf.StringValue note = 3;
+  Kind kind = 4;
+  map<string, uint64> stock = 5;
+  oneof price {
+    uint32 cents = 6;
+    Free free = 7;
+  }
+  optional int64 count = 8;
+  message Free {}
+  enum Kind {
+    KIND_UNSPECIFIED = 0;
+    KIND_BOOK = 1;
+  }
+  reserved 9, 10;
+}
+
+message Reply {
+  oneof result {
+    .shop.v1.Item ok = 1;
+    string err = 2;
+  }
+}
+
+service Shop {
+  rpc Get (Item) returns (Reply) {
+    option idempotency_level = NO_SIDE_EFFECTS;
+  }
+  rpc Clear (google.protobuf.Empty) returns (google.protobuf.Empty);
+}
+"#;
+    let ast = protobuf::parse(proto).unwrap();
+    let mut env = TypeEnv::new();
+    let actor = check_prog(&mut env, &ast).unwrap();
+    let did = candid_export::compile(&env, &actor);
+    let expected = r#"type Item = record {
+  name : text;
+  bundle : vec Item;
+  note : opt text;
+  kind : Item_Kind;
+  stock : vec record { text; nat64 };
+  price : opt variant { cents : nat32; free : Item_Free };
+  count : opt int64;
+};
+type Item_Free = record {};
+type Item_Kind = variant { KIND_UNSPECIFIED; KIND_BOOK };
+type Reply = variant { ok : Item; err : text };
+service : { Get : (Item) -> (Reply) query; Clear : () -> () }"#;
+    assert_eq!(did, expected);
+    assert!(protobuf::parse("message A { B b = 1; }").is_err());
+    assert!(protobuf::parse("service S { rpc f (stream A) returns (A); }").is_err());
+}
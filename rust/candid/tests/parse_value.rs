@@ -220,3 +220,97 @@ fn parse_shorthand() {
         "(variant { 42 = null }, variant { label = null })"
     );
 }
+
+#[test]
+fn parse_number_literals() {
+    let args = parse_args("(0xffff_ffff_ffff_ffff_ff, 1_000_000, 1e3, 2.5E-2, -1.5e+2)");
+    assert_eq!(
+        args.args,
+        vec![
+            IDLValue::Number("4722366482869645213695".to_owned()),
+            IDLValue::Number("1000000".to_owned()),
+            IDLValue::Float64(1000f64),
+            IDLValue::Float64(0.025f64),
+            IDLValue::Float64(-150f64),
+        ]
+    );
+    let result = parse_args_err("(1e)");
+    assert_eq!(
+        format!("{}", result.unwrap_err()),
+        "Candid parser error: Unexpected eof"
+    );
+}
+
+#[test]
+fn parse_annotations() {
+    let args =
+        parse_args("(42 : nat8, (vec { 1; 2 } : vec int16), (3.5 : float32), (1 : float64))");
+    assert_eq!(
+        args.args,
+        vec![
+            IDLValue::Nat8(42),
+            IDLValue::Vec(vec![IDLValue::Int16(1), IDLValue::Int16(2)]),
+            IDLValue::Float32(3.5),
+            IDLValue::Float64(1f64),
+        ]
+    );
+    // Nested annotations are only allowed when the value determines the type
+    let args = parse_args("(record { a = (42 : nat8); b = opt (\"aaaaa-aa\" : principal) })");
+    assert_eq!(
+        args.args[0].value_ty(),
+        parse_type("record { a: nat8; b: opt principal }")
+    );
+    for text in &[
+        "(record { (variant { b } : variant { a; b }) })",
+        "(record { a = (vec {} : vec text) })",
+        "(opt (null : opt nat))",
+    ] {
+        let result = parse_args_err(text);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("only supported on a whole argument"));
+    }
+    let (args, types) = IDLArgs::parse_annotated("(vec {} : vec text, 42)").unwrap();
+    assert_eq!(
+        args.args,
+        vec![IDLValue::Vec(vec![]), IDLValue::Number("42".to_owned())]
+    );
+    assert_eq!(types, vec![Some(parse_type("vec text")), None]);
+    let result = parse_args_err("(256 : nat8)");
+    assert_eq!(
+        format!("{}", result.unwrap_err()),
+        "Candid parser error: number too large to fit in target type"
+    );
+    let result = parse_args_err("(42 : list)");
+    assert!(result.is_err());
+}
+
+#[test]
+fn parse_principal_shorthand() {
+    let value = IDLValue::Text("aaaaa-aa".to_owned());
+    let principal = value
+        .annotate_type(true, &TypeEnv::new(), &Type::Principal)
+        .unwrap();
+    assert_eq!(principal.to_string(), "principal \"aaaaa-aa\"");
+    assert!(value
+        .annotate_type(false, &TypeEnv::new(), &Type::Principal)
+        .is_err());
+    let args = parse_args("(\"aaaaa-aa\" : principal)");
+    assert_eq!(args.args, vec![principal]);
+    assert!(parse_args_err("(\"not a principal\" : principal)").is_err());
+}
+
+#[test]
+fn pretty_blob() {
+    use candid::parser::value::pretty::pp_args;
+    let args = parse_args("(blob \"\\de\\ad\", blob \"DIDL\\00\\\"\\\\\", vec { 1; 2 })");
+    let text = pp_args(&args).pretty(80).to_string();
+    assert_eq!(
+        text,
+        "(blob \"\\de\\ad\", blob \"DIDL\\00\\\"\\\\\", vec { 1; 2 })"
+    );
+    assert_eq!(text.parse::<IDLArgs>().unwrap(), args);
+    let empty = parse_args("(vec {} : blob)");
+    assert_eq!(pp_args(&empty).pretty(80).to_string(), "(vec {})");
+}
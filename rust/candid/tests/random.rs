@@ -0,0 +1,104 @@
+#![cfg(feature = "random")]
+use candid::parser::random::{random_args, random_value, GenConfig};
+use candid::parser::types::IDLProg;
+use candid::parser::typing::{check_prog, TypeEnv};
+use candid::parser::value::{IDLArgs, IDLValue};
+use candid::types::Type;
+use rand::{rngs::StdRng, SeedableRng};
+
+fn parse_env(prog: &str) -> TypeEnv {
+    let ast = prog.parse::<IDLProg>().unwrap();
+    let mut env = TypeEnv::new();
+    check_prog(&mut env, &ast).unwrap();
+    env
+}
+
+fn round_trip(env: &TypeEnv, types: &[Type]) {
+    let mut rng = StdRng::seed_from_u64(42);
+    let config = GenConfig::new();
+    for _ in 0..100 {
+        let args = random_args(&mut rng, env, types, &config).unwrap();
+        let bytes = args.to_bytes_with_types(env, types).unwrap();
+        let decoded = IDLArgs::from_bytes_with_types(&bytes, env, types).unwrap();
+        assert_eq!(args, decoded);
+    }
+}
+
+#[test]
+fn random_primitives() {
+    use Type::*;
+    let env = TypeEnv::new();
+    let types = vec![
+        Null, Bool, Nat, Int, Nat8, Nat16, Nat32, Nat64, Int8, Int16, Int32, Int64, Text, Reserved,
+    ];
+    round_trip(&env, &types);
+}
+
+#[test]
+fn random_recursive() {
+    let env = parse_env(
+        r#"
+type List = opt record { head: int; tail: List };
+type Tree = variant { leaf: int; node: record { left: Tree; right: Tree } };
+type A = record { a: opt A; b: vec A; c: variant { x; y: A } };
+type Profile = record { name: text; id: principal; tags: vec text; age: opt nat8 };
+"#,
+    );
+    let types: Vec<_> = ["List", "Tree", "A", "Profile"]
+        .iter()
+        .map(|id| Type::Var(id.to_string()))
+        .collect();
+    round_trip(&env, &types);
+}
+
+#[test]
+fn random_bounds() {
+    let env = parse_env("type List = opt record { head: int; tail: List };");
+    let ty = Type::Var("List".to_string());
+    let config = GenConfig::new().with_depth(0);
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..10 {
+        let v = random_value(&mut rng, &env, &ty, &config).unwrap();
+        assert_eq!(v, IDLValue::None);
+    }
+    let config = GenConfig::new().with_size(3);
+    let ty = Type::Vec(Box::new(Type::Text));
+    for _ in 0..10 {
+        match random_value(&mut rng, &env, &ty, &config).unwrap() {
+            IDLValue::Vec(vs) => {
+                assert!(vs.len() <= 3);
+                for v in vs {
+                    match v {
+                        IDLValue::Text(s) => assert!(s.len() <= 3),
+                        _ => panic!("expect text"),
+                    }
+                }
+            }
+            _ => panic!("expect vec"),
+        }
+    }
+}
+
+#[test]
+fn random_uninhabited() {
+    let env = parse_env("type E = record { a: E };");
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = GenConfig::new();
+    assert!(random_value(&mut rng, &env, &Type::Empty, &config).is_err());
+    let ty = Type::Opt(Box::new(Type::Var("E".to_string())));
+    assert_eq!(
+        random_value(&mut rng, &env, &ty, &config).unwrap(),
+        IDLValue::None
+    );
+    let ty = Type::Variant(vec![]);
+    assert!(random_value(&mut rng, &env, &ty, &config).is_err());
+    // A record that directly contains itself
+    let env = parse_env("type t = record { t };");
+    let ty = Type::Var("t".to_string());
+    assert!(random_value(&mut rng, &env, &ty, &config).is_err());
+    let ty = Type::Vec(Box::new(Type::Var("t".to_string())));
+    assert_eq!(
+        random_value(&mut rng, &env, &ty, &config).unwrap(),
+        IDLValue::Vec(vec![])
+    );
+}
@@ -310,6 +310,19 @@ fn test_vector() {
     all_check([[[[()]]]], "4449444c046d016d026d036d7f010001010101");
     // Space bomb!
     all_check(vec![(); 1000], "4449444c016d7f0100e807");
+    // More records than entries in the type table
+    #[derive(PartialEq, Debug, Deserialize, CandidType)]
+    struct A {
+        foo: i32,
+        bar: Option<(bool, i8)>,
+    }
+    let records: Vec<_> = (0..10)
+        .map(|i| A {
+            foo: i,
+            bar: Some((i % 2 == 0, i as i8)),
+        })
+        .collect();
+    test_decode(&encode(&records), &records);
 }
 
 #[test]
@@ -0,0 +1,150 @@
+use candid::service::{Dispatcher, RejectCode};
+use candid::{check_prog, CandidType, Decode, Deserialize, Encode, IDLProg, TypeEnv};
+
+fn dispatcher() -> Dispatcher {
+    let did_file = r#"
+type List = opt record { head: int; tail: List };
+type Profile = record { name: text; age: nat8 };
+service : {
+  len : (List) -> (nat64) query;
+  profile : (text) -> (Profile);
+  incr : () -> (nat64);
+  greet : (text) -> (text);
+}
+"#;
+    let mut env = TypeEnv::new();
+    let actor = check_prog(&mut env, &did_file.parse::<IDLProg>().unwrap())
+        .unwrap()
+        .unwrap();
+    Dispatcher::new(env, actor).unwrap()
+}
+
+#[derive(CandidType, Deserialize)]
+struct List {
+    head: candid::Int,
+    tail: Option<Box<List>>,
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq)]
+struct Profile {
+    name: String,
+    age: u8,
+}
+
+#[test]
+fn dispatch() {
+    let mut dispatcher = dispatcher();
+    let mut counter = 0u64;
+    dispatcher
+        .method("len", |(mut list,): (Option<Box<List>>,)| {
+            let mut len = 0u64;
+            while let Some(node) = list {
+                len += 1;
+                list = node.tail;
+            }
+            (len,)
+        })
+        .unwrap()
+        .method("incr", move |()| {
+            counter += 1;
+            (counter,)
+        })
+        .unwrap()
+        .try_method("profile", |(name,): (String,)| {
+            if name.is_empty() {
+                Err("empty name".to_string())
+            } else {
+                Ok((Profile { name, age: 42 },))
+            }
+        })
+        .unwrap();
+    assert_eq!(dispatcher.missing().unwrap(), ["greet"]);
+
+    let list = Some(Box::new(List {
+        head: 1.into(),
+        tail: Some(Box::new(List {
+            head: 2.into(),
+            tail: None,
+        })),
+    }));
+    let reply = dispatcher
+        .dispatch("len", &Encode!(&list).unwrap())
+        .unwrap();
+    assert_eq!(Decode!(&reply, u64).unwrap(), 2);
+
+    dispatcher.dispatch("incr", &Encode!().unwrap()).unwrap();
+    let reply = dispatcher.dispatch("incr", &Encode!().unwrap()).unwrap();
+    assert_eq!(Decode!(&reply, u64).unwrap(), 2);
+
+    let reply = dispatcher
+        .dispatch("profile", &Encode!(&"Ada").unwrap())
+        .unwrap();
+    assert_eq!(
+        Decode!(&reply, Profile).unwrap(),
+        Profile {
+            name: "Ada".to_string(),
+            age: 42
+        }
+    );
+    let reject = dispatcher
+        .dispatch("profile", &Encode!(&"").unwrap())
+        .unwrap_err();
+    assert_eq!(reject.code, RejectCode::HandlerReject);
+    assert_eq!(reject.message, "empty name");
+
+    let reject = dispatcher
+        .dispatch("profile", &Encode!(&42u8).unwrap())
+        .unwrap_err();
+    assert_eq!(reject.code, RejectCode::InvalidArguments);
+    let reject = dispatcher
+        .dispatch("greet", &Encode!(&"Ada").unwrap())
+        .unwrap_err();
+    assert_eq!(reject.code, RejectCode::MethodNotFound);
+}
+
+#[test]
+fn extra_opt_arguments() {
+    let mut dispatcher = dispatcher();
+    dispatcher
+        .method("greet", |(name, times): (String, Option<u8>)| {
+            (name.repeat(times.unwrap_or(1) as usize),)
+        })
+        .unwrap();
+    let reply = dispatcher
+        .dispatch("greet", &Encode!(&"a").unwrap())
+        .unwrap();
+    assert_eq!(Decode!(&reply, String).unwrap(), "a");
+    let reply = dispatcher
+        .dispatch("greet", &Encode!(&"a", &Some(3u8)).unwrap())
+        .unwrap();
+    assert_eq!(Decode!(&reply, String).unwrap(), "aaa");
+    // Extra arguments have to be nullable
+    let result = dispatcher.method("profile", |(name, _): (String, u8)| {
+        (Profile { name, age: 0 },)
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn check_signatures() {
+    let mut dispatcher = dispatcher();
+    // Results can be more specific, arguments can be more general
+    dispatcher
+        .method("greet", |(): ()| ("hello".to_string(),))
+        .unwrap();
+    let result = dispatcher.method("greet", |(): ()| ("hello".to_string(),));
+    assert!(result.is_err());
+    let result = dispatcher.method("incr", |(): ()| (1i64,));
+    assert!(result.is_err());
+    let result = dispatcher.method("profile", |(age,): (u8,)| {
+        (Profile {
+            name: String::new(),
+            age,
+        },)
+    });
+    assert!(result.is_err());
+    let result = dispatcher.method("profile", |(name,): (String,)| (name,));
+    assert!(result.is_err());
+    let result = dispatcher.method("unknown", |(): ()| ());
+    assert!(result.is_err());
+}
@@ -0,0 +1,120 @@
+use candid::stable::Schema;
+use candid::{CandidType, Deserialize};
+
+#[derive(CandidType, Deserialize, Debug, PartialEq)]
+struct Item {
+    id: u64,
+    name: String,
+    legacy: bool,
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq)]
+struct StateV1 {
+    items: Vec<Item>,
+    admin: String,
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq)]
+struct NewItem {
+    id: u64,
+    name: String,
+    tags: Option<Vec<String>>,
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq)]
+struct StateV2 {
+    items: Vec<NewItem>,
+    admin: String,
+    paused: Option<bool>,
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq)]
+struct StateV3 {
+    items: Vec<NewItem>,
+    admins: Vec<String>,
+}
+
+fn state_v1() -> StateV1 {
+    StateV1 {
+        items: vec![Item {
+            id: 1,
+            name: "a".to_string(),
+            legacy: true,
+        }],
+        admin: "root".to_string(),
+    }
+}
+
+#[test]
+fn subtype_upgrade() {
+    let bytes = Schema::<StateV1>::new(1).to_bytes(&state_v1()).unwrap();
+    let (state, report) = Schema::<StateV2>::new(2).from_bytes(&bytes).unwrap();
+    assert_eq!(
+        state,
+        StateV2 {
+            items: vec![NewItem {
+                id: 1,
+                name: "a".to_string(),
+                tags: None,
+            }],
+            admin: "root".to_string(),
+            paused: None,
+        }
+    );
+    assert_eq!(report.version, 1);
+    assert!(!report.migrated);
+    let paths = |paths: &[candid::parser::value::IDLPath]| -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    };
+    let legacy = candid::idl_hash("legacy");
+    assert_eq!(paths(&report.dropped), [format!(".items[*].{}", legacy)]);
+    assert_eq!(paths(&report.defaulted), [".items[*].tags", ".paused"]);
+
+    // Same type
+    let bytes = Schema::<StateV2>::new(2).to_bytes(&state).unwrap();
+    let (_, report) = Schema::<StateV2>::new(2).from_bytes(&bytes).unwrap();
+    assert!(report.dropped.is_empty() && report.defaulted.is_empty());
+}
+
+#[test]
+fn migrations() {
+    let schema = || {
+        Schema::<StateV3>::new(3).migration(1, |old: StateV2| StateV3 {
+            items: old.items,
+            admins: vec![old.admin],
+        })
+    };
+    // The migration reads the stored value as `StateV2`
+    let bytes = Schema::<StateV1>::new(1).to_bytes(&state_v1()).unwrap();
+    let (state, report) = schema().from_bytes(&bytes).unwrap();
+    assert_eq!(state.admins, ["root"]);
+    assert!(report.migrated);
+    assert_eq!(report.defaulted.len(), 2);
+
+    // Without a migration, the types are not compatible
+    let bytes = Schema::<StateV1>::new(2).to_bytes(&state_v1()).unwrap();
+    let err = schema().from_bytes(&bytes).unwrap_err();
+    assert!(err.to_string().contains("schema version 2"));
+}
+
+#[test]
+fn chunked_io() {
+    let schema = Schema::<StateV1>::new(1).with_chunk_size(3);
+    let mut bytes = Vec::new();
+    schema.write(&mut bytes, &state_v1()).unwrap();
+    assert_eq!(
+        bytes,
+        Schema::<StateV1>::new(1).to_bytes(&state_v1()).unwrap()
+    );
+    let (state, _) = schema.read(&bytes[..]).unwrap();
+    assert_eq!(state, state_v1());
+
+    // Truncated, corrupted or trailing bytes
+    assert!(schema.read(&bytes[..bytes.len() - 1]).is_err());
+    let mut corrupted = bytes.clone();
+    corrupted[0] = b'X';
+    assert!(schema.from_bytes(&corrupted).is_err());
+    let mut trailing = bytes;
+    trailing.push(0);
+    assert!(schema.from_bytes(&trailing).is_err());
+}
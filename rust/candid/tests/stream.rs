@@ -0,0 +1,104 @@
+use candid::de::IDLStream;
+use candid::{CandidType, Decode, Deserialize, Encode, IDLArgs, Int, Nat, Principal};
+use std::io::Read;
+
+#[derive(CandidType, Deserialize, Debug, PartialEq)]
+struct List {
+    head: Int,
+    tail: Option<Box<List>>,
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq)]
+enum Event {
+    Call { method: String, args: Vec<u8> },
+    Reply(Option<Nat>),
+    Reject,
+}
+
+fn messages() -> Vec<Vec<u8>> {
+    let list = List {
+        head: Int::from(-1),
+        tail: Some(Box::new(List {
+            head: Int::from(2),
+            tail: None,
+        })),
+    };
+    let principal = Principal::from_text("w7x7r-cok77-xa").unwrap();
+    let big = "123456789012345678901234567890".parse::<Nat>().unwrap();
+    vec![
+        Encode!().unwrap(),
+        Encode!(&list).unwrap(),
+        Encode!(
+            &Event::Call {
+                method: "greet".to_string(),
+                args: vec![1, 2, 3]
+            },
+            &Event::Reply(Some(big)),
+            &Event::Reject
+        )
+        .unwrap(),
+        Encode!(&principal, &(1u16, -2i64, 3.5f64), &vec![(); 1000]).unwrap(),
+        Encode!(&Some("text"), &true).unwrap(),
+    ]
+}
+
+// Returns at most one byte from each read.
+struct Trickle<'a>(&'a [u8]);
+
+impl<'a> Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() || self.0.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.0[0];
+        self.0 = &self.0[1..];
+        Ok(1)
+    }
+}
+
+#[test]
+fn read_stream() {
+    let messages = messages();
+    let log = messages.concat();
+    let mut stream = IDLStream::new(Trickle(&log));
+    for expected in messages.iter() {
+        let message = stream.next_message().unwrap().unwrap();
+        assert_eq!(message, &expected[..]);
+        IDLArgs::from_bytes(message).unwrap();
+    }
+    assert!(stream.next_message().unwrap().is_none());
+
+    let mut stream = IDLStream::new(&log[..]);
+    stream.next_message().unwrap();
+    let message = stream.next_message().unwrap().unwrap();
+    let list = Decode!(message, List).unwrap();
+    assert_eq!(list.head, Int::from(-1));
+}
+
+#[test]
+fn read_only_needed_bytes() {
+    let messages = messages();
+    let log = messages.concat();
+    let mut reader = &log[..];
+    let mut stream = IDLStream::new(&mut reader);
+    stream.next_message().unwrap();
+    stream.next_message().unwrap();
+    drop(stream);
+    assert_eq!(reader, &messages[2..].concat()[..]);
+}
+
+#[test]
+fn invalid_stream() {
+    let messages = messages();
+    let message = &messages[2];
+    let mut stream = IDLStream::new(&message[..message.len() - 1]);
+    assert!(stream.next_message().is_err());
+    let mut stream = IDLStream::new(&b"DIDX"[..]);
+    assert!(stream.next_message().is_err());
+    // An opt tag that is neither 0 nor 1
+    let bytes = hex::decode("4449444c016e7c010002").unwrap();
+    assert!(IDLStream::new(&bytes[..]).next_message().is_err());
+    // type T = record { T }
+    let bytes = hex::decode("4449444c016c0100000100").unwrap();
+    assert!(IDLStream::new(&bytes[..]).next_message().is_err());
+}
@@ -0,0 +1,88 @@
+use candid::types::subtype::{subtype, Gamma};
+use candid::{check_prog, IDLProg, TypeEnv};
+
+fn check(defs: &str, t1: &str, t2: &str) -> bool {
+    let mut env = TypeEnv::new();
+    check_prog(&mut env, &defs.parse::<IDLProg>().unwrap()).unwrap();
+    let t1 = env.find_type(t1).unwrap().clone();
+    let t2 = env.find_type(t2).unwrap().clone();
+    subtype(&mut Gamma::new(), &env, &t1, &t2).is_ok()
+}
+
+#[test]
+fn primitive() {
+    let defs = "type n = nat; type i = int; type r = reserved; type e = empty;";
+    assert!(check(defs, "n", "i"));
+    assert!(!check(defs, "i", "n"));
+    assert!(check(defs, "i", "r"));
+    assert!(check(defs, "e", "n"));
+    assert!(!check(defs, "r", "n"));
+}
+
+#[test]
+fn option() {
+    let defs = r#"
+type n = null;
+type nat = nat;
+type opt_nat = opt nat;
+type opt_int = opt int;
+type opt_opt = opt opt nat;
+"#;
+    assert!(check(defs, "n", "opt_nat"));
+    assert!(check(defs, "nat", "opt_int"));
+    assert!(check(defs, "opt_nat", "opt_int"));
+    assert!(!check(defs, "opt_int", "opt_nat"));
+    assert!(check(defs, "opt_nat", "opt_opt"));
+    assert!(!check(defs, "opt_opt", "opt_nat"));
+}
+
+#[test]
+fn record_variant() {
+    let defs = r#"
+type r1 = record { a : nat; b : text };
+type r2 = record { a : int };
+type r3 = record { a : int; c : opt nat };
+type r4 = record { a : int; c : nat };
+type v1 = variant { a; b : nat };
+type v2 = variant { a; b : int; c };
+"#;
+    assert!(check(defs, "r1", "r2"));
+    assert!(!check(defs, "r2", "r1"));
+    assert!(check(defs, "r1", "r3"));
+    assert!(!check(defs, "r1", "r4"));
+    assert!(check(defs, "v1", "v2"));
+    assert!(!check(defs, "v2", "v1"));
+}
+
+#[test]
+fn recursive() {
+    let defs = r#"
+type list = opt record { head : nat; tail : list };
+type list2 = opt record { head : int; tail : list2 };
+type stream = record { head : nat; next : func () -> (stream) query };
+type stream2 = record { head : int; next : func () -> (stream2) query };
+type stream3 = record { head : int; next : func () -> (stream3) };
+"#;
+    assert!(check(defs, "list", "list2"));
+    assert!(!check(defs, "list2", "list"));
+    assert!(check(defs, "stream", "stream2"));
+    assert!(!check(defs, "stream2", "stream"));
+    assert!(!check(defs, "stream", "stream3"));
+}
+
+#[test]
+fn function_service() {
+    let defs = r#"
+type f1 = func (int) -> (nat, bool);
+type f2 = func (nat, text) -> (int);
+type f3 = func (nat, text, opt nat) -> (int);
+type s1 = service { f : f1; g : () -> () };
+type s2 = service { f : f2 };
+"#;
+    assert!(check(defs, "f1", "f2"));
+    assert!(!check(defs, "f2", "f1"));
+    assert!(check(defs, "f2", "f3"));
+    assert!(check(defs, "f3", "f2"));
+    assert!(check(defs, "s1", "s2"));
+    assert!(!check(defs, "s2", "s1"));
+}
@@ -86,6 +86,53 @@ fn test_variant() {
     );
 }
 
+#[test]
+fn test_type_env() {
+    use candid::types::{
+        env_clear,
+        internal::{try_unroll, unroll},
+        TypeId,
+    };
+    #[derive(Debug, CandidType)]
+    struct Tree<T> {
+        value: T,
+        children: Vec<Tree<T>>,
+    }
+    assert_ne!(TypeId::of::<Tree<u8>>(), TypeId::of::<Tree<i8>>());
+    assert_eq!(TypeId::of::<Tree<u8>>(), TypeId::of::<Tree<u8>>());
+    let children = |t: Type| match t {
+        Type::Record(fs) => fs
+            .into_iter()
+            .find(|f| f.id.get_id() == candid::idl_hash("children")),
+        _ => None,
+    };
+    // Types derived in another thread can be unrolled here.
+    let t = std::thread::spawn(Tree::<u8>::ty).join().unwrap();
+    let tree = Type::Knot(TypeId::of::<Tree<u8>>());
+    assert_eq!(
+        children(t.clone()).unwrap().ty,
+        Type::Vec(Box::new(tree.clone()))
+    );
+    assert_eq!(unroll(&tree), t);
+    env_clear();
+    // Types are derived again, with the knots of the new generation
+    let new_tree = Type::Knot(TypeId::of::<Tree<u8>>());
+    assert_ne!(new_tree, tree);
+    let new_t = Tree::<u8>::ty();
+    assert_eq!(
+        children(new_t.clone()).unwrap().ty,
+        Type::Vec(Box::new(new_tree))
+    );
+    assert_ne!(Tree::<i8>::ty(), new_t);
+    // Knots from before still resolve, to the types derived again
+    assert_eq!(try_unroll(&tree).unwrap(), new_t);
+    let value = Tree {
+        value: 1u8,
+        children: vec![],
+    };
+    assert!(candid::ser::IDLBuilder::new().arg(&value).is_ok());
+}
+
 fn field(id: &str, ty: Type) -> candid::types::Field {
     candid::types::Field {
         id: Label::Named(id.to_string()),
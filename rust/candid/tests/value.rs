@@ -1,9 +1,9 @@
 use candid::parser::{
     types::IDLProg,
     typing::{check_prog, TypeEnv},
-    value::{IDLArgs, IDLField, IDLValue},
+    value::{IDLArgs, IDLField, IDLPath, IDLValue},
 };
-use candid::types::Label;
+use candid::types::{Label, Type};
 use candid::Decode;
 
 #[test]
@@ -121,6 +121,159 @@ fn test_variant() {
     test_decode(&encoded, &value);
 }
 
+#[test]
+fn test_select() {
+    let v: IDLValue = r#"record {
+  items = vec { record { owner = "a" }; record { owner = "b" } };
+  next = opt opt 5;
+  none = null;
+  status = variant { ok = 42 };
+}"#
+    .parse()
+    .unwrap();
+    let select = |path: &str| -> Vec<String> {
+        let path = path.parse::<IDLPath>().unwrap();
+        assert_eq!(
+            path.to_string(),
+            path.to_string().parse::<IDLPath>().unwrap().to_string()
+        );
+        v.select(&path)
+            .unwrap()
+            .into_iter()
+            .map(|v| v.to_string())
+            .collect()
+    };
+    assert_eq!(select(".items[1].owner"), ["\"b\""]);
+    assert_eq!(select(".items[*].owner"), ["\"a\"", "\"b\""]);
+    assert_eq!(select(".next.?.?"), ["5"]);
+    assert_eq!(select(".none.?"), Vec::<String>::new());
+    assert_eq!(select(".status#ok"), ["42"]);
+    assert_eq!(select(".status#err"), Vec::<String>::new());
+    assert_eq!(select("."), [v.to_string()]);
+    // Field names are compared by hash
+    let hash = candid::idl_hash("status");
+    assert_eq!(select(&format!(".{}#ok", hash)), ["42"]);
+    for path in &[".items[2]", ".missing", ".items.owner", ".next#ok"] {
+        assert!(v.select(&path.parse().unwrap()).is_err());
+    }
+    for path in &["items", ".items[", ".items[x]", ".", "#"] {
+        assert_eq!(path.parse::<IDLPath>().is_err(), *path != ".");
+    }
+}
+
+#[test]
+fn test_update() {
+    let mut args: IDLArgs = "(vec { opt 1; null; opt 3 }, vec { opt 4 })"
+        .parse()
+        .unwrap();
+    let path = "[*].?".parse::<IDLPath>().unwrap();
+    let n = args
+        .update(&path, |v| *v = IDLValue::Text(v.to_string()))
+        .unwrap();
+    assert_eq!(n, 3);
+    assert_eq!(
+        args.to_string(),
+        r#"(vec { opt "1"; null; opt "3"; }, vec { opt "4"; })"#
+    );
+    assert_eq!(args.select(&path).unwrap().len(), 3);
+}
+
+#[test]
+fn test_coerce() {
+    let candid = r#"
+type List = opt record { head: nat; tail: List };
+type IntList = opt record { head: int; tail: IntList };
+type Old = record { id: nat; name: text; tags: vec variant { a; b: nat8 } };
+type New = record {
+  id: int;
+  extra: opt nat;
+  tags: vec variant { a; b: opt nat8; c };
+  name: opt opt text;
+  version: reserved;
+};
+"#;
+    let ast = candid.parse::<IDLProg>().unwrap();
+    let mut env = TypeEnv::new();
+    check_prog(&mut env, &ast).unwrap();
+    let coerce = |value: &str, from: &str, to: &str| {
+        let from = [env.ast_to_type(&from.parse().unwrap()).unwrap()];
+        let to = [env.ast_to_type(&to.parse().unwrap()).unwrap()];
+        let bytes = IDLArgs::new(&[value.parse().unwrap()])
+            .to_bytes_with_types(&env, &from)
+            .unwrap();
+        let args = IDLArgs::from_bytes_with_types(&bytes, &env, &from).unwrap();
+        let res = args.coerce_types(&env, &from, &to)?;
+        // The result is a value of the target type
+        res.to_bytes_with_types(&env, &to).unwrap();
+        Ok::<_, candid::Error>(res.args[0].to_string())
+    };
+    assert_eq!(
+        coerce(
+            "record { id = 1; name = \"a\"; tags = vec { variant { a }; variant { b = 2 } } }",
+            "Old",
+            "New"
+        )
+        .unwrap(),
+        "record { id = 1; name = opt opt \"a\"; tags = vec { variant { a = null }; variant { b = opt 2 }; }; extra = null; version = reserved; }"
+    );
+    assert_eq!(
+        coerce(
+            "opt record { head = 1; tail = opt record { head = 2; tail = null } }",
+            "List",
+            "IntList"
+        )
+        .unwrap(),
+        "opt record { head = 1; tail = opt record { head = 2; tail = null; }; }"
+    );
+    // Values that do not fit under opt become null
+    assert_eq!(coerce("-1", "int", "opt nat").unwrap(), "null");
+    assert_eq!(coerce("opt \"a\"", "opt text", "opt nat").unwrap(), "null");
+    assert_eq!(coerce("5", "nat", "opt int").unwrap(), "opt 5");
+    // Incompatible values
+    assert!(coerce("-1", "int", "nat").is_err());
+    assert!(coerce("record { id = 1 }", "record { id: nat }", "New").is_err());
+    assert!(coerce("variant { c }", "variant { a; c }", "variant { a; b }").is_err());
+}
+
+#[test]
+fn test_coerce_types() {
+    let env = TypeEnv::new();
+    let args: IDLArgs = "(42, \"text\")".parse().unwrap();
+    let from = [Type::Nat, Type::Text];
+    let args = args.annotate_types(true, &env, &from).unwrap();
+    let to = [Type::Int];
+    assert_eq!(
+        args.coerce_types(&env, &from, &to).unwrap().to_string(),
+        "(42)"
+    );
+    let to = [Type::Int, Type::Text, Type::Opt(Box::new(Type::Nat))];
+    assert_eq!(
+        args.coerce_types(&env, &from, &to).unwrap().to_string(),
+        "(42, \"text\", null)"
+    );
+    let to = [Type::Int, Type::Text, Type::Nat];
+    assert!(args.coerce_types(&env, &from, &to).is_err());
+}
+
+#[test]
+fn test_coerce_references() {
+    let env = TypeEnv::new();
+    let ty = |s: &str| env.ast_to_type(&s.parse().unwrap()).unwrap();
+    let id = IDLValue::Principal(candid::Principal::from_text("w7x7r-cok77-xa").unwrap());
+    let coerce = |from: &str, to: &str| id.coerce_to(&env, &ty(from), &ty(to));
+    assert_eq!(
+        coerce("service { f : (nat) -> () }", "service {}").unwrap(),
+        id
+    );
+    assert_eq!(
+        coerce("func (nat) -> ()", "func (nat, opt text) -> ()").unwrap(),
+        id
+    );
+    assert!(coerce("service {}", "service { f : (nat) -> () }").is_err());
+    assert!(coerce("func (nat) -> ()", "func (text) -> ()").is_err());
+    assert!(coerce("service {}", "func () -> ()").is_err());
+}
+
 fn parse_check(str: &str) {
     let args = str.parse::<IDLArgs>().unwrap();
     let encoded = args.to_bytes().unwrap();
@@ -0,0 +1,387 @@
+use candid::bindings::candid::{pp_label, pp_ty, KEYWORDS};
+use candid::idl_hash;
+use candid::parser::format::format;
+use candid::parser::grammar::IDLProgParser;
+use candid::parser::lexer::{Lexer, LexicalError, Token};
+use candid::parser::typing::{check_prog, TypeEnv};
+use candid::parser::ParserError;
+use candid::pretty::LINE_WIDTH;
+use candid::types::Type;
+use std::cell::Cell;
+use std::collections::BTreeSet;
+
+/// Byte range in the source text.
+pub type Span = std::ops::Range<usize>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentKind {
+    /// Type name in `type name = ...`
+    TypeDef,
+    /// Type name used in a type expression
+    TypeRef,
+    /// Field name of a record or variant
+    Label,
+    /// Method name of the main service
+    Method,
+}
+
+#[derive(Debug, Clone)]
+pub struct Ident {
+    pub name: String,
+    pub kind: IdentKind,
+    pub span: Span,
+    /// The whole declaration for `TypeDef` and `Method`, otherwise the same as `span`.
+    pub decl: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Type,
+    Method,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The whole declaration
+    pub span: Span,
+    /// The name of the declaration
+    pub selection: Span,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Type,
+    Keyword,
+}
+
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+}
+
+/// The analysis result of a single Candid file.
+pub struct Analysis {
+    text: String,
+    idents: Vec<Ident>,
+    diagnostics: Vec<Diagnostic>,
+    env: TypeEnv,
+    actor: Option<Type>,
+    checked: bool,
+}
+
+type Spanned = (usize, Token, usize);
+
+impl Analysis {
+    pub fn new(text: &str) -> Self {
+        let tokens: Vec<Spanned> = Lexer::new(text).map_while(|t| t.ok()).collect();
+        let idents = classify(&tokens);
+        let mut res = Analysis {
+            text: text.to_string(),
+            idents,
+            diagnostics: Vec::new(),
+            env: TypeEnv::new(),
+            actor: None,
+            checked: false,
+        };
+        let last = Cell::new(0..0);
+        let lex_error = Cell::new(None);
+        let (lexed, docs) = Lexer::new(text).tokenize();
+        let lexer = lexed.into_iter().inspect(|t| match t {
+            Ok((l, _, r)) => last.set(*l..*r),
+            Err(_) => lex_error.set(Some(last.take().end)),
+        });
+        match IDLProgParser::new().parse(&docs, lexer) {
+            Err(e) => {
+                let span = match e {
+                    ParserError::InvalidToken { location } => location..location,
+                    ParserError::UnrecognizedEOF { location, .. } => location..location,
+                    ParserError::UnrecognizedToken {
+                        token: (l, _, r), ..
+                    } => l..r,
+                    ParserError::ExtraToken { token: (l, _, r) } => l..r,
+                    ParserError::User {
+                        error: LexicalError::NonTerminatedString(pos),
+                    } => pos..text.len(),
+                    ParserError::User { .. } => match lex_error.get() {
+                        Some(pos) => pos..pos,
+                        None => last.take(),
+                    },
+                };
+                res.diagnostics.push(Diagnostic {
+                    span,
+                    message: e.to_string(),
+                });
+            }
+            Ok(prog) => {
+                let mut env = TypeEnv::new();
+                match check_prog(&mut env, &prog) {
+                    Ok(actor) => {
+                        res.actor = actor;
+                        res.checked = true;
+                    }
+                    Err(e) => res.diagnostics = check_diagnostics(&e.to_string(), &res.idents),
+                }
+                res.env = env;
+            }
+        }
+        res
+    }
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+    pub fn idents(&self) -> &[Ident] {
+        &self.idents
+    }
+    /// Returns the identifier at `offset`. The end of an identifier also counts.
+    pub fn ident_at(&self, offset: usize) -> Option<&Ident> {
+        self.idents
+            .iter()
+            .find(|id| id.span.start <= offset && offset <= id.span.end)
+    }
+    /// Returns the name of the type definition for the type name at `offset`.
+    pub fn definition(&self, offset: usize) -> Option<Span> {
+        let name = self.type_name_at(offset)?;
+        self.idents
+            .iter()
+            .find(|id| id.kind == IdentKind::TypeDef && id.name == name)
+            .map(|id| id.span.clone())
+    }
+    /// Returns all uses of the type name at `offset`.
+    pub fn references(&self, offset: usize, include_declaration: bool) -> Vec<Span> {
+        let name = match self.type_name_at(offset) {
+            Some(name) => name,
+            None => return Vec::new(),
+        };
+        self.idents
+            .iter()
+            .filter(|id| id.name == name)
+            .filter(|id| {
+                id.kind == IdentKind::TypeRef
+                    || (include_declaration && id.kind == IdentKind::TypeDef)
+            })
+            .map(|id| id.span.clone())
+            .collect()
+    }
+    /// Returns the hover text in markdown for the identifier at `offset`.
+    pub fn hover(&self, offset: usize) -> Option<(Span, String)> {
+        let id = self.ident_at(offset)?;
+        let text = match id.kind {
+            IdentKind::TypeDef | IdentKind::TypeRef => {
+                let ty = self.env.find_type(&id.name).ok()?;
+                let mut text = format!("```candid\ntype {} = {}\n```", id.name, pretty(ty));
+                let expanded = self.env.rec_find_type(&id.name).ok()?;
+                if let Type::Var(_) = ty {
+                    text.push_str(&format!("\n\n```candid\n{}\n```", pretty(expanded)));
+                }
+                if let Type::Record(fs) | Type::Variant(fs) = expanded {
+                    if !fs.is_empty() {
+                        text.push_str("\n\nField ids:");
+                        for f in fs.iter() {
+                            let label = pp_label(&f.id).pretty(LINE_WIDTH).to_string();
+                            text.push_str(&format!("\n* `{}`: {}", label, f.id.get_id()));
+                        }
+                    }
+                }
+                text
+            }
+            IdentKind::Label => format!("field `{}`: {}", id.name, idl_hash(&id.name)),
+            IdentKind::Method => {
+                let ty = self.method_type(&id.name)?;
+                format!("```candid\n{} : {}\n```", id.name, pretty(ty))
+            }
+        };
+        Some((id.span.clone(), text))
+    }
+    /// Returns the type names defined in the file and the Candid keywords.
+    pub fn completions(&self) -> Vec<Completion> {
+        let types: BTreeSet<_> = self
+            .idents
+            .iter()
+            .filter(|id| id.kind == IdentKind::TypeDef)
+            .map(|id| id.name.clone())
+            .collect();
+        let types = types.into_iter().map(|label| Completion {
+            label,
+            kind: CompletionKind::Type,
+        });
+        let keywords = KEYWORDS.iter().map(|k| Completion {
+            label: k.to_string(),
+            kind: CompletionKind::Keyword,
+        });
+        types.chain(keywords).collect()
+    }
+    /// Returns the type definitions and methods in the order of appearance.
+    pub fn symbols(&self) -> Vec<Symbol> {
+        self.idents
+            .iter()
+            .filter_map(|id| {
+                let (kind, detail) = match id.kind {
+                    IdentKind::TypeDef => (
+                        SymbolKind::Type,
+                        self.env.find_type(&id.name).ok().map(pretty),
+                    ),
+                    IdentKind::Method => {
+                        (SymbolKind::Method, self.method_type(&id.name).map(pretty))
+                    }
+                    _ => return None,
+                };
+                Some(Symbol {
+                    name: id.name.clone(),
+                    kind,
+                    span: id.decl.clone(),
+                    selection: id.span.clone(),
+                    detail,
+                })
+            })
+            .collect()
+    }
+    /// Pretty prints the file with `parser::format::format`, which keeps the comments and the
+    /// order of the declarations. Files with syntax errors cannot be formatted.
+    pub fn format(&self) -> Option<String> {
+        format(&self.text, LINE_WIDTH).ok()
+    }
+    fn type_name_at(&self, offset: usize) -> Option<&str> {
+        let id = self.ident_at(offset)?;
+        match id.kind {
+            IdentKind::TypeDef | IdentKind::TypeRef => Some(&id.name),
+            _ => None,
+        }
+    }
+    fn method_type(&self, name: &str) -> Option<&Type> {
+        let actor = self.actor.as_ref()?;
+        let serv = self.env.as_service(actor).ok()?;
+        serv.iter().find(|(id, _)| id == name).map(|(_, ty)| ty)
+    }
+}
+
+fn pretty(ty: &Type) -> String {
+    pp_ty(ty).pretty(LINE_WIDTH).to_string()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scope {
+    Record,
+    Variant,
+    Service,
+    Other,
+}
+
+fn classify(tokens: &[Spanned]) -> Vec<Ident> {
+    let mut res = Vec::new();
+    let mut scopes = Vec::new();
+    for (i, (l, tok, r)) in tokens.iter().enumerate() {
+        let prev = if i > 0 { Some(&tokens[i - 1].1) } else { None };
+        let next = tokens.get(i + 1).map(|t| &t.1);
+        let scope = scopes.last().copied().unwrap_or(Scope::Other);
+        let (name, kind) = match tok {
+            Token::LBrace => {
+                // skip the optional name and colon in `service name : {`
+                let head = tokens[..i]
+                    .iter()
+                    .rev()
+                    .find(|t| !matches!(t.1, Token::Colon | Token::Id(_)));
+                scopes.push(match prev {
+                    Some(Token::Record) => Scope::Record,
+                    Some(Token::Variant) => Scope::Variant,
+                    Some(Token::Service) => Scope::Service,
+                    Some(Token::Colon) if matches!(head, Some((_, Token::Service, _))) => {
+                        Scope::Service
+                    }
+                    _ => Scope::Other,
+                });
+                continue;
+            }
+            Token::RBrace => {
+                scopes.pop();
+                continue;
+            }
+            Token::Id(name) | Token::Text(name) if next == Some(&Token::Colon) => {
+                if scope == Scope::Service {
+                    (name, IdentKind::Method)
+                } else {
+                    (name, IdentKind::Label)
+                }
+            }
+            Token::Id(name)
+                if scope == Scope::Variant
+                    && (prev == Some(&Token::LBrace) || prev == Some(&Token::Semi)) =>
+            {
+                (name, IdentKind::Label)
+            }
+            Token::Id(name) if prev == Some(&Token::Type) => (name, IdentKind::TypeDef),
+            Token::Id(name) => (name, IdentKind::TypeRef),
+            _ => continue,
+        };
+        let decl = match kind {
+            IdentKind::TypeDef => tokens[i - 1].0..decl_end(tokens, i),
+            IdentKind::Method => *l..decl_end(tokens, i),
+            _ => *l..*r,
+        };
+        res.push(Ident {
+            name: name.clone(),
+            kind,
+            span: *l..*r,
+            decl,
+        });
+    }
+    res
+}
+
+/// The end of the declaration starting at token `i`, including the terminating semicolon.
+fn decl_end(tokens: &[Spanned], i: usize) -> usize {
+    let mut depth = 0;
+    for j in i + 1..tokens.len() {
+        match tokens[j].1 {
+            Token::LBrace | Token::LParen => depth += 1,
+            Token::RBrace | Token::RParen if depth == 0 => return tokens[j - 1].2,
+            Token::RBrace | Token::RParen => depth -= 1,
+            Token::Semi if depth == 0 => return tokens[j].2,
+            _ => (),
+        }
+    }
+    tokens.last().map(|t| t.2).unwrap_or(0)
+}
+
+/// `check_prog` reports the first type error without a location. Find the identifiers
+/// mentioned by the error message.
+fn check_diagnostics(message: &str, idents: &[Ident]) -> Vec<Diagnostic> {
+    let find = |name: &str, kind: IdentKind| {
+        idents
+            .iter()
+            .filter(|id| id.kind == kind && id.name == name)
+            .map(|id| id.span.clone())
+            .collect::<Vec<_>>()
+    };
+    let spans = if let Some(name) = message.strip_prefix("Unbound type identifier ") {
+        find(name, IdentKind::TypeRef)
+    } else if let Some(name) = message.strip_prefix("duplicate binding for ") {
+        find(name, IdentKind::TypeDef).into_iter().skip(1).collect()
+    } else if let Some(name) = message.strip_suffix(" has cyclic type definition") {
+        find(name, IdentKind::TypeDef)
+    } else {
+        Vec::new()
+    };
+    if spans.is_empty() {
+        return vec![Diagnostic {
+            span: 0..0,
+            message: message.to_string(),
+        }];
+    }
+    spans
+        .into_iter()
+        .map(|span| Diagnostic {
+            span,
+            message: message.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,10 @@
+//! Language server for Candid interface description files.
+//!
+//! The analysis works on byte offsets of a single `.did` file, and the `candid-lsp` binary
+//! translates the results to the Language Server Protocol.
+
+pub mod analysis;
+pub mod line_index;
+
+pub use analysis::Analysis;
+pub use line_index::LineIndex;
@@ -0,0 +1,49 @@
+/// Converts between byte offsets and LSP positions (zero-based line and UTF-16 column).
+pub struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let mut line_starts = vec![0];
+        for (i, c) in text.char_indices() {
+            if c == '\n' {
+                line_starts.push(i + 1);
+            }
+        }
+        LineIndex { text, line_starts }
+    }
+    /// Returns `(line, column)` of a byte offset.
+    pub fn position(&self, offset: usize) -> (u32, u32) {
+        let offset = offset.min(self.text.len());
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        let start = self.line_starts[line];
+        let col: usize = self.text[start..offset].chars().map(char::len_utf16).sum();
+        (line as u32, col as u32)
+    }
+    /// Returns the byte offset of `(line, column)`, clamped to the end of the line.
+    pub fn offset(&self, line: u32, col: u32) -> usize {
+        let line = line as usize;
+        if line >= self.line_starts.len() {
+            return self.text.len();
+        }
+        let start = self.line_starts[line];
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .copied()
+            .unwrap_or(self.text.len());
+        let mut utf16 = 0;
+        for (i, c) in self.text[start..end].char_indices() {
+            if utf16 >= col as usize || c == '\n' {
+                return start + i;
+            }
+            utf16 += c.len_utf16();
+        }
+        end
+    }
+}
@@ -0,0 +1,250 @@
+use candid_lsp::analysis::{CompletionKind, Span, SymbolKind};
+use candid_lsp::{Analysis, LineIndex};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+    PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, Formatting, GotoDefinition, HoverRequest, References,
+    Request as _,
+};
+use lsp_types::*;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+impl Document {
+    fn new(text: String) -> Self {
+        let analysis = Analysis::new(&text);
+        Document { text, analysis }
+    }
+    fn offset(&self, pos: Position) -> usize {
+        LineIndex::new(&self.text).offset(pos.line, pos.character)
+    }
+    fn range(&self, span: &Span) -> Range {
+        let index = LineIndex::new(&self.text);
+        let (line, col) = index.position(span.start);
+        let start = Position::new(line, col);
+        let (line, col) = index.position(span.end);
+        let end = Position::new(line, col);
+        Range::new(start, end)
+    }
+}
+
+struct Server {
+    connection: Connection,
+    docs: HashMap<Url, Document>,
+}
+
+impl Server {
+    fn run(mut self) -> Result<()> {
+        while let Ok(msg) = self.connection.receiver.recv() {
+            match msg {
+                Message::Request(req) => {
+                    if self.connection.handle_shutdown(&req)? {
+                        return Ok(());
+                    }
+                    let resp = self.handle_request(req);
+                    self.connection.sender.send(Message::Response(resp))?;
+                }
+                Message::Notification(not) => self.handle_notification(not)?,
+                Message::Response(_) => (),
+            }
+        }
+        Ok(())
+    }
+    fn handle_request(&self, req: Request) -> Response {
+        let id = req.id.clone();
+        let result = match req.method.as_str() {
+            HoverRequest::METHOD => dispatch(req, |p| self.hover(p)),
+            GotoDefinition::METHOD => dispatch(req, |p| self.definition(p)),
+            References::METHOD => dispatch(req, |p| self.references(p)),
+            Completion::METHOD => dispatch(req, |p| self.completion(p)),
+            DocumentSymbolRequest::METHOD => dispatch(req, |p| self.symbols(p)),
+            Formatting::METHOD => dispatch(req, |p| self.format(p)),
+            method => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("unknown request {}", method),
+                )
+            }
+        };
+        match result {
+            Ok(value) => Response::new_ok(id, value),
+            Err(e) => Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string()),
+        }
+    }
+    fn handle_notification(&mut self, not: Notification) -> Result<()> {
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams = serde_json::from_value(not.params)?;
+                let uri = params.text_document.uri;
+                self.docs
+                    .insert(uri.clone(), Document::new(params.text_document.text));
+                self.publish_diagnostics(uri)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams = serde_json::from_value(not.params)?;
+                let uri = params.text_document.uri;
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.docs.insert(uri.clone(), Document::new(change.text));
+                    self.publish_diagnostics(uri)?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams = serde_json::from_value(not.params)?;
+                let uri = params.text_document.uri;
+                self.docs.remove(&uri);
+                self.send_diagnostics(uri, Vec::new())?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+    fn publish_diagnostics(&self, uri: Url) -> Result<()> {
+        let doc = &self.docs[&uri];
+        let diagnostics = doc
+            .analysis
+            .diagnostics()
+            .iter()
+            .map(|d| Diagnostic {
+                range: doc.range(&d.span),
+                severity: Some(DiagnosticSeverity::Error),
+                source: Some("candid".to_string()),
+                message: d.message.clone(),
+                ..Default::default()
+            })
+            .collect();
+        self.send_diagnostics(uri, diagnostics)
+    }
+    fn send_diagnostics(&self, uri: Url, diagnostics: Vec<Diagnostic>) -> Result<()> {
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        };
+        let not = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection.sender.send(Message::Notification(not))?;
+        Ok(())
+    }
+    fn doc_at(&self, params: &TextDocumentPositionParams) -> Option<(&Document, usize)> {
+        let doc = self.docs.get(&params.text_document.uri)?;
+        Some((doc, doc.offset(params.position)))
+    }
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let (doc, offset) = self.doc_at(&params.text_document_position_params)?;
+        let (span, text) = doc.analysis.hover(offset)?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: text,
+            }),
+            range: Some(doc.range(&span)),
+        })
+    }
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let params = params.text_document_position_params;
+        let (doc, offset) = self.doc_at(&params)?;
+        let span = doc.analysis.definition(offset)?;
+        let uri = params.text_document.uri;
+        Some(Location::new(uri, doc.range(&span)).into())
+    }
+    fn references(&self, params: ReferenceParams) -> Option<Vec<Location>> {
+        let (doc, offset) = self.doc_at(&params.text_document_position)?;
+        let uri = &params.text_document_position.text_document.uri;
+        let include_declaration = params.context.include_declaration;
+        let locations = doc
+            .analysis
+            .references(offset, include_declaration)
+            .iter()
+            .map(|span| Location::new(uri.clone(), doc.range(span)))
+            .collect();
+        Some(locations)
+    }
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let doc = self
+            .docs
+            .get(&params.text_document_position.text_document.uri)?;
+        let items: Vec<_> = doc
+            .analysis
+            .completions()
+            .into_iter()
+            .map(|c| CompletionItem {
+                label: c.label,
+                kind: Some(match c.kind {
+                    CompletionKind::Type => CompletionItemKind::Struct,
+                    CompletionKind::Keyword => CompletionItemKind::Keyword,
+                }),
+                ..Default::default()
+            })
+            .collect();
+        Some(items.into())
+    }
+    #[allow(deprecated)]
+    fn symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let doc = self.docs.get(&params.text_document.uri)?;
+        let symbols: Vec<_> = doc
+            .analysis
+            .symbols()
+            .into_iter()
+            .map(|s| DocumentSymbol {
+                name: s.name,
+                detail: s.detail,
+                kind: match s.kind {
+                    SymbolKind::Type => lsp_types::SymbolKind::Struct,
+                    SymbolKind::Method => lsp_types::SymbolKind::Method,
+                },
+                deprecated: None,
+                range: doc.range(&s.span),
+                selection_range: doc.range(&s.selection),
+                children: None,
+            })
+            .collect();
+        Some(symbols.into())
+    }
+    fn format(&self, params: DocumentFormattingParams) -> Option<Vec<TextEdit>> {
+        let doc = self.docs.get(&params.text_document.uri)?;
+        let text = doc.analysis.format()?;
+        let range = doc.range(&(0..doc.text.len()));
+        Some(vec![TextEdit::new(range, text)])
+    }
+}
+
+fn dispatch<P, R>(req: Request, f: impl FnOnce(P) -> R) -> Result<serde_json::Value>
+where
+    P: DeserializeOwned,
+    R: serde::Serialize,
+{
+    let params = serde_json::from_value(req.params)?;
+    Ok(serde_json::to_value(f(params))?)
+}
+
+fn main() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::Full)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+    let server = Server {
+        connection,
+        docs: HashMap::new(),
+    };
+    server.run()?;
+    io_threads.join()?;
+    Ok(())
+}
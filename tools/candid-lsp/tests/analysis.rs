@@ -0,0 +1,159 @@
+use candid_lsp::analysis::{CompletionKind, IdentKind, SymbolKind};
+use candid_lsp::{Analysis, LineIndex};
+
+const PROG: &str = r#"// A linked list
+type List = opt record { head: int; tail: List };
+type Color = variant { red; green; List };
+type Alias = List;
+service : {
+  "get": (nat) -> (List) query;
+  set: (List, Color) -> ();
+}
+"#;
+
+fn offset(text: &str, pat: &str, nth: usize) -> usize {
+    text.match_indices(pat).nth(nth).unwrap().0
+}
+
+#[test]
+fn idents() {
+    let a = Analysis::new(PROG);
+    assert!(a.diagnostics().is_empty());
+    let kinds: Vec<_> = a
+        .idents()
+        .iter()
+        .map(|id| (id.name.as_str(), id.kind))
+        .collect();
+    use IdentKind::*;
+    assert_eq!(
+        kinds,
+        vec![
+            ("List", TypeDef),
+            ("head", Label),
+            ("int", TypeRef),
+            ("tail", Label),
+            ("List", TypeRef),
+            ("Color", TypeDef),
+            ("red", Label),
+            ("green", Label),
+            ("List", Label),
+            ("Alias", TypeDef),
+            ("List", TypeRef),
+            ("get", Method),
+            ("nat", TypeRef),
+            ("List", TypeRef),
+            ("set", Method),
+            ("List", TypeRef),
+            ("Color", TypeRef),
+        ]
+    );
+}
+
+#[test]
+fn definition_and_references() {
+    let a = Analysis::new(PROG);
+    let def = offset(PROG, "List", 0);
+    let use_site = offset(PROG, "List", 1) + 2;
+    assert_eq!(a.definition(use_site), Some(def..def + 4));
+    let refs = a.references(def, false);
+    assert_eq!(refs.len(), 4);
+    assert!(!refs.contains(&(def..def + 4)));
+    // variant label with the same name is not a reference
+    let label = offset(PROG, "List", 2);
+    assert!(!refs.contains(&(label..label + 4)));
+    assert_eq!(a.references(def, true).len(), 5);
+    assert_eq!(a.definition(offset(PROG, "head", 0)), None);
+}
+
+#[test]
+fn hover() {
+    let a = Analysis::new(PROG);
+    let (_, text) = a.hover(offset(PROG, "Color", 0)).unwrap();
+    assert!(text.contains("type Color = variant { red; List; green }"));
+    assert!(text.contains("* `red`: 5691729"));
+    let (_, text) = a.hover(offset(PROG, "Alias", 0)).unwrap();
+    assert!(text.contains("type Alias = List"));
+    assert!(text.contains("opt record { head : int; tail : List }"));
+    let (_, text) = a.hover(offset(PROG, "head", 0)).unwrap();
+    assert_eq!(text, "field `head`: 1158359328");
+    let (_, text) = a.hover(offset(PROG, "set", 0)).unwrap();
+    assert!(text.contains("set : func (List, Color) -> ()"));
+}
+
+#[test]
+fn symbols_and_completions() {
+    let a = Analysis::new(PROG);
+    let symbols: Vec<_> = a
+        .symbols()
+        .into_iter()
+        .map(|s| (s.name, s.kind, &PROG[s.span]))
+        .collect();
+    assert_eq!(symbols[0].0, "List");
+    assert_eq!(symbols[0].1, SymbolKind::Type);
+    assert_eq!(
+        symbols[0].2,
+        "type List = opt record { head: int; tail: List };"
+    );
+    assert_eq!(symbols[3].0, "get");
+    assert_eq!(symbols[3].1, SymbolKind::Method);
+    assert_eq!(symbols[4].2, "set: (List, Color) -> ();");
+    let completions = a.completions();
+    let types: Vec<_> = completions
+        .iter()
+        .filter(|c| c.kind == CompletionKind::Type)
+        .map(|c| c.label.as_str())
+        .collect();
+    assert_eq!(types, vec!["Alias", "Color", "List"]);
+    assert!(completions.iter().any(|c| c.label == "record"));
+}
+
+#[test]
+fn diagnostics() {
+    let text = "type A = record { a: B };\nservice : {}";
+    let a = Analysis::new(text);
+    let d = &a.diagnostics()[0];
+    assert_eq!(&text[d.span.clone()], "B");
+    assert_eq!(d.message, "Unbound type identifier B");
+
+    let text = "type A = nat;\ntype A = int;";
+    let a = Analysis::new(text);
+    let d = &a.diagnostics()[0];
+    assert_eq!(d.span, 19..20);
+
+    let text = "type A = record { a: nat };\ntype B = vec A A;";
+    let a = Analysis::new(text);
+    let d = &a.diagnostics()[0];
+    assert_eq!(&text[d.span.clone()], "A");
+    assert_eq!(d.span.start, 43);
+
+    let text = "type A = \"text";
+    let a = Analysis::new(text);
+    assert_eq!(a.diagnostics()[0].span, 9..14);
+}
+
+#[test]
+fn format() {
+    let a = Analysis::new(PROG);
+    let res = a.format().unwrap();
+    assert!(res.starts_with("// A linked list\ntype List = "));
+    assert!(Analysis::new(&res).diagnostics().is_empty());
+    assert_eq!(Analysis::new(&res).format().unwrap(), res);
+    let res = Analysis::new("/// doc\ntype A=nat; // nat\n")
+        .format()
+        .unwrap();
+    assert_eq!(res, "/// doc\ntype A = nat; // nat\n");
+    assert!(Analysis::new("type A = ").format().is_none());
+}
+
+#[test]
+fn line_index() {
+    let text = "ab\n\u{1F600}c\nd";
+    let index = LineIndex::new(text);
+    assert_eq!(index.position(0), (0, 0));
+    assert_eq!(index.position(3), (1, 0));
+    assert_eq!(index.position(7), (1, 2));
+    assert_eq!(index.position(text.len()), (2, 1));
+    assert_eq!(index.offset(1, 2), 7);
+    assert_eq!(index.offset(0, 10), 2);
+    assert_eq!(index.offset(5, 0), text.len());
+}
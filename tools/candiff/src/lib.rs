@@ -2,11 +2,15 @@ use log::error;
 
 use std::rc::Rc;
 
+pub mod types;
+
 pub use ::candid::parser::value::IDLField as Field;
 pub use ::candid::parser::value::IDLValue as Value;
 
 pub use ::candid::types::{Field as TypeField, Label, Type};
 pub use ::candid::Nat;
+pub use ::candid::TypeEnv;
+use ::candid::{Error, Result};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum VecEdit<R> {
@@ -130,100 +134,423 @@ pub mod pretty {
     }
 }
 
+/// Parse the output of [`pretty::value_edit`] back into an edit. Values in
+/// the edits are parsed without types, as by `IDLValue::from_str`.
+pub mod parse {
+    use super::*;
+    use candid::parser::lexer::{Lexer, Token};
+
+    struct Parser<'a> {
+        text: &'a str,
+        tokens: Vec<(usize, Token, usize)>,
+        pos: usize,
+    }
+
+    impl<'a> Parser<'a> {
+        fn next(&mut self) -> Result<Token> {
+            let tok = self
+                .tokens
+                .get(self.pos)
+                .map(|t| t.1.clone())
+                .ok_or_else(|| Error::msg("unexpected end of edit"))?;
+            self.pos += 1;
+            Ok(tok)
+        }
+        fn peek(&self) -> Option<&Token> {
+            self.tokens.get(self.pos).map(|t| &t.1)
+        }
+        fn expect(&mut self, expected: Token) -> Result<()> {
+            match self.next()? {
+                tok if tok == expected => Ok(()),
+                tok => Err(Error::msg(format!("expected {}, found {}", expected, tok))),
+            }
+        }
+        fn index(&mut self) -> Result<usize> {
+            match self.next()? {
+                Token::Number(n) => n
+                    .parse::<usize>()
+                    .map_err(|_| Error::msg(format!("invalid index {}", n))),
+                tok => Err(Error::msg(format!("expected an index, found {}", tok))),
+            }
+        }
+        fn label(&mut self) -> Result<Label> {
+            match self.next()? {
+                Token::Number(n) => n
+                    .parse::<u32>()
+                    .map(Label::Id)
+                    .map_err(|_| Error::msg(format!("invalid field id {}", n))),
+                Token::Id(id) | Token::Text(id) => Ok(Label::Named(id)),
+                tok => Err(Error::msg(format!("expected a label, found {}", tok))),
+            }
+        }
+        // The value up to the closing brace, which is consumed. The pretty
+        // printer writes `opt { v }`, where the value grammar expects `opt v`,
+        // so the braces of options are blanked out before parsing.
+        fn value(&mut self) -> Result<Value> {
+            let first = self.pos;
+            // the index of the matching `}` of each open brace
+            let mut open = vec![];
+            let mut opt_braces = vec![];
+            loop {
+                let (_, tok, _) = self
+                    .tokens
+                    .get(self.pos)
+                    .ok_or_else(|| Error::msg("unexpected end of edit"))?;
+                match tok {
+                    Token::LBrace => {
+                        let is_opt = self.pos > first && self.tokens[self.pos - 1].1 == Token::Opt;
+                        open.push((self.pos, is_opt));
+                    }
+                    Token::RBrace => match open.pop() {
+                        None => break,
+                        Some((i, true)) => opt_braces.extend_from_slice(&[i, self.pos]),
+                        Some(_) => (),
+                    },
+                    _ => (),
+                }
+                self.pos += 1;
+            }
+            let start = self.tokens[first].0;
+            let end = self.tokens[self.pos].0;
+            self.pos += 1;
+            let mut text = self.text[start..end].to_string();
+            for i in opt_braces {
+                let at = self.tokens[i].0 - start;
+                text.replace_range(at..at + 1, " ");
+            }
+            text.parse::<Value>()
+        }
+        // Edits terminated by `;`, up to the closing brace.
+        fn edits<T>(&mut self, item: impl Fn(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+            self.expect(Token::LBrace)?;
+            let mut edits = vec![];
+            while self.peek() != Some(&Token::RBrace) {
+                edits.push(item(self)?);
+                self.expect(Token::Semi)?;
+            }
+            self.expect(Token::RBrace)?;
+            Ok(edits)
+        }
+        fn value_edit(&mut self) -> Result<RcValueEdit> {
+            use ValueEdit::*;
+            let edit = match self.next()? {
+                Token::Id(id) if id == "skip" => Skip,
+                Token::Id(id) if id == "put" => {
+                    self.expect(Token::LBrace)?;
+                    Put(self.value()?)
+                }
+                Token::Opt => {
+                    self.expect(Token::LBrace)?;
+                    let edit = self.value_edit()?;
+                    self.expect(Token::RBrace)?;
+                    Opt(edit)
+                }
+                Token::Variant => {
+                    self.expect(Token::LBrace)?;
+                    let edit = self.value_edit()?;
+                    self.expect(Token::RBrace)?;
+                    Variant(edit)
+                }
+                Token::Vec => Vec(self.edits(Self::vec_edit)?),
+                Token::Record => Record(self.edits(Self::record_edit)?),
+                tok => return Err(Error::msg(format!("unexpected {}", tok))),
+            };
+            Ok(RcValueEdit(Rc::new(edit)))
+        }
+        fn vec_edit(&mut self) -> Result<VecEdit<RcValueEdit>> {
+            let kind = self.next()?;
+            self.expect(Token::LBrace)?;
+            let i = self.index()?;
+            let edit = match kind {
+                Token::Id(id) if id == "insert" => {
+                    return Ok(VecEdit::InsertValue(i, self.value()?))
+                }
+                Token::Id(id) if id == "edit" => VecEdit::EditValue(i, self.value_edit()?),
+                Token::Id(id) if id == "remove" => VecEdit::RemoveValue(i),
+                tok => return Err(Error::msg(format!("unexpected vector edit {}", tok))),
+            };
+            self.expect(Token::RBrace)?;
+            Ok(edit)
+        }
+        fn record_edit(&mut self) -> Result<RecordEdit<RcValueEdit>> {
+            match self.next()? {
+                Token::Id(id) if id == "drop" => Ok(RecordEdit::DropValue(self.label()?)),
+                Token::Id(id) if id == "edit" => {
+                    self.expect(Token::LBrace)?;
+                    let label = self.label()?;
+                    let edit = self.value_edit()?;
+                    self.expect(Token::RBrace)?;
+                    Ok(RecordEdit::EditValue(label, edit))
+                }
+                tok => Err(Error::msg(format!("unexpected record edit {}", tok))),
+            }
+        }
+    }
+
+    pub fn value_edit(text: &str) -> Result<RcValueEdit> {
+        let tokens = Lexer::new(text)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::msg(e.to_string()))?;
+        let mut parser = Parser {
+            text,
+            tokens,
+            pos: 0,
+        };
+        let edit = parser.value_edit()?;
+        match parser.peek() {
+            None => Ok(edit),
+            Some(tok) => Err(Error::msg(format!("unexpected {} after the edit", tok))),
+        }
+    }
+}
+
+impl std::str::FromStr for RcValueEdit {
+    type Err = Error;
+    fn from_str(str: &str) -> Result<Self> {
+        parse::value_edit(str)
+    }
+}
+
 /// Compare the values, with optional (common) type.
 pub fn value_diff(v1: &Value, v2: &Value, t: &Option<Type>) -> RcValueEdit {
-    RcValueEdit(Rc::new(value_diff_rec(v1, v2, t)))
+    value_diff_with_env(v1, v2, t, &TypeEnv::new())
+}
+
+/// Compare the values, with optional (common) type, whose `Var`s are bound in `env`.
+pub fn value_diff_with_env(v1: &Value, v2: &Value, t: &Option<Type>, env: &TypeEnv) -> RcValueEdit {
+    RcValueEdit(Rc::new(value_diff_rec(v1, v2, t.as_ref(), env)))
+}
+
+fn diff(v1: &Value, v2: &Value, t: Option<&Type>, env: &TypeEnv) -> RcValueEdit {
+    RcValueEdit(Rc::new(value_diff_rec(v1, v2, t, env)))
 }
 
+/// Chase `Var` through the environment; an unbound type is forgotten.
+fn resolve<'a>(t: Option<&'a Type>, env: &'a TypeEnv) -> Option<&'a Type> {
+    match t {
+        Some(Type::Var(id)) => match env.rec_find_type(id) {
+            Ok(t) => Some(t),
+            Err(e) => {
+                error!("{}", e);
+                None
+            }
+        },
+        t => t,
+    }
+}
+
+/// Compare the fields of two records. With field types, only the fields
+/// of the type are compared, as the others are ignored when decoding.
 pub fn record_diff(
     fs1: &[Field],
     fs2: &[Field],
-    _fts: Option<&[TypeField]>,
+    fts: Option<&[TypeField]>,
+    env: &TypeEnv,
 ) -> Vec<RecordEdit<RcValueEdit>> {
+    let find = |fs: &'_ [Field], id: &Label| fs.iter().position(|f| f.id == *id);
     let mut edits = vec![];
-    for f1 in fs1.iter() {
-        let mut found_f1 = false;
-        for f2 in fs2.iter() {
-            if f1.id == f2.id {
-                found_f1 = true;
-                let edit_f12 = value_diff(&f1.val, &f2.val, &Option::None); // to do -- give field type
-                if !value_edit_is_skip(&edit_f12) {
-                    edits.push(RecordEdit::EditValue(f1.id.clone(), edit_f12));
-                }
-                break;
+    let mut field_edit = |f1: Option<&Field>, f2: Option<&Field>, t: Option<&Type>| match (f1, f2) {
+        (Some(f1), Some(f2)) => {
+            let edit_f12 = diff(&f1.val, &f2.val, t, env);
+            if !value_edit_is_skip(&edit_f12) {
+                edits.push(RecordEdit::EditValue(f1.id.clone(), edit_f12));
             }
         }
-        if !found_f1 {
-            edits.push(RecordEdit::DropValue(f1.id.clone()))
+        (Some(f1), None) => edits.push(RecordEdit::DropValue(f1.id.clone())),
+        (None, Some(f2)) => edits.push(RecordEdit::EditValue(
+            f2.id.clone(),
+            RcValueEdit(Rc::new(ValueEdit::Put(f2.val.clone()))),
+        )),
+        (None, None) => (),
+    };
+    match fts {
+        Some(fts) => {
+            for ft in fts.iter() {
+                let f1 = find(fs1, &ft.id).map(|i| &fs1[i]);
+                let f2 = find(fs2, &ft.id).map(|i| &fs2[i]);
+                field_edit(f1, f2, Some(&ft.ty));
+            }
+        }
+        None => {
+            for f1 in fs1.iter() {
+                let f2 = find(fs2, &f1.id).map(|i| &fs2[i]);
+                field_edit(Some(f1), f2, None);
+            }
+            for f2 in fs2.iter() {
+                if find(fs1, &f2.id).is_none() {
+                    field_edit(None, Some(f2), None);
+                }
+            }
         }
     }
-    for f2 in fs2.iter() {
-        let mut found_f2 = false;
-        for f1 in fs1.iter() {
-            if f1.id == f2.id {
-                found_f2 = true;
-                break;
+    edits
+}
+
+/// The number of rounds after which `middle_snake` gives up, so that the cost of
+/// aligning very different vectors stays linear in their length.
+const MAX_ROUNDS: isize = 256;
+
+/// Align the vectors with the linear space variant of Myers' algorithm, and
+/// return the matching index pairs, in order. Two elements match when they are
+/// structurally equal. Parts that are too different to align are left out, so
+/// they are compared by position.
+fn align(v1: &[Value], v2: &[Value]) -> Vec<(usize, usize)> {
+    let mut matches = vec![];
+    align_rec(v1, v2, (0, v1.len()), (0, v2.len()), &mut matches);
+    matches
+}
+
+/// Align `v1[x0..x1]` with `v2[y0..y1]`, by splitting them at a middle snake.
+fn align_rec(
+    v1: &[Value],
+    v2: &[Value],
+    (mut x0, mut x1): (usize, usize),
+    (mut y0, mut y1): (usize, usize),
+    matches: &mut Vec<(usize, usize)>,
+) {
+    while x0 < x1 && y0 < y1 && v1[x0] == v2[y0] {
+        matches.push((x0, y0));
+        x0 += 1;
+        y0 += 1;
+    }
+    let mut suffix = 0;
+    while x0 < x1 && y0 < y1 && v1[x1 - 1] == v2[y1 - 1] {
+        x1 -= 1;
+        y1 -= 1;
+        suffix += 1;
+    }
+    if x0 < x1 && y0 < y1 {
+        let ((x, y), (u, w)) = match middle_snake(&v1[x0..x1], &v2[y0..y1]) {
+            Some(snake) => snake,
+            None => return,
+        };
+        align_rec(v1, v2, (x0, x0 + x), (y0, y0 + y), matches);
+        matches.extend((x..u).map(|i| (x0 + i, y0 + i - x + y)));
+        align_rec(v1, v2, (x0 + u, x1), (y0 + w, y1), matches);
+    }
+    matches.extend((0..suffix).map(|i| (x1 + i, y1 + i)));
+}
+
+/// Find the middle snake of an optimal edit path from `a` to `b`, by searching
+/// forward from the start and backward from the end at once. Returns the start
+/// and end of the snake, or `None` after `MAX_ROUNDS` rounds.
+fn middle_snake(a: &[Value], b: &[Value]) -> Option<((usize, usize), (usize, usize))> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = ((n + m + 1) / 2).min(MAX_ROUNDS);
+    let off = max + 1;
+    // The furthest reaching x of each diagonal k = x - y, forward, and backward
+    // from the end, where the diagonal of the backward k is delta - k.
+    let mut vf = vec![0isize; 2 * max as usize + 3];
+    let mut vb = vec![0isize; 2 * max as usize + 3];
+    let snake = |v: &[isize], d: isize, k: isize, eq: &dyn Fn(isize, isize) -> bool| {
+        let i = (off + k) as usize;
+        let x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+            v[i + 1]
+        } else {
+            v[i - 1] + 1
+        };
+        let (mut u, mut w) = (x, x - k);
+        while u < n && w < m && eq(u, w) {
+            u += 1;
+            w += 1;
+        }
+        ((x, x - k), (u, w))
+    };
+    let fwd = |x: isize, y: isize| a[x as usize] == b[y as usize];
+    let bwd = |x: isize, y: isize| a[(n - x - 1) as usize] == b[(m - y - 1) as usize];
+    let point = |x: isize, y: isize| (x as usize, y as usize);
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let ((x, y), (u, w)) = snake(&vf, d, k, &fwd);
+            vf[(off + k) as usize] = u;
+            let kb = delta - k;
+            if odd && kb.abs() < d && u + vb[(off + kb) as usize] >= n {
+                return Some((point(x, y), point(u, w)));
             }
         }
-        if !found_f2 {
-            edits.push(RecordEdit::EditValue(
-                f2.id.clone(),
-                RcValueEdit(Rc::new(ValueEdit::Put(f2.val.clone()))),
-            ))
+        for k in (-d..=d).step_by(2) {
+            let ((x, y), (u, w)) = snake(&vb, d, k, &bwd);
+            vb[(off + k) as usize] = u;
+            let kf = delta - k;
+            if !odd && kf.abs() <= d && u + vf[(off + kf) as usize] >= n {
+                return Some((point(n - u, m - w), point(n - x, m - y)));
+            }
         }
     }
-    edits
+    None
 }
 
-/// Simple algorithm for computing a naive edit
-///
-/// Limitations:
-///  - No alignment; over-uses in-place edits.
-///  - No removal edits used within the output (yet).
-///  - All insertions at the end (if any).
+/// Compute a minimal sequence of vector edits, from an alignment of the
+/// vectors. Between two aligned elements, removed and inserted elements
+/// are paired up as in-place edits.
 ///
-pub fn vec_diff_simple(v1: &[Value], v2: &[Value], ty: &Option<Type>) -> Vec<VecEdit<RcValueEdit>> {
-    let mut edits = vec![];
-    let prefix_len = v1.len().min(v2.len());
-    let ty = match ty {
+/// The positions of the edits are relative to the vector obtained by
+/// applying the previous edits, in order.
+pub fn vec_diff(
+    v1: &[Value],
+    v2: &[Value],
+    ty: Option<&Type>,
+    env: &TypeEnv,
+) -> Vec<VecEdit<RcValueEdit>> {
+    let ty = match resolve(ty, env) {
         None => None,
-        Some(Type::Vec(t)) => Some((**t).clone()),
+        Some(Type::Vec(t)) => Some(&**t),
         _ => {
             error!("invalid type");
             None
         }
     };
-    for i in 0..prefix_len {
-        let edit = value_diff(&v1[i], &v2[i], &ty);
-        if !value_edit_is_skip(&edit) {
-            edits.push(VecEdit::EditValue(i, edit))
+    let mut edits = vec![];
+    let (mut i, mut j, mut pos) = (0, 0, 0);
+    let end = std::iter::once((v1.len(), v2.len()));
+    for (mi, mj) in align(v1, v2).into_iter().chain(end) {
+        let (removed, inserted) = (mi - i, mj - j);
+        let edited = removed.min(inserted);
+        for k in 0..edited {
+            // The values may only differ where the type ignores them
+            let edit = diff(&v1[i + k], &v2[j + k], ty, env);
+            if !value_edit_is_skip(&edit) {
+                edits.push(VecEdit::EditValue(pos, edit));
+            }
+            pos += 1;
         }
-    }
-    if v1.len() > v2.len() {
-        for (i, v1i) in v1.iter().enumerate().skip(prefix_len) {
-            edits.push(VecEdit::InsertValue(i, v1i.clone()))
+        for _ in edited..removed {
+            edits.push(VecEdit::RemoveValue(pos));
         }
-    } else {
-        for (i, v2i) in v2.iter().enumerate().skip(prefix_len) {
-            edits.push(VecEdit::InsertValue(i, v2i.clone()))
+        for v in v2[j + edited..mj].iter() {
+            edits.push(VecEdit::InsertValue(pos, v.clone()));
+            pos += 1;
         }
+        // skip the aligned element
+        pos += 1;
+        i = mi + 1;
+        j = mj + 1;
     }
     edits
 }
 
-/// Compare the values, with optional (common) type.
-pub fn value_diff_rec(v1: &Value, v2: &Value, _t: &Option<Type>) -> ValueEdit<RcValueEdit> {
+/// Compare the values, with optional (common) type, whose `Var`s are bound in `env`.
+pub fn value_diff_rec(
+    v1: &Value,
+    v2: &Value,
+    t: Option<&Type>,
+    env: &TypeEnv,
+) -> ValueEdit<RcValueEdit> {
     use Value::*;
     use ValueEdit::{Put, Skip};
 
+    let t = resolve(t, env);
     match (v1, v2) {
+        // reserved values carry no information
+        _ if t == Some(&Type::Reserved) => Skip,
         (Opt(x), Opt(y)) => {
-            let d = value_diff(
-                &**x,
-                &**y,
-                // to do
-                &Option::None,
-            );
+            let t = match t {
+                Some(Type::Opt(t)) => Some(&**t),
+                _ => Option::None,
+            };
+            let d = diff(x, y, t, env);
             if value_edit_is_skip(&d) {
                 Skip
             } else {
@@ -232,7 +559,11 @@ pub fn value_diff_rec(v1: &Value, v2: &Value, _t: &Option<Type>) -> ValueEdit<Rc
         }
         (Variant(f1, _), Variant(f2, _)) => {
             if f1.id == f2.id {
-                let edit = value_diff(&f1.val, &f2.val, &Option::None);
+                let t = match t {
+                    Some(Type::Variant(fs)) => fs.iter().find(|f| f.id == f1.id).map(|f| &f.ty),
+                    _ => Option::None,
+                };
+                let edit = diff(&f1.val, &f2.val, t, env);
                 if value_edit_is_skip(&edit) {
                     Skip
                 } else {
@@ -243,7 +574,11 @@ pub fn value_diff_rec(v1: &Value, v2: &Value, _t: &Option<Type>) -> ValueEdit<Rc
             }
         }
         (Record(fs1), Record(fs2)) => {
-            let edits = record_diff(fs1, fs2, Option::None); // to do -- give field types
+            let fts = match t {
+                Some(Type::Record(fts)) => Some(fts.as_slice()),
+                _ => Option::None,
+            };
+            let edits = record_diff(fs1, fs2, fts, env);
             if edits.is_empty() {
                 Skip
             } else {
@@ -251,7 +586,7 @@ pub fn value_diff_rec(v1: &Value, v2: &Value, _t: &Option<Type>) -> ValueEdit<Rc
             }
         }
         (Vec(x), Vec(y)) => {
-            let edits = vec_diff_simple(x, y, &Option::None); // to do -- give type
+            let edits = vec_diff(x, y, t, env);
             if edits.is_empty() {
                 Skip
             } else {
@@ -361,3 +696,168 @@ pub fn value_edit_is_skip(edit: &RcValueEdit) -> bool {
         _ => false,
     }
 }
+
+fn mismatch(edit: &str, v: &Value) -> Error {
+    let kind = match v {
+        Value::Opt(_) | Value::None => "an option",
+        Value::Vec(_) => "a vector",
+        Value::Record(_) => "a record",
+        Value::Variant(..) => "a variant",
+        _ => "a primitive value",
+    };
+    Error::msg(format!("cannot apply a {} edit to {}", edit, kind))
+}
+
+fn find_field(fs: &[Field], id: &Label) -> Result<usize> {
+    fs.iter()
+        .position(|f| f.id == *id)
+        .ok_or_else(|| Error::msg(format!("field {} not found", id)))
+}
+
+fn apply_vec_edit(vs: &mut Vec<Value>, edit: &VecEdit<RcValueEdit>) -> Result<()> {
+    match edit {
+        VecEdit::InsertValue(i, v) if *i <= vs.len() => vs.insert(*i, v.clone()),
+        VecEdit::EditValue(i, e) if *i < vs.len() => vs[*i] = apply_edit(&vs[*i], e)?,
+        VecEdit::RemoveValue(i) if *i < vs.len() => {
+            vs.remove(*i);
+        }
+        _ => return Err(Error::msg("vector edit out of bounds")),
+    }
+    Ok(())
+}
+
+fn apply_record_edit(fs: &mut Vec<Field>, edit: &RecordEdit<RcValueEdit>) -> Result<()> {
+    match edit {
+        RecordEdit::EditValue(id, e) => match (find_field(fs, id), &*e.0) {
+            (Ok(i), _) => fs[i].val = apply_edit(&fs[i].val, e)?,
+            // a new field, kept in field id order
+            (Err(_), ValueEdit::Put(v)) => {
+                let i = fs
+                    .iter()
+                    .position(|f| f.id.get_id() > id.get_id())
+                    .unwrap_or(fs.len());
+                fs.insert(
+                    i,
+                    Field {
+                        id: id.clone(),
+                        val: v.clone(),
+                    },
+                );
+            }
+            (Err(e), _) => return Err(e),
+        },
+        RecordEdit::DropValue(id) => {
+            fs.remove(find_field(fs, id)?);
+        }
+    }
+    Ok(())
+}
+
+/// Apply the edit to the value. The edits of a vector or a record apply
+/// in order, each to the result of the previous ones, so that
+/// `apply_edit(v1, value_diff(v1, v2))` is `v2`.
+pub fn apply_edit(v: &Value, edit: &RcValueEdit) -> Result<Value> {
+    use ValueEdit::*;
+    match (&*edit.0, v) {
+        (Skip, _) => Ok(v.clone()),
+        (Put(w), _) => Ok(w.clone()),
+        (Opt(e), Value::Opt(x)) => Ok(Value::Opt(Box::new(apply_edit(x, e)?))),
+        (Variant(e), Value::Variant(f, idx)) => {
+            let f = Field {
+                id: f.id.clone(),
+                val: apply_edit(&f.val, e)?,
+            };
+            Ok(Value::Variant(Box::new(f), *idx))
+        }
+        (Vec(edits), Value::Vec(vs)) => {
+            let mut vs = vs.clone();
+            for e in edits.iter() {
+                apply_vec_edit(&mut vs, e)?;
+            }
+            Ok(Value::Vec(vs))
+        }
+        (Record(edits), Value::Record(fs)) => {
+            let mut fs = fs.clone();
+            for e in edits.iter() {
+                apply_record_edit(&mut fs, e)?;
+            }
+            Ok(Value::Record(fs))
+        }
+        (Opt(_), _) => Err(mismatch("opt", v)),
+        (Variant(_), _) => Err(mismatch("variant", v)),
+        (Vec(_), _) => Err(mismatch("vec", v)),
+        (Record(_), _) => Err(mismatch("record", v)),
+    }
+}
+
+/// Invert the edit, given the value it applies to: the result turns
+/// `apply_edit(v, edit)` back into `v`.
+pub fn invert(v: &Value, edit: &RcValueEdit) -> Result<RcValueEdit> {
+    use ValueEdit::*;
+    let inverse = match (&*edit.0, v) {
+        (Skip, _) => Skip,
+        (Put(_), _) => Put(v.clone()),
+        (Opt(e), Value::Opt(x)) => Opt(invert(x, e)?),
+        (Variant(e), Value::Variant(f, _)) => Variant(invert(&f.val, e)?),
+        (Vec(edits), Value::Vec(vs)) => {
+            let mut vs = vs.clone();
+            let mut inverse = vec![];
+            for e in edits.iter() {
+                let out_of_bounds = || Error::msg("vector edit out of bounds");
+                inverse.push(match e {
+                    VecEdit::InsertValue(i, _) => VecEdit::RemoveValue(*i),
+                    VecEdit::RemoveValue(i) => {
+                        VecEdit::InsertValue(*i, vs.get(*i).ok_or_else(out_of_bounds)?.clone())
+                    }
+                    VecEdit::EditValue(i, e) => {
+                        VecEdit::EditValue(*i, invert(vs.get(*i).ok_or_else(out_of_bounds)?, e)?)
+                    }
+                });
+                apply_vec_edit(&mut vs, e)?;
+            }
+            inverse.reverse();
+            Vec(inverse)
+        }
+        (Record(edits), Value::Record(fs)) => {
+            let mut fs = fs.clone();
+            let mut inverse = vec![];
+            for e in edits.iter() {
+                inverse.push(match e {
+                    RecordEdit::EditValue(id, e) => match find_field(&fs, id) {
+                        Ok(i) => RecordEdit::EditValue(id.clone(), invert(&fs[i].val, e)?),
+                        Err(_) => RecordEdit::DropValue(id.clone()),
+                    },
+                    RecordEdit::DropValue(id) => {
+                        let v = fs[find_field(&fs, id)?].val.clone();
+                        RecordEdit::EditValue(id.clone(), RcValueEdit(Rc::new(Put(v))))
+                    }
+                });
+                apply_record_edit(&mut fs, e)?;
+            }
+            inverse.reverse();
+            Record(inverse)
+        }
+        (Opt(_), _) => return Err(mismatch("opt", v)),
+        (Variant(_), _) => return Err(mismatch("variant", v)),
+        (Vec(_), _) => return Err(mismatch("vec", v)),
+        (Record(_), _) => return Err(mismatch("record", v)),
+    };
+    Ok(RcValueEdit(Rc::new(inverse)))
+}
+
+/// Compose two edits into one, which applies `e1` and then `e2`.
+pub fn compose(e1: &RcValueEdit, e2: &RcValueEdit) -> Result<RcValueEdit> {
+    use ValueEdit::*;
+    let edit = match (&*e1.0, &*e2.0) {
+        (Skip, _) => return Ok(e2.clone()),
+        (_, Skip) => return Ok(e1.clone()),
+        (_, Put(_)) => return Ok(e2.clone()),
+        (Put(v), _) => Put(apply_edit(v, e2)?),
+        (Opt(x), Opt(y)) => Opt(compose(x, y)?),
+        (Variant(x), Variant(y)) => Variant(compose(x, y)?),
+        (Vec(x), Vec(y)) => Vec(x.iter().chain(y.iter()).cloned().collect()),
+        (Record(x), Record(y)) => Record(x.iter().chain(y.iter()).cloned().collect()),
+        _ => return Err(Error::msg("cannot compose edits of different kinds")),
+    };
+    Ok(RcValueEdit(Rc::new(edit)))
+}
@@ -0,0 +1,430 @@
+//! Compare two versions of a service type, for API review.
+//!
+//! Each change is classified as breaking or not by the subtyping rules of
+//! Candid: results of the new service must be subtypes of the old results,
+//! and arguments of the old service must be subtypes of the new arguments.
+
+use candid::parser::types::FuncMode;
+use candid::types::subtype::{is_nullable, subtype, Gamma};
+use candid::types::{Field, Function, Type};
+use candid::{Error, Result, TypeEnv};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// The kind of a named part of a service type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Item {
+    Method,
+    Argument,
+    Result,
+    Field,
+    Case,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeKind {
+    Added(Item),
+    Removed(Item),
+    /// Modes of a method, `query`, `oneway` or `update`
+    ModeChanged {
+        old: String,
+        new: String,
+    },
+    /// Types that are not compared further, bound in the old and new environment
+    TypeChanged {
+        old: Type,
+        new: Type,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeChange {
+    /// Location of the change, such as `greet.arg0.name`
+    pub path: String,
+    pub kind: ChangeKind,
+    pub breaking: bool,
+}
+
+// Data flows from the service in results, and to the service in arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Direction {
+    Outbound,
+    Inbound,
+}
+
+impl Direction {
+    fn flip(self) -> Self {
+        match self {
+            Direction::Outbound => Direction::Inbound,
+            Direction::Inbound => Direction::Outbound,
+        }
+    }
+}
+
+struct Differ<'a> {
+    old_env: &'a TypeEnv,
+    new_env: &'a TypeEnv,
+    // Both environments, with their names prefixed, to check subtyping
+    env: TypeEnv,
+    gamma: Gamma,
+    // Pairs of named types being compared
+    visiting: HashSet<(Type, Type, Direction)>,
+    changes: Vec<TypeChange>,
+}
+
+const OLD: &str = "old/";
+const NEW: &str = "new/";
+
+fn rename(t: &Type, prefix: &str) -> Type {
+    use Type::*;
+    match t {
+        Var(id) => Var(format!("{}{}", prefix, id)),
+        Opt(t) => Opt(Box::new(rename(t, prefix))),
+        Vec(t) => Vec(Box::new(rename(t, prefix))),
+        Record(fs) => Record(rename_fields(fs, prefix)),
+        Variant(fs) => Variant(rename_fields(fs, prefix)),
+        Func(f) => Func(Function {
+            modes: f.modes.clone(),
+            args: f.args.iter().map(|t| rename(t, prefix)).collect(),
+            rets: f.rets.iter().map(|t| rename(t, prefix)).collect(),
+        }),
+        Service(ms) => Service(
+            ms.iter()
+                .map(|(id, t)| (id.clone(), rename(t, prefix)))
+                .collect(),
+        ),
+        t => t.clone(),
+    }
+}
+
+fn rename_fields(fs: &[Field], prefix: &str) -> Vec<Field> {
+    fs.iter()
+        .map(|f| Field {
+            id: f.id.clone(),
+            ty: rename(&f.ty, prefix),
+        })
+        .collect()
+}
+
+fn modes(f: &Function) -> String {
+    let mut modes: Vec<_> = f
+        .modes
+        .iter()
+        .map(|m| match m {
+            FuncMode::Query => "query",
+            FuncMode::Oneway => "oneway",
+        })
+        .collect();
+    if modes.is_empty() {
+        modes.push("update");
+    }
+    modes.sort_unstable();
+    modes.join(" ")
+}
+
+fn join(path: &str, item: &str) -> String {
+    if path.is_empty() {
+        item.to_string()
+    } else {
+        format!("{}.{}", path, item)
+    }
+}
+
+impl<'a> Differ<'a> {
+    fn push(&mut self, path: &str, kind: ChangeKind, breaking: bool) {
+        self.changes.push(TypeChange {
+            path: path.to_string(),
+            kind,
+            breaking,
+        });
+    }
+
+    // If the new type can replace the old one in the direction of the data.
+    fn compatible(&mut self, old: &Type, new: &Type, dir: Direction) -> bool {
+        let old = rename(old, OLD);
+        let new = rename(new, NEW);
+        let (t1, t2) = match dir {
+            Direction::Outbound => (new, old),
+            Direction::Inbound => (old, new),
+        };
+        subtype(&mut self.gamma, &self.env, &t1, &t2).is_ok()
+    }
+
+    fn resolve<'b>(env: &'b TypeEnv, t: &'b Type) -> &'b Type {
+        match t {
+            Type::Var(id) => env.rec_find_type(id).unwrap(),
+            t => t,
+        }
+    }
+
+    // Changes in named types are reported at each use, but not again inside their own
+    // definition.
+    fn ty(&mut self, path: &str, old: &Type, new: &Type, dir: Direction) {
+        if let (Type::Var(_), _) | (_, Type::Var(_)) = (old, new) {
+            let key = (old.clone(), new.clone(), dir);
+            if self.visiting.insert(key.clone()) {
+                self.structure(path, old, new, dir);
+                self.visiting.remove(&key);
+            }
+        } else {
+            self.structure(path, old, new, dir);
+        }
+    }
+
+    fn structure(&mut self, path: &str, old: &Type, new: &Type, dir: Direction) {
+        use Type::*;
+        match (
+            Self::resolve(self.old_env, old),
+            Self::resolve(self.new_env, new),
+        ) {
+            (Opt(t1), Opt(t2)) | (Vec(t1), Vec(t2)) => self.ty(path, t1, t2, dir),
+            (Record(fs1), Record(fs2)) => self.fields(path, fs1, fs2, Item::Field, dir),
+            (Variant(fs1), Variant(fs2)) => self.fields(path, fs1, fs2, Item::Case, dir),
+            (Func(f1), Func(f2)) => self.func(path, f1, f2, dir),
+            (Service(ms1), Service(ms2)) => self.service(path, ms1, ms2, dir),
+            (t1, t2) => {
+                if rename(t1, OLD) == rename(t2, NEW) {
+                    return;
+                }
+                let breaking = !self.compatible(old, new, dir);
+                let kind = ChangeKind::TypeChanged {
+                    old: old.clone(),
+                    new: new.clone(),
+                };
+                self.push(path, kind, breaking);
+            }
+        }
+    }
+
+    fn fields(&mut self, path: &str, fs1: &[Field], fs2: &[Field], item: Item, dir: Direction) {
+        let old: BTreeMap<_, _> = fs1.iter().map(|f| (f.id.get_id(), f)).collect();
+        let new: BTreeMap<_, _> = fs2.iter().map(|f| (f.id.get_id(), f)).collect();
+        for f in fs1.iter() {
+            let path = join(path, &f.id.to_string());
+            match new.get(&f.id.get_id()) {
+                Some(f2) => self.ty(&path, &f.ty, &f2.ty, dir),
+                None => {
+                    // Missing fields are read as null, and inbound cases are never sent.
+                    let breaking = match (item, dir) {
+                        (Item::Field, Direction::Outbound) => !is_nullable(self.old_env, &f.ty),
+                        (Item::Field, Direction::Inbound) => false,
+                        (_, Direction::Outbound) => false,
+                        (_, Direction::Inbound) => true,
+                    };
+                    self.push(&path, ChangeKind::Removed(item), breaking);
+                }
+            }
+        }
+        for f in fs2.iter().filter(|f| !old.contains_key(&f.id.get_id())) {
+            let breaking = match (item, dir) {
+                (Item::Field, Direction::Outbound) => false,
+                (Item::Field, Direction::Inbound) => !is_nullable(self.new_env, &f.ty),
+                (_, Direction::Outbound) => true,
+                (_, Direction::Inbound) => false,
+            };
+            let path = join(path, &f.id.to_string());
+            self.push(&path, ChangeKind::Added(item), breaking);
+        }
+    }
+
+    // Arguments and results are tuples, whose extra elements are ignored.
+    fn args(&mut self, path: &str, ts1: &[Type], ts2: &[Type], item: Item, dir: Direction) {
+        let prefix = match item {
+            Item::Argument => "arg",
+            _ => "ret",
+        };
+        for i in 0..ts1.len().max(ts2.len()) {
+            let path = join(path, &format!("{}{}", prefix, i));
+            match (ts1.get(i), ts2.get(i)) {
+                (Some(t1), Some(t2)) => self.ty(&path, t1, t2, dir),
+                (Some(t1), None) => {
+                    let breaking = dir == Direction::Outbound && !is_nullable(self.old_env, t1);
+                    self.push(&path, ChangeKind::Removed(item), breaking);
+                }
+                (None, Some(t2)) => {
+                    let breaking = dir == Direction::Inbound && !is_nullable(self.new_env, t2);
+                    self.push(&path, ChangeKind::Added(item), breaking);
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+    }
+
+    fn func(&mut self, path: &str, f1: &Function, f2: &Function, dir: Direction) {
+        let (old, new) = (modes(f1), modes(f2));
+        if old != new {
+            self.push(path, ChangeKind::ModeChanged { old, new }, true);
+        }
+        self.args(path, &f1.args, &f2.args, Item::Argument, dir.flip());
+        self.args(path, &f1.rets, &f2.rets, Item::Result, dir);
+    }
+
+    fn service(
+        &mut self,
+        path: &str,
+        ms1: &[(String, Type)],
+        ms2: &[(String, Type)],
+        dir: Direction,
+    ) {
+        let old: BTreeMap<_, _> = ms1.iter().map(|(id, t)| (id, t)).collect();
+        let new: BTreeMap<_, _> = ms2.iter().map(|(id, t)| (id, t)).collect();
+        // Methods are reported in the order of their names
+        let ids: BTreeSet<_> = old.keys().chain(new.keys()).collect();
+        for id in ids {
+            let path = join(path, id);
+            match (old.get(id), new.get(id)) {
+                (Some(t1), Some(t2)) => self.ty(&path, t1, t2, dir),
+                (Some(_), None) => {
+                    let breaking = dir == Direction::Outbound;
+                    self.push(&path, ChangeKind::Removed(Item::Method), breaking);
+                }
+                (None, Some(_)) => {
+                    let breaking = dir == Direction::Inbound;
+                    self.push(&path, ChangeKind::Added(Item::Method), breaking);
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+    }
+}
+
+/// Compare the old and new service types, whose `Var`s are bound in `old_env` and `new_env`.
+pub fn service_diff(
+    old_env: &TypeEnv,
+    old: &Type,
+    new_env: &TypeEnv,
+    new: &Type,
+) -> Result<Vec<TypeChange>> {
+    old_env.as_service(old)?;
+    new_env.as_service(new)?;
+    let mut env = TypeEnv::new();
+    for (prefix, e) in [(OLD, old_env), (NEW, new_env)].iter() {
+        for (id, t) in e.0.iter() {
+            env.0.insert(format!("{}{}", prefix, id), rename(t, prefix));
+        }
+    }
+    let mut differ = Differ {
+        old_env,
+        new_env,
+        env,
+        gamma: Gamma::new(),
+        visiting: HashSet::new(),
+        changes: Vec::new(),
+    };
+    differ.ty("", old, new, Direction::Outbound);
+    Ok(differ.changes)
+}
+
+/// Compare the services of two checked did files.
+pub fn actor_diff(
+    old_env: &TypeEnv,
+    old: &Option<Type>,
+    new_env: &TypeEnv,
+    new: &Option<Type>,
+) -> Result<Vec<TypeChange>> {
+    match (old, new) {
+        (Some(old), Some(new)) => service_diff(old_env, old, new_env, new),
+        (None, _) => Err(Error::msg("the old did file has no service")),
+        (_, None) => Err(Error::msg("the new did file has no service")),
+    }
+}
+
+/// Whether any of the changes breaks existing clients.
+pub fn is_breaking(changes: &[TypeChange]) -> bool {
+    changes.iter().any(|c| c.breaking)
+}
+
+impl std::fmt::Display for Item {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let item = match self {
+            Item::Method => "method",
+            Item::Argument => "argument",
+            Item::Result => "result",
+            Item::Field => "record field",
+            Item::Case => "variant case",
+        };
+        write!(f, "{}", item)
+    }
+}
+
+impl std::fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeKind::Added(item) => write!(f, "added {}", item),
+            ChangeKind::Removed(item) => write!(f, "removed {}", item),
+            ChangeKind::ModeChanged { old, new } => {
+                write!(f, "changed mode from {} to {}", old, new)
+            }
+            ChangeKind::TypeChanged { old, new } => {
+                write!(f, "changed type from {} to {}", old, new)
+            }
+        }
+    }
+}
+
+/// One change per line, such as `breaking: greet.arg0: changed type from nat to text`.
+pub fn pretty(changes: &[TypeChange]) -> String {
+    changes
+        .iter()
+        .map(|c| {
+            let class = if c.breaking { "breaking" } else { "compatible" };
+            format!("{}: {}: {}\n", class, c.path, c.kind)
+        })
+        .collect()
+}
+
+fn json_str(s: &str) -> String {
+    let mut res = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '"' => res.push_str("\\\""),
+            '\n' => res.push_str("\\n"),
+            c if c.is_control() => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+/// The changes as a JSON array of objects, with fields `path`, `change`, `breaking`,
+/// and `item` for additions and removals or `old` and `new` otherwise.
+pub fn to_json(changes: &[TypeChange]) -> String {
+    let items: Vec<_> = changes
+        .iter()
+        .map(|c| {
+            let mut fields = vec![("path", json_str(&c.path))];
+            match &c.kind {
+                ChangeKind::Added(item) | ChangeKind::Removed(item) => {
+                    let change = match c.kind {
+                        ChangeKind::Added(_) => "added",
+                        _ => "removed",
+                    };
+                    fields.push(("change", json_str(change)));
+                    fields.push(("item", json_str(&item.to_string())));
+                }
+                ChangeKind::ModeChanged { old, new } => {
+                    fields.push(("change", json_str("mode")));
+                    fields.push(("old", json_str(old)));
+                    fields.push(("new", json_str(new)));
+                }
+                ChangeKind::TypeChanged { old, new } => {
+                    fields.push(("change", json_str("type")));
+                    fields.push(("old", json_str(&old.to_string())));
+                    fields.push(("new", json_str(&new.to_string())));
+                }
+            }
+            fields.push(("breaking", c.breaking.to_string()));
+            let fields: Vec<_> = fields
+                .iter()
+                .map(|(k, v)| format!("    \"{}\": {}", k, v))
+                .collect();
+            format!("  {{\n{}\n  }}", fields.join(",\n"))
+        })
+        .collect();
+    if items.is_empty() {
+        "[]".to_string()
+    } else {
+        format!("[\n{}\n]", items.join(",\n"))
+    }
+}
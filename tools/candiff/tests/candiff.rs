@@ -272,4 +272,320 @@ mod diff {
             .stdout(predicate::eq(b"variant { put { 2 } }\n" as &[u8]))
             .success();
     }
+
+    #[test]
+    fn vec_remove_middle() {
+        let mut cmd = candiff();
+        cmd.arg("diff")
+            .arg("vec {1; 2; 3}")
+            .arg("vec {1; 3}")
+            .arg("-t vec nat");
+        cmd.assert()
+            .stdout(predicate::eq(b"vec { remove { 1 }; }\n" as &[u8]))
+            .success();
+    }
+
+    #[test]
+    fn vec_insert_front() {
+        let mut cmd = candiff();
+        cmd.arg("diff")
+            .arg("vec {1; 2}")
+            .arg("vec {0; 1; 2}")
+            .arg("-t vec nat");
+        cmd.assert()
+            .stdout(predicate::eq(b"vec { insert { 0 0 }; }\n" as &[u8]))
+            .success();
+    }
+
+    #[test]
+    fn vec_align() {
+        let mut cmd = candiff();
+        cmd.arg("diff")
+            .arg("vec {1; 2; 3; 4; 5}")
+            .arg("vec {0; 2; 3; 5; 6; 7}")
+            .arg("-t vec nat");
+        cmd.assert()
+            .stdout(predicate::eq(
+                b"vec { edit { 0 put { 0 } }; remove { 3 }; insert { 4 6 }; insert { 5 7 }; }\n"
+                    as &[u8],
+            ))
+            .success();
+    }
+}
+
+mod typed {
+    use candid::parser::value::IDLValue;
+    use candid::{check_prog, IDLProg, TypeEnv};
+
+    fn diff(prog: &str, ty: &str, v1: &str, v2: &str) -> String {
+        let mut env = TypeEnv::new();
+        check_prog(&mut env, &prog.parse::<IDLProg>().unwrap()).unwrap();
+        let ty = env.find_type(ty).unwrap().clone();
+        let v1 = v1.parse::<IDLValue>().unwrap();
+        let v2 = v2.parse::<IDLValue>().unwrap();
+        let edit = candiff::value_diff_with_env(&v1, &v2, &Some(ty), &env);
+        let doc = candiff::pretty::value_edit(&edit);
+        doc.pretty(80).to_string()
+    }
+
+    #[test]
+    fn var_field_type() {
+        let prog = "type t = vec opt r; type r = record { a : nat };";
+        // `b` is not in the type, so it is ignored
+        let edit = diff(
+            prog,
+            "t",
+            "vec { opt record { a = 1; b = 1 } }",
+            "vec { opt record { a = 1; b = 2 } }",
+        );
+        assert_eq!(edit, "skip");
+        let edit = diff(
+            prog,
+            "t",
+            "vec { opt record { a = 1; b = 1 } }",
+            "vec { opt record { a = 2; b = 2 } }",
+        );
+        assert_eq!(
+            edit,
+            "vec { edit { 0 opt { record { edit { a put { 2 } }; } } }; }"
+        );
+    }
+
+    #[test]
+    fn reserved() {
+        let prog = "type t = variant { a : reserved; b : nat };";
+        assert_eq!(
+            diff(prog, "t", "variant { a = 1 }", "variant { a = 2 }"),
+            "skip"
+        );
+        assert_eq!(
+            diff(prog, "t", "variant { a = 1 }", "variant { b = 2 }"),
+            "put { variant { b = 2 } }"
+        );
+    }
+}
+
+mod edit {
+    use candid::parser::random::{random_value, GenConfig};
+    use candid::parser::value::IDLValue;
+    use candid::{check_prog, IDLProg, TypeEnv};
+    use candiff::{apply_edit, compose, invert, value_diff_with_env, RcValueEdit};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn pretty(edit: &RcValueEdit) -> String {
+        let doc = candiff::pretty::value_edit(edit);
+        doc.pretty(80).to_string()
+    }
+
+    #[test]
+    fn apply() {
+        let v1 = "vec { record { a = 1; c = 3 }; record { a = 2 } }"
+            .parse::<IDLValue>()
+            .unwrap();
+        let edit = "vec { edit { 0 record { edit { b put { 2 } }; drop c; } }; remove { 1 }; }"
+            .parse::<RcValueEdit>()
+            .unwrap();
+        let v2 = apply_edit(&v1, &edit).unwrap();
+        assert_eq!(
+            v2,
+            "vec { record { a = 1; b = 2 } }"
+                .parse::<IDLValue>()
+                .unwrap()
+        );
+        let back = apply_edit(&v2, &invert(&v1, &edit).unwrap()).unwrap();
+        assert_eq!(back, v1);
+        let bad = "vec { remove { 2 }; }".parse::<RcValueEdit>().unwrap();
+        assert!(apply_edit(&v1, &bad).is_err());
+        let bad = "record { drop a; }".parse::<RcValueEdit>().unwrap();
+        assert!(apply_edit(&v1, &bad).is_err());
+    }
+
+    #[test]
+    fn parse_error() {
+        assert!("vec { remove { 1 } }".parse::<RcValueEdit>().is_err());
+        assert!("put { 1 } skip".parse::<RcValueEdit>().is_err());
+        assert!("opt { keep }".parse::<RcValueEdit>().is_err());
+    }
+
+    #[test]
+    fn properties() {
+        let prog = r#"
+type t = vec record {
+  a : nat;
+  b : opt vec int8;
+  c : variant { x : text; y : nat8 };
+  "d e" : opt record { nat16; bool };
+};"#;
+        let mut env = TypeEnv::new();
+        check_prog(&mut env, &prog.parse::<IDLProg>().unwrap()).unwrap();
+        let ty = env.find_type("t").unwrap().clone();
+        let config = GenConfig::new().with_size(8);
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..200 {
+            let vs: Vec<_> = (0..3)
+                .map(|_| random_value(&mut rng, &env, &ty, &config).unwrap())
+                .collect();
+            let ty = Some(ty.clone());
+            let d12 = value_diff_with_env(&vs[0], &vs[1], &ty, &env);
+            let d23 = value_diff_with_env(&vs[1], &vs[2], &ty, &env);
+            assert_eq!(apply_edit(&vs[0], &d12).unwrap(), vs[1]);
+            let inverse = invert(&vs[0], &d12).unwrap();
+            assert_eq!(apply_edit(&vs[1], &inverse).unwrap(), vs[0]);
+            let d13 = compose(&d12, &d23).unwrap();
+            assert_eq!(apply_edit(&vs[0], &d13).unwrap(), vs[2]);
+            for edit in [d12, inverse, d13].iter() {
+                let text = pretty(edit);
+                let parsed = text
+                    .parse::<RcValueEdit>()
+                    .unwrap_or_else(|e| panic!("{}\n{}", text, e));
+                assert_eq!(pretty(&parsed), text);
+            }
+        }
+    }
+}
+
+mod align {
+    use candid::parser::value::IDLValue;
+    use candid::{types::Type, TypeEnv};
+    use candiff::{apply_edit, value_diff, vec_diff, VecEdit};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn nats(ns: &[u8]) -> Vec<IDLValue> {
+        ns.iter().map(|n| IDLValue::Nat8(*n)).collect()
+    }
+
+    fn lcs(a: &[u8], b: &[u8]) -> usize {
+        let mut t = vec![vec![0; b.len() + 1]; a.len() + 1];
+        for i in 0..a.len() {
+            for j in 0..b.len() {
+                t[i + 1][j + 1] = if a[i] == b[j] {
+                    t[i][j] + 1
+                } else {
+                    t[i][j + 1].max(t[i + 1][j])
+                };
+            }
+        }
+        t[a.len()][b.len()]
+    }
+
+    #[test]
+    fn minimal() {
+        let ty = Type::Vec(Box::new(Type::Nat8));
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..500 {
+            let gen = |rng: &mut StdRng| -> Vec<u8> {
+                let len = rng.gen_range(0, 16);
+                (0..len).map(|_| rng.gen_range(0, 4)).collect()
+            };
+            let (a, b) = (gen(&mut rng), gen(&mut rng));
+            let edits = vec_diff(&nats(&a), &nats(&b), Some(&ty), &TypeEnv::new());
+            // The elements that are neither removed nor edited are aligned
+            let changed = edits
+                .iter()
+                .filter(|e| !matches!(e, VecEdit::InsertValue(..)))
+                .count();
+            assert_eq!(a.len() - changed, lcs(&a, &b), "{:?} {:?}", a, b);
+            let (v1, v2) = (IDLValue::Vec(nats(&a)), IDLValue::Vec(nats(&b)));
+            let edit = value_diff(&v1, &v2, &Some(ty.clone()));
+            assert_eq!(apply_edit(&v1, &edit).unwrap(), v2);
+        }
+    }
+
+    #[test]
+    fn large() {
+        let n = 20_000;
+        let v1 = IDLValue::Vec((0..n).map(IDLValue::Nat64).collect());
+        let v2 = IDLValue::Vec((n..2 * n).map(IDLValue::Nat64).collect());
+        let ty = Some(Type::Vec(Box::new(Type::Nat64)));
+        let edit = value_diff(&v1, &v2, &ty);
+        assert_eq!(apply_edit(&v1, &edit).unwrap(), v2);
+    }
+}
+
+mod types {
+    use candid::{check_prog, IDLProg, TypeEnv};
+    use candiff::types::{actor_diff, is_breaking, pretty, to_json};
+
+    fn diff(old: &str, new: &str) -> Vec<candiff::types::TypeChange> {
+        let mut old_env = TypeEnv::new();
+        let old_actor = check_prog(&mut old_env, &old.parse::<IDLProg>().unwrap()).unwrap();
+        let mut new_env = TypeEnv::new();
+        let new_actor = check_prog(&mut new_env, &new.parse::<IDLProg>().unwrap()).unwrap();
+        actor_diff(&old_env, &old_actor, &new_env, &new_actor).unwrap()
+    }
+
+    #[test]
+    fn methods() {
+        let old = r#"
+type user = record { name : text; age : nat; nick : opt text };
+type event = variant { login; logout };
+service : {
+  greet : (user) -> (text);
+  get : (nat) -> (user, event) query;
+  drop : (text) -> ();
+}"#;
+        let new = r#"
+type user = record { name : text; age : int; email : text };
+type event = variant { login; logout; expired };
+service : {
+  greet : (user, opt nat) -> (text, opt text);
+  get : (nat) -> (user, event);
+  ping : () -> () oneway;
+}"#;
+        let changes = diff(old, new);
+        assert!(is_breaking(&changes));
+        assert_eq!(
+            pretty(&changes),
+            r#"breaking: drop: removed method
+breaking: get: changed mode from query to update
+breaking: get.ret0.age: changed type from nat to int
+compatible: get.ret0.nick: removed record field
+compatible: get.ret0.email: added record field
+breaking: get.ret1.expired: added variant case
+compatible: greet.arg0.age: changed type from nat to int
+compatible: greet.arg0.nick: removed record field
+breaking: greet.arg0.email: added record field
+compatible: greet.arg1: added argument
+compatible: greet.ret1: added result
+compatible: ping: added method
+"#
+        );
+    }
+
+    #[test]
+    fn recursive() {
+        let old = r#"
+type list = opt record { head : nat; tail : list };
+service : { sum : (list) -> (nat); rev : (list) -> (list) }"#;
+        let new = r#"
+type list = opt record { head : int; tail : list };
+service : { sum : (list) -> (nat); rev : (list) -> (list) }"#;
+        let changes = diff(old, new);
+        assert_eq!(
+            pretty(&changes),
+            "compatible: rev.arg0.head: changed type from nat to int
+breaking: rev.ret0.head: changed type from nat to int
+compatible: sum.arg0.head: changed type from nat to int
+"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let old = "service : { f : (nat) -> () }";
+        let new = "service : { f : (int) -> () }";
+        assert_eq!(
+            to_json(&diff(old, new)),
+            r#"[
+  {
+    "path": "f.arg0",
+    "change": "type",
+    "old": "nat",
+    "new": "int",
+    "breaking": false
+  }
+]"#
+        );
+        assert_eq!(to_json(&diff(old, old)), "[]");
+    }
 }
@@ -1,9 +1,18 @@
-use candid::{check_prog, parser::types::IDLTypes, types::Type, Error, IDLArgs, IDLProg, TypeEnv};
+use candid::bindings::doc::Format;
+use candid::de::IDLStream;
+use candid::parser::infer::infer_type;
+use candid::parser::types::{DocComments, IDLTypes};
+use candid::parser::value::IDLPath;
+use candid::{check_prog, types::Type, Error, IDLArgs, IDLProg, TypeEnv};
 use exitfailure::ExitFailure;
+use std::io::BufRead;
 use std::path::{Path, PathBuf};
 use structopt::clap::AppSettings;
 use structopt::StructOpt;
 
+mod mock;
+mod repl;
+
 #[derive(StructOpt)]
 #[structopt(global_settings = &[AppSettings::ColoredHelp, AppSettings::DeriveDisplayOrder])]
 enum Command {
@@ -16,10 +25,33 @@ enum Command {
     Bind {
         /// Specifies did file for code generation
         input: PathBuf,
-        #[structopt(short, long, possible_values = &["js", "did"])]
+        #[structopt(short, long, possible_values = &["js", "did", "mo", "py", "go", "proto", "jsonschema", "openapi"])]
         /// Specifies target language
         target: String,
     },
+    /// Generate reference documentation for a service
+    Doc {
+        /// Specifies did file for documentation
+        input: PathBuf,
+        #[structopt(short, long, possible_values = &["md", "html"], default_value = "md")]
+        /// Specifies output format
+        format: Format,
+        #[structopt(long)]
+        /// Specifies the document title, defaults to the file name
+        title: Option<String>,
+    },
+    /// Format Candid files in place
+    Fmt {
+        #[structopt(required = true)]
+        /// Specifies did files to format
+        inputs: Vec<PathBuf>,
+        #[structopt(long)]
+        /// Checks if the files are formatted without writing them
+        check: bool,
+        #[structopt(long, default_value = "80")]
+        /// Specifies maximal line width
+        width: usize,
+    },
     /// Generate test suites for different languages
     Test {
         /// Specifies .test.did file for test suites generation
@@ -31,19 +63,47 @@ enum Command {
     /// Encode Candid value
     Encode {
         /// Specifies Candid textual format for encoding
-        args: IDLArgs,
+        #[structopt(parse(try_from_str = IDLArgs::parse_annotated))]
+        args: (IDLArgs, Vec<Option<Type>>),
         #[structopt(flatten)]
         annotate: TypeAnnotation,
         #[structopt(short, long)]
         /// Pretty-prints hex string
         pretty: bool,
+        #[structopt(long)]
+        /// Infers the narrowest number types that fit the values, instead of int
+        narrow: bool,
     },
     /// Decode Candid binary data
     Decode {
+        #[structopt(required_unless("stream"))]
         /// Specifies Candid binary data in hex string
-        blob: String,
+        blob: Option<String>,
         #[structopt(flatten)]
         annotate: TypeAnnotation,
+        #[structopt(long)]
+        /// Prints only the values at a path, such as `.items[2].owner`, `.?`, `#ok` or `[*]`
+        select: Option<IDLPath>,
+        #[structopt(long, conflicts_with("blob"))]
+        /// Decodes back-to-back messages from stdin, either binary or in hex lines
+        stream: bool,
+    },
+    /// Convert Candid binary data encoded for one method signature to another
+    Convert {
+        /// Specifies Candid binary data in hex string
+        blob: String,
+        #[structopt(long)]
+        /// Types of the data, such as `(nat, text)`, or a method specified in --defs option
+        from: String,
+        #[structopt(long)]
+        /// Types of the converted data, or a method specified in --defs option
+        to: String,
+        #[structopt(long)]
+        /// Converts the results of the methods instead of the arguments
+        results: bool,
+        #[structopt(short, long)]
+        /// Loads did file for --from and --to to reference type definitions
+        defs: Option<PathBuf>,
     },
     /// Diff two Candid values
     Diff {
@@ -52,6 +112,48 @@ enum Command {
         #[structopt(flatten)]
         annotate: TypeAnnotation,
     },
+    /// Compare the services of two did files, and classify the changes
+    DiffTypes {
+        /// Specifies the did file of the current service
+        old: PathBuf,
+        /// Specifies the did file of the upgraded service
+        new: PathBuf,
+        #[structopt(long)]
+        /// Prints the changes as JSON
+        json: bool,
+    },
+    /// Generate random Candid values
+    Random {
+        #[structopt(flatten)]
+        annotate: TypeAnnotation,
+        #[structopt(long, default_value = "5")]
+        /// Maximal nesting depth of the generated values
+        depth: usize,
+        #[structopt(long, default_value = "10")]
+        /// Maximal length of the generated vectors and texts
+        size: usize,
+        #[structopt(long)]
+        /// Seed for the random number generator
+        seed: Option<u64>,
+    },
+    /// Start an interactive shell with a loaded service
+    Repl {
+        #[structopt(short, long)]
+        /// Specifies did file with the service and type definitions
+        defs: PathBuf,
+    },
+    /// Serve a mock of a service over a local HTTP endpoint
+    Mock {
+        #[structopt(short, long)]
+        /// Specifies did file with the service and type definitions
+        defs: PathBuf,
+        #[structopt(long, default_value = "8000")]
+        /// Port of the endpoint on localhost
+        port: u16,
+        #[structopt(long)]
+        /// Seed for the random responses
+        seed: Option<u64>,
+    },
 }
 
 #[derive(StructOpt)]
@@ -83,6 +185,16 @@ impl TypeAnnotation {
         } else {
             None
         };
+        let types = self.get_types_in(&env, &actor, mode)?;
+        Ok((env, types))
+    }
+    /// Same as `get_types`, with the definitions already loaded in `env` and `actor`.
+    fn get_types_in(
+        &self,
+        env: &TypeEnv,
+        actor: &Option<Type>,
+        mode: Mode,
+    ) -> candid::Result<Vec<Type>> {
         match (&self.tys, &self.method) {
             (None, None) => Err(Error::msg("no type annotations")),
             (Some(tys), None) => {
@@ -90,28 +202,75 @@ impl TypeAnnotation {
                 for ty in tys.args.iter() {
                     types.push(env.ast_to_type(ty)?);
                 }
-                Ok((env, types))
+                Ok(types)
             }
             (None, Some(meth)) => {
                 let actor = actor
+                    .as_ref()
                     .ok_or_else(|| Error::msg("Cannot use --method with a non-service did file"))?;
-                let func = env.get_method(&actor, &meth)?;
+                let func = env.get_method(actor, meth)?;
                 let types = match mode {
                     Mode::Encode => &func.args,
                     Mode::Decode => &func.rets,
                 }
                 .clone();
-                Ok((env, types))
+                Ok(types)
             }
             _ => unreachable!(),
         }
     }
 }
 
-fn check_file(env: &mut TypeEnv, file: &Path) -> candid::Result<Option<Type>> {
+// Messages on stdin are either binary, or in hex with any number of messages per line.
+fn decode_stream<F>(mut decode: F) -> Result<(), failure::Error>
+where
+    F: FnMut(&[u8]) -> candid::Result<()>,
+{
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    if input.fill_buf()?.starts_with(b"DIDL") {
+        let mut stream = IDLStream::new(input);
+        while let Some(message) = stream.next_message()? {
+            decode(message)?;
+        }
+        return Ok(());
+    }
+    for line in input.lines() {
+        let bytes = hex::decode(line?.trim())?;
+        let mut stream = IDLStream::new(&bytes[..]);
+        while let Some(message) = stream.next_message()? {
+            decode(message)?;
+        }
+    }
+    Ok(())
+}
+
+// A signature is either a sequence of types, or a method name.
+fn signature(text: &str, defs: &Option<PathBuf>) -> TypeAnnotation {
+    let (tys, method) = match text.parse::<IDLTypes>() {
+        Ok(tys) => (Some(tys), None),
+        Err(_) => (None, Some(text.to_string())),
+    };
+    TypeAnnotation {
+        tys,
+        method,
+        defs: defs.clone(),
+    }
+}
+
+// `.proto` files are imported as Candid programs.
+fn parse_file(file: &Path) -> candid::Result<IDLProg> {
     let prog = std::fs::read_to_string(file)
         .map_err(|_| Error::msg(format!("could not read file {}", file.display())))?;
-    let ast = prog.parse::<IDLProg>()?;
+    if file.extension().and_then(|ext| ext.to_str()) == Some("proto") {
+        candid::bindings::protobuf::parse(&prog)
+    } else {
+        prog.parse::<IDLProg>()
+    }
+}
+
+fn check_file(env: &mut TypeEnv, file: &Path) -> candid::Result<Option<Type>> {
+    let ast = parse_file(file)?;
     check_prog(env, &ast)
 }
 
@@ -123,14 +282,64 @@ fn main() -> Result<(), ExitFailure> {
         }
         Command::Bind { input, target } => {
             let mut env = TypeEnv::new();
-            let actor = check_file(&mut env, &input)?;
+            let ast = parse_file(&input)?;
+            let actor = check_prog(&mut env, &ast)?;
+            let docs = DocComments::from_prog(&ast);
             let content = match target.as_str() {
-                "js" => candid::bindings::javascript::compile(&env, &actor),
-                "did" => candid::bindings::candid::compile(&env, &actor),
+                "js" => candid::bindings::javascript::compile_with_docs(&env, &actor, &docs),
+                "did" => candid::bindings::candid::compile_with_docs(&env, &actor, &docs),
+                "mo" => candid::bindings::motoko::compile(&env, &actor),
+                "py" => candid::bindings::python::compile(&env, &actor),
+                "go" => candid::bindings::go::compile(&env, &actor),
+                "proto" => candid::bindings::protobuf::compile(&env, &actor)?,
+                "jsonschema" => candid::bindings::jsonschema::compile(&env, &actor),
+                "openapi" => candid::bindings::jsonschema::compile_openapi(&env, &actor),
                 _ => unreachable!(),
             };
             println!("{}", content);
         }
+        Command::Doc {
+            input,
+            format,
+            title,
+        } => {
+            let mut env = TypeEnv::new();
+            let ast = parse_file(&input)?;
+            let actor = check_prog(&mut env, &ast)?;
+            let docs = DocComments::from_prog(&ast);
+            let title = title.unwrap_or_else(|| {
+                input
+                    .file_stem()
+                    .map_or(String::new(), |s| s.to_string_lossy().to_string())
+            });
+            let content = candid::bindings::doc::compile(&env, &actor, &docs, &title, format)?;
+            print!("{}", content);
+        }
+        Command::Fmt {
+            inputs,
+            check,
+            width,
+        } => {
+            let mut unformatted = Vec::new();
+            for input in inputs.iter() {
+                let content = std::fs::read_to_string(input)
+                    .map_err(|_| Error::msg(format!("could not read file {}", input.display())))?;
+                let formatted = candid::parser::format::format(&content, width)
+                    .map_err(|e| Error::msg(format!("{}: {}", input.display(), e)))?;
+                if formatted == content {
+                    continue;
+                }
+                if check {
+                    unformatted.push(input.display().to_string());
+                } else {
+                    std::fs::write(input, formatted)?;
+                }
+            }
+            if !unformatted.is_empty() {
+                let msg = format!("not formatted: {}", unformatted.join(", "));
+                return Err(Error::msg(msg).into());
+            }
+        }
         Command::Test { input, target } => {
             let test = std::fs::read_to_string(&input)
                 .map_err(|_| Error::msg(format!("could not read file {}", input.display())))?;
@@ -148,10 +357,19 @@ fn main() -> Result<(), ExitFailure> {
         Command::Encode {
             args,
             pretty,
+            narrow,
             annotate,
         } => {
+            let (args, annotated) = args;
             let bytes = if annotate.is_empty() {
-                args.to_bytes()?
+                // Arguments without annotations get their inferred types
+                let types = args
+                    .args
+                    .iter()
+                    .zip(annotated)
+                    .map(|(v, t)| t.map_or_else(|| infer_type(v, narrow), Ok))
+                    .collect::<candid::Result<Vec<_>>>()?;
+                args.to_bytes_with_types(&TypeEnv::new(), &types)?
             } else {
                 let (env, types) = annotate.get_types(Mode::Encode)?;
                 args.to_bytes_with_types(&env, &types)?
@@ -163,39 +381,117 @@ fn main() -> Result<(), ExitFailure> {
             };
             println!("{}", hex);
         }
-        Command::Decode { blob, annotate } => {
-            let bytes = hex::decode(&blob)?;
-            let value = if annotate.is_empty() {
-                IDLArgs::from_bytes(&bytes)?
+        Command::Decode {
+            blob,
+            annotate,
+            select,
+            stream,
+        } => {
+            let types = if annotate.is_empty() {
+                None
             } else {
-                let (env, types) = annotate.get_types(Mode::Decode)?;
-                IDLArgs::from_bytes_with_types(&bytes, &env, &types)?
+                Some(annotate.get_types(Mode::Decode)?)
             };
-            println!("{}", value);
+            let decode = |bytes: &[u8]| -> candid::Result<()> {
+                let value = match types {
+                    None => IDLArgs::from_bytes(bytes)?,
+                    Some((ref env, ref types)) => {
+                        IDLArgs::from_bytes_with_types(bytes, env, types)?
+                    }
+                };
+                match select {
+                    Some(ref path) => {
+                        for v in value.select(path)? {
+                            println!("{}", v);
+                        }
+                    }
+                    None => println!("{}", value),
+                }
+                Ok(())
+            };
+            if stream {
+                decode_stream(decode)?;
+            } else {
+                // The blob is required without --stream
+                decode(&hex::decode(blob.unwrap_or_default())?)?;
+            }
+        }
+        Command::Convert {
+            blob,
+            from,
+            to,
+            results,
+            defs,
+        } => {
+            let mut env = TypeEnv::new();
+            let actor = match defs {
+                Some(ref file) => check_file(&mut env, file)?,
+                None => None,
+            };
+            let mode = || if results { Mode::Decode } else { Mode::Encode };
+            let from = signature(&from, &defs).get_types_in(&env, &actor, mode())?;
+            let to = signature(&to, &defs).get_types_in(&env, &actor, mode())?;
+            let bytes = hex::decode(&blob)?;
+            let args = IDLArgs::from_bytes_with_types(&bytes, &env, &from)?;
+            let args = args.coerce_types(&env, &from, &to)?;
+            println!("{}", hex::encode(args.to_bytes_with_types(&env, &to)?));
         }
         Command::Diff {
             values1,
             values2,
             annotate,
         } => {
-            let (vs1, vs2) = if annotate.is_empty() {
-                (values1.args, values2.args)
+            let (vs1, vs2, env, types) = if annotate.is_empty() {
+                (values1.args, values2.args, TypeEnv::new(), Vec::new())
             } else {
                 // Either we assume the types are in decode mode, or forbid the use of --method in diff
                 let (env, types) = annotate.get_types(Mode::Decode)?;
                 (
                     values1.annotate_types(true, &env, &types)?.args,
                     values2.annotate_types(true, &env, &types)?.args,
+                    env,
+                    types,
                 )
             };
             if vs1.len() != vs2.len() {
                 return Err(Error::msg("value length mismatch").into());
             }
-            for (v1, v2) in vs1.iter().zip(vs2.iter()) {
-                let edit = candiff::value_diff(&v1, &v2, &None);
+            for (i, (v1, v2)) in vs1.iter().zip(vs2.iter()).enumerate() {
+                let edit = candiff::value_diff_with_env(v1, v2, &types.get(i).cloned(), &env);
                 println!("{}", candiff::pretty::value_edit(&edit).pretty(80));
             }
         }
+        Command::DiffTypes { old, new, json } => {
+            let mut old_env = TypeEnv::new();
+            let old_actor = check_file(&mut old_env, &old)?;
+            let mut new_env = TypeEnv::new();
+            let new_actor = check_file(&mut new_env, &new)?;
+            let changes = candiff::types::actor_diff(&old_env, &old_actor, &new_env, &new_actor)?;
+            if json {
+                println!("{}", candiff::types::to_json(&changes));
+            } else {
+                print!("{}", candiff::types::pretty(&changes));
+            }
+        }
+        Command::Random {
+            annotate,
+            depth,
+            size,
+            seed,
+        } => {
+            use candid::parser::random::{random_args, GenConfig};
+            use rand::{rngs::StdRng, SeedableRng};
+            let (env, types) = annotate.get_types(Mode::Encode)?;
+            let mut rng = match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+            let config = GenConfig::new().with_depth(depth).with_size(size);
+            let args = random_args(&mut rng, &env, &types, &config)?;
+            println!("{}", args);
+        }
+        Command::Repl { defs } => repl::run(&defs)?,
+        Command::Mock { defs, port, seed } => mock::serve(&defs, port, seed)?,
     };
     Ok(())
 }
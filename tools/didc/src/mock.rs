@@ -0,0 +1,83 @@
+//! Local HTTP endpoint for `didc mock`.
+//!
+//! `POST /<method>` with the encoded arguments as body replies with the encoded results of
+//! the mock service, or with status 400 and the error message.
+
+use crate::check_file;
+use candid::{mock::MockService, Error, TypeEnv};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+
+pub fn serve(defs: &Path, port: u16, seed: Option<u64>) -> Result<(), failure::Error> {
+    let mut env = TypeEnv::new();
+    let actor = check_file(&mut env, defs)?
+        .ok_or_else(|| Error::msg(format!("no service in {}", defs.display())))?;
+    let mut mock = MockService::new(env, actor)?;
+    if let Some(seed) = seed {
+        mock = mock.with_seed(seed);
+    }
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    eprintln!("Serving mock service at http://{}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        // A broken connection only fails its own request
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = handle(&mut mock, stream) {
+            eprintln!("Error: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn handle(mock: &mut MockService, mut stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut request = line.split_whitespace();
+    let verb = request.next().unwrap_or_default();
+    let method = request.next().unwrap_or_default().trim_start_matches('/');
+    let mut len = 0u64;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                len = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    // Content-Length is not trusted for the allocation, the body grows as it is read
+    let mut body = Vec::new();
+    reader.take(len).read_to_end(&mut body)?;
+    if body.len() as u64 != len {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    let (status, reply) = if verb != "POST" {
+        ("405 Method Not Allowed", b"expects POST /<method>".to_vec())
+    } else {
+        match mock.call(method, &body) {
+            Ok(reply) => {
+                eprintln!("{}{}", method, mock.calls().last().unwrap().args);
+                ("200 OK", reply)
+            }
+            Err(e) => ("400 Bad Request", e.to_string().into_bytes()),
+        }
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reply.len()
+    )?;
+    stream.write_all(&reply)
+}
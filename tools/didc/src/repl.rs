@@ -0,0 +1,203 @@
+//! Interactive shell for `didc repl`, which keeps the service definitions loaded between commands.
+
+use crate::{check_file, Mode, TypeAnnotation};
+use candid::parser::random::{random_args, GenConfig};
+use candid::parser::types::IDLType;
+use candid::types::subtype::{subtype, Gamma};
+use candid::{idl_hash, types::Type, Error, IDLArgs, TypeEnv};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor};
+use std::path::{Path, PathBuf};
+
+const HELP: &str = "\
+encode <method> (<args>)   Encode the arguments of a method
+decode <method> <hex>      Decode the results of a method
+random <method>            Generate random arguments for a method
+type <name>                Show a type definition or a method type
+hash <name>                Show the hash of a field name
+subtype <type> <type>      Check if the first type is a subtype of the second one
+help                       Show this message
+exit                       Leave the shell";
+
+const COMMANDS: &[&str] = &[
+    "encode", "decode", "random", "type", "hash", "subtype", "help", "exit",
+];
+
+struct Repl {
+    env: TypeEnv,
+    actor: Option<Type>,
+}
+
+impl Repl {
+    fn methods(&self) -> Vec<String> {
+        self.actor
+            .as_ref()
+            .and_then(|actor| self.env.as_service(actor).ok())
+            .map(|ms| ms.iter().map(|(id, _)| id.clone()).collect())
+            .unwrap_or_default()
+    }
+    fn types(&self, method: &str, mode: Mode) -> candid::Result<Vec<Type>> {
+        let annotate = TypeAnnotation {
+            tys: None,
+            method: Some(method.to_string()),
+            defs: None,
+        };
+        annotate.get_types_in(&self.env, &self.actor, mode)
+    }
+    fn run(&self, line: &str) -> candid::Result<String> {
+        let (cmd, rest) = split_word(line);
+        match cmd {
+            "encode" => {
+                let (method, args) = split_word(rest);
+                let types = self.types(method, Mode::Encode)?;
+                let args = args.parse::<IDLArgs>()?;
+                let bytes = args.to_bytes_with_types(&self.env, &types)?;
+                Ok(hex::encode(&bytes))
+            }
+            "decode" => {
+                let (method, blob) = split_word(rest);
+                let types = self.types(method, Mode::Decode)?;
+                let bytes = hex::decode(blob).map_err(Error::msg)?;
+                let args = IDLArgs::from_bytes_with_types(&bytes, &self.env, &types)?;
+                Ok(args.to_string())
+            }
+            "random" => {
+                let types = self.types(rest, Mode::Encode)?;
+                let mut rng = rand::thread_rng();
+                let args = random_args(&mut rng, &self.env, &types, &GenConfig::new())?;
+                Ok(args.to_string())
+            }
+            "type" => match self.env.find_type(rest) {
+                Ok(ty) => Ok(format!("type {} = {}", rest, ty)),
+                Err(_) => {
+                    let func = self
+                        .actor
+                        .as_ref()
+                        .and_then(|actor| self.env.get_method(actor, rest).ok())
+                        .ok_or_else(|| Error::msg(format!("unbound type or method {}", rest)))?;
+                    Ok(format!("{} : {}", rest, Type::Func(func.clone())))
+                }
+            },
+            "hash" => Ok(idl_hash(rest).to_string()),
+            "subtype" => {
+                let (t1, t2) = self.parse_types(rest)?;
+                Ok(match subtype(&mut Gamma::new(), &self.env, &t1, &t2) {
+                    Ok(()) => format!("{} <: {}", t1, t2),
+                    Err(e) => format!("not a subtype: {}", e),
+                })
+            }
+            "help" => Ok(HELP.to_string()),
+            _ => Err(Error::msg(format!("unknown command {}, try help", cmd))),
+        }
+    }
+    // Splits the text between the two types, at the first space that makes both sides parse.
+    fn parse_types(&self, text: &str) -> candid::Result<(Type, Type)> {
+        for (i, _) in text.char_indices().filter(|(_, c)| c.is_whitespace()) {
+            if let (Ok(t1), Ok(t2)) = (text[..i].parse::<IDLType>(), text[i..].parse::<IDLType>()) {
+                return Ok((self.env.ast_to_type(&t1)?, self.env.ast_to_type(&t2)?));
+            }
+        }
+        Err(Error::msg("expects two types"))
+    }
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.find(char::is_whitespace) {
+        Some(i) => (&text[..i], text[i..].trim_start()),
+        None => (text, ""),
+    }
+}
+
+/// Completes command names, then method or type names depending on the command.
+struct Helper {
+    methods: Vec<String>,
+    types: Vec<String>,
+}
+
+impl Completer for Helper {
+    type Candidate = String;
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let words: Vec<_> = line[..start].split_whitespace().collect();
+        let names: Vec<&str> = match words.as_slice() {
+            [] => COMMANDS.to_vec(),
+            ["encode"] | ["decode"] | ["random"] => {
+                self.methods.iter().map(|m| m.as_str()).collect()
+            }
+            ["type"] => self
+                .types
+                .iter()
+                .chain(self.methods.iter())
+                .map(|t| t.as_str())
+                .collect(),
+            ["subtype", ..] => self.types.iter().map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        let word = &line[start..];
+        let candidates = names
+            .into_iter()
+            .filter(|name| name.starts_with(word))
+            .map(|name| name.to_string())
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for Helper {
+    type Hint = String;
+}
+impl Highlighter for Helper {}
+impl Validator for Helper {}
+impl rustyline::Helper for Helper {}
+
+pub fn run(defs: &Path) -> Result<(), failure::Error> {
+    let mut env = TypeEnv::new();
+    let actor = check_file(&mut env, defs)?;
+    let repl = Repl { env, actor };
+    let helper = Helper {
+        methods: repl.methods(),
+        types: repl.env.0.keys().cloned().collect(),
+    };
+    let mut rl = Editor::<Helper>::new()?;
+    rl.set_helper(Some(helper));
+    let history = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".didc_history"));
+    if let Some(ref history) = history {
+        // There is no history file on the first run
+        let _ = rl.load_history(history);
+    }
+    loop {
+        let line = match rl.readline("didc> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        rl.add_history_entry(line);
+        if line == "exit" {
+            break;
+        }
+        match repl.run(line) {
+            Ok(res) => println!("{}", res),
+            Err(e) => println!("Error: {}", e),
+        }
+    }
+    if let Some(ref history) = history {
+        rl.save_history(history)?;
+    }
+    Ok(())
+}